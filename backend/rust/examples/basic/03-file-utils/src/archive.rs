@@ -0,0 +1,567 @@
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+use zip::result::ZipError;
+use zip::write::SimpleFileOptions;
+
+use crate::error::{FileUtilsError, PathContext, Result};
+use crate::records::{FileKind, FileRecord};
+use crate::sandbox::Sandbox;
+use crate::utils;
+
+/// 文件类型位，与 st_mode 一致，用于在列表中显示归档条目的类型
+const S_IFDIR: u32 = 0o040000;
+const S_IFREG: u32 = 0o100000;
+const S_IFLNK: u32 = 0o120000;
+
+/// 支持的归档格式，按文件扩展名识别
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArchiveFormat {
+    Tar,
+    TarGz,
+    Zip,
+}
+
+impl ArchiveFormat {
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Ok(ArchiveFormat::TarGz)
+        } else if name.ends_with(".tar") {
+            Ok(ArchiveFormat::Tar)
+        } else if name.ends_with(".zip") {
+            Ok(ArchiveFormat::Zip)
+        } else {
+            Err(FileUtilsError::InvalidInput(format!(
+                "无法识别的归档格式: {} (支持 .tar、.tar.gz、.tgz、.zip)",
+                path.display()
+            )))
+        }
+    }
+}
+
+/// 把文件或目录打包为归档，条目以 source 的名字为顶层目录；返回条目数
+///
+/// 符号链接按链接本身保存，不会跟随。
+pub fn pack(source: &Path, archive: &Path) -> Result<usize> {
+    let format = ArchiveFormat::from_path(archive)?;
+    let base = source
+        .file_name()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("archive"));
+
+    // 先收集条目再创建归档，避免把正在写入的归档本身打包进去
+    let mut entries = Vec::new();
+    collect_entries(source, &base, &mut entries)?;
+
+    let file = fs::File::create_new(archive).with_path(archive)?;
+    let result = match format {
+        ArchiveFormat::Tar => write_tar(file, &entries).map(drop),
+        ArchiveFormat::TarGz => write_tar(GzEncoder::new(file, Compression::default()), &entries)
+            .and_then(|encoder| encoder.finish().map(drop).with_path(archive)),
+        ArchiveFormat::Zip => write_zip(file, &entries, archive),
+    };
+
+    // 失败时不留下不完整的归档
+    if result.is_err() {
+        let _ = fs::remove_file(archive);
+    }
+    result.map(|_| entries.len())
+}
+
+/// 把归档解包到 dest，返回条目数
+///
+/// 每个条目都必须位于 dest 之内：名字中的 ".."、绝对路径、指向外部的链接
+/// 以及经由已解出的符号链接逃逸的路径都会被拒绝（zip-slip）。已存在的文件不会被覆盖。
+pub fn unpack(archive: &Path, dest: &Path) -> Result<usize> {
+    let format = ArchiveFormat::from_path(archive)?;
+    let file = fs::File::open(archive).with_path(archive)?;
+
+    fs::create_dir_all(dest).with_path(dest)?;
+    let sandbox = Sandbox::new(dest)?;
+
+    let mut dirs = Vec::new();
+    let count = match format {
+        ArchiveFormat::Tar => unpack_tar(tar::Archive::new(file), archive, dest, &sandbox, &mut dirs)?,
+        ArchiveFormat::TarGz => unpack_tar(tar::Archive::new(GzDecoder::new(file)), archive, dest, &sandbox, &mut dirs)?,
+        ArchiveFormat::Zip => unpack_zip(file, archive, dest, &sandbox, &mut dirs)?,
+    };
+
+    // 目录的权限和时间最后设置，并且先子后父：否则写入子条目会改变父目录的 mtime，
+    // 只读目录也会挡住后续条目
+    for dir in dirs.iter().rev() {
+        restore_metadata(&dir.path, dir.mode, dir.mtime)?;
+    }
+
+    Ok(count)
+}
+
+/// 列出归档中的条目，name 为条目在归档内的完整路径
+pub fn list(archive: &Path) -> Result<Vec<FileRecord>> {
+    let format = ArchiveFormat::from_path(archive)?;
+    let file = fs::File::open(archive).with_path(archive)?;
+
+    match format {
+        ArchiveFormat::Tar => list_tar(tar::Archive::new(file), archive),
+        ArchiveFormat::TarGz => list_tar(tar::Archive::new(GzDecoder::new(file)), archive),
+        ArchiveFormat::Zip => list_zip(file, archive),
+    }
+}
+
+/// 待打包的条目：磁盘上的路径和归档内的名字
+struct PackEntry {
+    path: PathBuf,
+    name: PathBuf,
+}
+
+/// 解包后需要最后设置权限和时间的目录
+struct ExtractedDir {
+    path: PathBuf,
+    mode: Option<u32>,
+    mtime: Option<i64>,
+}
+
+fn collect_entries(path: &Path, name: &Path, entries: &mut Vec<PackEntry>) -> Result<()> {
+    let metadata = fs::symlink_metadata(path).with_path(path)?;
+    entries.push(PackEntry { path: path.to_path_buf(), name: name.to_path_buf() });
+
+    if metadata.is_dir() {
+        let mut children: Vec<_> = fs::read_dir(path)
+            .with_path(path)?
+            .map(|entry| entry.map(|e| e.file_name()))
+            .collect::<io::Result<_>>()
+            .with_path(path)?;
+        children.sort();
+
+        for child in children {
+            collect_entries(&path.join(&child), &name.join(&child), entries)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn write_tar<W: Write>(writer: W, entries: &[PackEntry]) -> Result<W> {
+    let mut builder = tar::Builder::new(writer);
+    builder.follow_symlinks(false);
+
+    for entry in entries {
+        // 目录只写入目录本身，子条目已在 entries 中
+        builder.append_path_with_name(&entry.path, &entry.name).with_path(&entry.path)?;
+    }
+
+    builder.into_inner().with_path("<tar>")
+}
+
+fn write_zip(file: fs::File, entries: &[PackEntry], archive: &Path) -> Result<()> {
+    let mut writer = zip::ZipWriter::new(file);
+
+    for entry in entries {
+        let metadata = fs::symlink_metadata(&entry.path).with_path(&entry.path)?;
+        let record = FileRecord::from_metadata(&entry.path, &metadata);
+        let name = zip_name(&entry.name);
+
+        let mut options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        if let Some(mode) = record.mode {
+            options = options.unix_permissions(mode);
+        }
+        if let Some(time) = record.mtime.and_then(zip_time) {
+            options = options.last_modified_time(time);
+        }
+
+        match record.kind {
+            FileKind::Dir => writer.add_directory(name, options).map_err(zip_error(archive))?,
+            FileKind::Symlink => {
+                let target = fs::read_link(&entry.path).with_path(&entry.path)?;
+                writer
+                    .add_symlink(name, target.to_string_lossy(), options)
+                    .map_err(zip_error(archive))?;
+            },
+            FileKind::File => {
+                writer.start_file(name, options).map_err(zip_error(archive))?;
+                let mut source = fs::File::open(&entry.path).with_path(&entry.path)?;
+                io::copy(&mut source, &mut writer).with_path(&entry.path)?;
+            },
+            // 设备文件、FIFO 等无法放进 zip
+            FileKind::Other => {},
+        }
+    }
+
+    writer.finish().map_err(zip_error(archive))?;
+    Ok(())
+}
+
+fn unpack_tar<R: Read>(
+    mut reader: tar::Archive<R>,
+    archive: &Path,
+    dest: &Path,
+    sandbox: &Sandbox,
+    dirs: &mut Vec<ExtractedDir>,
+) -> Result<usize> {
+    reader.set_preserve_permissions(true);
+    reader.set_preserve_mtime(true);
+    reader.set_overwrite(false);
+
+    let mut count = 0;
+    for entry in reader.entries().with_path(archive)? {
+        let mut entry = entry.with_path(archive)?;
+        let name = entry.path().with_path(archive)?.into_owned();
+        let entry_type = entry.header().entry_type();
+        let target = target_path(sandbox, dest, &name, entry_type.is_dir())?;
+
+        if entry_type.is_symlink() || entry_type.is_hard_link() {
+            let link = entry.link_name().with_path(archive)?.unwrap_or_default().into_owned();
+            // 硬链接的目标相对于归档根目录，符号链接的目标相对于链接所在目录
+            let base = if entry_type.is_hard_link() { Path::new("") } else { name.parent().unwrap_or(Path::new("")) };
+            check_link(dest, &name, base, &link)?;
+        }
+
+        if entry_type.is_dir() {
+            fs::create_dir_all(&target).with_path(&target)?;
+            dirs.push(ExtractedDir {
+                path: target,
+                mode: entry.header().mode().ok(),
+                mtime: entry.header().mtime().ok().map(|t| t as i64),
+            });
+        } else if !entry.unpack_in(dest).with_path(&target)? {
+            return Err(FileUtilsError::OutsideRoot { path: name, root: dest.to_path_buf() });
+        }
+
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+fn unpack_zip(
+    file: fs::File,
+    archive: &Path,
+    dest: &Path,
+    sandbox: &Sandbox,
+    dirs: &mut Vec<ExtractedDir>,
+) -> Result<usize> {
+    let mut reader = zip::ZipArchive::new(file).map_err(zip_error(archive))?;
+
+    for i in 0..reader.len() {
+        let mut entry = reader.by_index(i).map_err(zip_error(archive))?;
+        let name = PathBuf::from(entry.name());
+        let target = target_path(sandbox, dest, &name, entry.is_dir())?;
+        let mode = entry.unix_mode().map(|mode| mode & 0o7777);
+        let mtime = entry.last_modified().map(from_zip_time);
+
+        if entry.is_dir() {
+            fs::create_dir_all(&target).with_path(&target)?;
+            dirs.push(ExtractedDir { path: target, mode, mtime });
+            continue;
+        }
+
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).with_path(parent)?;
+        }
+
+        if entry.is_symlink() {
+            let mut link = String::new();
+            entry.read_to_string(&mut link).with_path(&target)?;
+            check_link(dest, &name, name.parent().unwrap_or(Path::new("")), Path::new(&link))?;
+            create_symlink(Path::new(&link), &target)?;
+        } else {
+            let mut output = fs::File::create_new(&target).with_path(&target)?;
+            io::copy(&mut entry, &mut output).with_path(&target)?;
+            drop(output);
+            restore_metadata(&target, mode, mtime)?;
+        }
+    }
+
+    Ok(reader.len())
+}
+
+fn list_tar<R: Read>(mut reader: tar::Archive<R>, archive: &Path) -> Result<Vec<FileRecord>> {
+    let mut records = Vec::new();
+
+    for entry in reader.entries().with_path(archive)? {
+        let entry = entry.with_path(archive)?;
+        let header = entry.header();
+        let entry_type = header.entry_type();
+        let path = entry.path().with_path(archive)?.into_owned();
+
+        let (kind, type_bits) = if entry_type.is_dir() {
+            (FileKind::Dir, S_IFDIR)
+        } else if entry_type.is_symlink() {
+            (FileKind::Symlink, S_IFLNK)
+        } else if entry_type.is_file() || entry_type.is_hard_link() {
+            (FileKind::File, S_IFREG)
+        } else {
+            (FileKind::Other, 0)
+        };
+
+        records.push(FileRecord {
+            name: path.display().to_string(),
+            kind,
+            size: if kind == FileKind::File { header.size().unwrap_or(0) } else { 0 },
+            mtime: header.mtime().ok().map(|t| t as i64),
+            mode: header.mode().ok().map(|mode| type_bits | (mode & 0o7777)),
+            path,
+            owner: header.username().ok().flatten().map(str::to_string),
+            group: header.groupname().ok().flatten().map(str::to_string),
+            target: entry.link_name().ok().flatten().map(|link| link.into_owned()),
+        });
+    }
+
+    Ok(records)
+}
+
+fn list_zip(file: fs::File, archive: &Path) -> Result<Vec<FileRecord>> {
+    let mut reader = zip::ZipArchive::new(file).map_err(zip_error(archive))?;
+    let mut records = Vec::new();
+
+    for i in 0..reader.len() {
+        let mut entry = reader.by_index(i).map_err(zip_error(archive))?;
+        let name = entry.name().trim_end_matches('/').to_string();
+
+        let kind = if entry.is_dir() {
+            FileKind::Dir
+        } else if entry.is_symlink() {
+            FileKind::Symlink
+        } else {
+            FileKind::File
+        };
+
+        let target = if kind == FileKind::Symlink {
+            let mut link = String::new();
+            entry.read_to_string(&mut link).with_path(archive)?;
+            Some(PathBuf::from(link))
+        } else {
+            None
+        };
+
+        records.push(FileRecord {
+            path: PathBuf::from(&name),
+            name,
+            kind,
+            size: if kind == FileKind::File { entry.size() } else { 0 },
+            mtime: entry.last_modified().map(from_zip_time),
+            mode: entry.unix_mode(),
+            owner: None,
+            group: None,
+            target,
+        });
+    }
+
+    Ok(records)
+}
+
+/// 条目解包后的路径；名字不安全、经由符号链接逃逸或会覆盖已有文件时报错
+fn target_path(sandbox: &Sandbox, dest: &Path, name: &Path, is_dir: bool) -> Result<PathBuf> {
+    if !utils::is_safe_path(name) {
+        return Err(FileUtilsError::OutsideRoot { path: name.to_path_buf(), root: dest.to_path_buf() });
+    }
+
+    let target = dest.join(name);
+    sandbox.check(&target)?;
+
+    // 已存在的目录可以合并，其余情况一律不覆盖
+    if let Ok(metadata) = fs::symlink_metadata(&target) {
+        if !(is_dir && metadata.is_dir()) {
+            return Err(FileUtilsError::AlreadyExists { path: target });
+        }
+    }
+
+    Ok(target)
+}
+
+/// 拒绝指向解包目录之外的链接，否则后续条目或使用者可以经由它读写外部文件
+fn check_link(dest: &Path, name: &Path, base: &Path, link: &Path) -> Result<()> {
+    if link.is_absolute() || !utils::is_safe_path(base.join(link)) {
+        return Err(FileUtilsError::OutsideRoot {
+            path: PathBuf::from(format!("{} -> {}", name.display(), link.display())),
+            root: dest.to_path_buf(),
+        });
+    }
+    Ok(())
+}
+
+/// 设置解包出的文件或目录的权限和修改时间
+fn restore_metadata(path: &Path, mode: Option<u32>, mtime: Option<i64>) -> Result<()> {
+    if let Some(mtime) = mtime.filter(|t| *t >= 0) {
+        let file = fs::File::open(path).with_path(path)?;
+        file.set_modified(UNIX_EPOCH + Duration::from_secs(mtime as u64)).with_path(path)?;
+    }
+
+    #[cfg(unix)]
+    if let Some(mode) = mode {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o7777)).with_path(path)?;
+    }
+    #[cfg(not(unix))]
+    let _ = mode;
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn create_symlink(link: &Path, target: &Path) -> Result<()> {
+    std::os::unix::fs::symlink(link, target).with_path(target)
+}
+
+/// 非 Unix 平台把链接目标写成普通文件
+#[cfg(not(unix))]
+fn create_symlink(link: &Path, target: &Path) -> Result<()> {
+    fs::write(target, link.to_string_lossy().as_bytes()).with_path(target)
+}
+
+/// zip 条目名总是使用 "/" 分隔
+fn zip_name(name: &Path) -> String {
+    name.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// zip 使用本地时间、精度为 2 秒，且不能早于 1980 年
+fn zip_time(timestamp: i64) -> Option<zip::DateTime> {
+    let local = timestamp + utils::local_offset(timestamp);
+    let (year, month, day) = utils::civil_from_days(local.div_euclid(86_400));
+    let seconds = local.rem_euclid(86_400);
+
+    zip::DateTime::from_date_and_time(
+        u16::try_from(year).ok()?,
+        month as u8,
+        day as u8,
+        (seconds / 3600) as u8,
+        (seconds % 3600 / 60) as u8,
+        (seconds % 60) as u8,
+    )
+    .ok()
+}
+
+fn from_zip_time(time: zip::DateTime) -> i64 {
+    let days = utils::days_from_civil(time.year() as i64, time.month() as u32, time.day() as u32);
+    let local = days * 86_400 + time.hour() as i64 * 3600 + time.minute() as i64 * 60 + time.second() as i64;
+    local - utils::local_offset(local)
+}
+
+fn zip_error(archive: &Path) -> impl Fn(ZipError) -> FileUtilsError + '_ {
+    move |error| match error {
+        ZipError::Io(e) => FileUtilsError::from_io(e, archive),
+        other => FileUtilsError::Archive { path: archive.to_path_buf(), message: other.to_string() },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// 2 秒对齐的时间戳，zip 能精确保存
+    const MTIME: u64 = 1_600_000_000;
+
+    fn set_mtime(path: &Path) {
+        let file = fs::File::open(path).unwrap();
+        file.set_modified(UNIX_EPOCH + Duration::from_secs(MTIME)).unwrap();
+    }
+
+    fn mtime_of(path: &Path) -> i64 {
+        crate::records::to_timestamp(fs::metadata(path).unwrap().modified().unwrap())
+    }
+
+    fn setup() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        let project = dir.path().join("project");
+        fs::create_dir_all(project.join("src")).unwrap();
+        fs::write(project.join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(project.join("run.sh"), "#!/bin/sh").unwrap();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(project.join("run.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+            std::os::unix::fs::symlink("src/main.rs", project.join("link")).unwrap();
+        }
+
+        set_mtime(&project.join("src/main.rs"));
+        set_mtime(&project.join("src"));
+        dir
+    }
+
+    fn round_trip(archive_name: &str) {
+        let dir = setup();
+        let archive = dir.path().join(archive_name);
+        let out = dir.path().join("out");
+
+        let packed = pack(&dir.path().join("project"), &archive).unwrap();
+        let names: Vec<String> = list(&archive).unwrap().into_iter().map(|r| r.name).collect();
+        assert_eq!(names.len(), packed);
+        assert!(names.iter().any(|n| n.trim_end_matches('/') == "project/src/main.rs"));
+
+        assert_eq!(unpack(&archive, &out).unwrap(), packed);
+        let project = out.join("project");
+        assert_eq!(fs::read_to_string(project.join("src/main.rs")).unwrap(), "fn main() {}");
+        assert_eq!(mtime_of(&project.join("src/main.rs")), MTIME as i64);
+        assert_eq!(mtime_of(&project.join("src")), MTIME as i64);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(project.join("run.sh")).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o755);
+            assert_eq!(fs::read_link(project.join("link")).unwrap(), Path::new("src/main.rs"));
+        }
+
+        // 再次解包不会覆盖已有文件
+        assert!(matches!(unpack(&archive, &out), Err(FileUtilsError::AlreadyExists { .. })));
+    }
+
+    #[test]
+    fn test_tar_gz_round_trip() {
+        round_trip("project.tar.gz");
+    }
+
+    #[test]
+    fn test_zip_round_trip() {
+        round_trip("project.zip");
+    }
+
+    #[test]
+    fn test_format_from_extension() {
+        assert_eq!(ArchiveFormat::from_path(Path::new("a.TGZ")).unwrap(), ArchiveFormat::TarGz);
+        assert_eq!(ArchiveFormat::from_path(Path::new("a.tar")).unwrap(), ArchiveFormat::Tar);
+        assert!(matches!(ArchiveFormat::from_path(Path::new("a.rar")), Err(FileUtilsError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_zip_slip_rejected() {
+        let dir = TempDir::new().unwrap();
+        let out = dir.path().join("out");
+
+        // 名字中含 ".." 的条目
+        let archive = dir.path().join("evil.zip");
+        let mut writer = zip::ZipWriter::new(fs::File::create(&archive).unwrap());
+        writer.start_file("../evil.txt", SimpleFileOptions::default()).unwrap();
+        writer.write_all(b"pwned").unwrap();
+        writer.finish().unwrap();
+
+        assert!(matches!(unpack(&archive, &out), Err(FileUtilsError::OutsideRoot { .. })));
+        assert!(!dir.path().join("evil.txt").exists());
+
+        // 先放一个指向外部的链接，再经由它写文件
+        let archive = dir.path().join("link.tar");
+        let mut builder = tar::Builder::new(fs::File::create(&archive).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        builder.append_link(&mut header, "escape", "..").unwrap();
+        builder.finish().unwrap();
+        drop(builder);
+
+        assert!(matches!(unpack(&archive, &out), Err(FileUtilsError::OutsideRoot { .. })));
+        assert!(fs::symlink_metadata(out.join("escape")).is_err());
+    }
+}
@@ -0,0 +1,125 @@
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::error::{PathContext, Result};
+
+/// 支持的摘要算法
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum HashAlgorithm {
+    #[default]
+    Sha256,
+    Blake3,
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "sha256" | "sha-256" => Ok(HashAlgorithm::Sha256),
+            "blake3" | "b3" => Ok(HashAlgorithm::Blake3),
+            other => Err(format!("不支持的摘要算法: {} (可选: sha256, blake3)", other)),
+        }
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HashAlgorithm::Sha256 => write!(f, "SHA-256"),
+            HashAlgorithm::Blake3 => write!(f, "BLAKE3"),
+        }
+    }
+}
+
+/// 计算文件内容的摘要，返回小写十六进制字符串
+pub fn hash_file(path: &Path, algorithm: HashAlgorithm) -> Result<String> {
+    let file = fs::File::open(path).with_path(path)?;
+    hash_reader(file, algorithm).with_path(path)
+}
+
+/// 以流的方式计算摘要，避免把大文件整个读入内存
+pub fn hash_reader<R: Read>(mut reader: R, algorithm: HashAlgorithm) -> io::Result<String> {
+    match algorithm {
+        HashAlgorithm::Sha256 => {
+            let mut hasher = Sha256::new();
+            io::copy(&mut reader, &mut hasher)?;
+            Ok(to_hex(&hasher.finalize()))
+        },
+        HashAlgorithm::Blake3 => {
+            let mut hasher = blake3::Hasher::new();
+            io::copy(&mut reader, &mut hasher)?;
+            Ok(hasher.finalize().to_hex().to_string())
+        },
+    }
+}
+
+/// 逐字节比较两个文件的内容
+pub fn same_content(a: &Path, b: &Path) -> Result<bool> {
+    if fs::metadata(a).with_path(a)?.len() != fs::metadata(b).with_path(b)?.len() {
+        return Ok(false);
+    }
+
+    let mut file_a = io::BufReader::new(fs::File::open(a).with_path(a)?);
+    let mut file_b = io::BufReader::new(fs::File::open(b).with_path(b)?);
+    let mut buf_a = [0u8; 8192];
+    let mut buf_b = [0u8; 8192];
+
+    loop {
+        let read = file_a.read(&mut buf_a).with_path(a)?;
+        if read == 0 {
+            return Ok(true);
+        }
+        file_b.read_exact(&mut buf_b[..read]).with_path(b)?;
+        if buf_a[..read] != buf_b[..read] {
+            return Ok(false);
+        }
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_known_digests() {
+        assert_eq!(
+            hash_reader(&b"abc"[..], HashAlgorithm::Sha256).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            hash_reader(&b"abc"[..], HashAlgorithm::Blake3).unwrap(),
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+        );
+    }
+
+    #[test]
+    fn test_algorithm_from_str() {
+        assert_eq!("SHA256".parse::<HashAlgorithm>(), Ok(HashAlgorithm::Sha256));
+        assert_eq!("blake3".parse::<HashAlgorithm>(), Ok(HashAlgorithm::Blake3));
+        assert!("md5".parse::<HashAlgorithm>().is_err());
+    }
+
+    #[test]
+    fn test_same_content() {
+        let dir = TempDir::new().unwrap();
+        let a = dir.path().join("a");
+        let b = dir.path().join("b");
+        let c = dir.path().join("c");
+        fs::write(&a, "hello world").unwrap();
+        fs::write(&b, "hello world").unwrap();
+        fs::write(&c, "hello there").unwrap();
+
+        assert!(same_content(&a, &b).unwrap());
+        assert!(!same_content(&a, &c).unwrap());
+    }
+}
@@ -0,0 +1,392 @@
+use regex::Regex;
+use serde::Serialize;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use crate::error::{FileUtilsError, PathContext, Result};
+use crate::interrupt;
+
+/// 判断二进制文件时检查的字节数，与 GNU grep 的做法相同：出现 NUL 字节即视为二进制
+const BINARY_PROBE: usize = 8000;
+
+/// 每行十六进制转储显示的字节数
+const HEX_WIDTH: usize = 16;
+
+/// view 显示的范围；十六进制模式下 Head/Tail 按字节计数
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ViewRange {
+    #[default]
+    All,
+    Head(usize),
+    Tail(usize),
+    /// 从 1 开始的行号范围，包含两端，结束为 None 时到文件末尾
+    Lines(usize, Option<usize>),
+}
+
+impl ViewRange {
+    /// 解析 `--lines` 的值："10:20"、"10:"、":20" 或单个行号 "15"
+    pub fn parse_lines(text: &str) -> Result<Self> {
+        let invalid = || FileUtilsError::InvalidInput(format!("无效的行号范围: {} (格式如 10:20、10:、:20)", text));
+        let number = |s: &str| s.trim().parse::<usize>().ok().filter(|n| *n > 0).ok_or_else(invalid);
+
+        let (start, end) = match text.split_once(':') {
+            Some((start, end)) => (
+                if start.trim().is_empty() { 1 } else { number(start)? },
+                if end.trim().is_empty() { None } else { Some(number(end)?) },
+            ),
+            None => {
+                let line = number(text)?;
+                (line, Some(line))
+            },
+        };
+
+        if end.is_some_and(|end| end < start) {
+            return Err(invalid());
+        }
+        Ok(ViewRange::Lines(start, end))
+    }
+}
+
+/// view 的选项，对应 `--head`、`--tail`、`--lines`、`--hex`、`-f`
+#[derive(Debug, Clone, Default)]
+pub struct ViewOptions {
+    pub range: ViewRange,
+    pub hex: bool,
+    pub follow: bool,
+}
+
+/// 文件开头是否含有 NUL 字节
+pub fn is_binary(path: &Path) -> Result<bool> {
+    let mut probe = Vec::with_capacity(BINARY_PROBE);
+    fs::File::open(path)
+        .and_then(|file| file.take(BINARY_PROBE as u64).read_to_end(&mut probe))
+        .with_path(path)?;
+    Ok(is_binary_bytes(&probe))
+}
+
+fn is_binary_bytes(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(BINARY_PROBE)].contains(&0)
+}
+
+/// 按行输出文本文件的指定范围
+pub fn write_lines<W: Write>(path: &Path, range: ViewRange, out: &mut W) -> Result<()> {
+    let mut file = fs::File::open(path).with_path(path)?;
+
+    let (skip, take) = match range {
+        ViewRange::All => (0, None),
+        ViewRange::Head(count) => (0, Some(count)),
+        ViewRange::Lines(start, end) => (start - 1, end.map(|end| end + 1 - start)),
+        ViewRange::Tail(count) => {
+            let start = tail_offset(&mut file, count).with_path(path)?;
+            file.seek(SeekFrom::Start(start)).with_path(path)?;
+            (0, None)
+        },
+    };
+
+    let mut reader = BufReader::new(file);
+    let mut line = Vec::new();
+    let mut index = 0;
+
+    while take.is_none_or(|take| index < skip + take) {
+        line.clear();
+        if reader.read_until(b'\n', &mut line).with_path(path)? == 0 {
+            break;
+        }
+        if index >= skip {
+            out.write_all(&line).with_path("<stdout>")?;
+        }
+        index += 1;
+    }
+
+    Ok(())
+}
+
+/// 最后 count 行开始的字节偏移，从文件末尾按块向前查找换行符
+fn tail_offset(file: &mut fs::File, count: usize) -> io::Result<u64> {
+    const CHUNK: u64 = 8192;
+    let len = file.seek(SeekFrom::End(0))?;
+    let mut position = len;
+    let mut newlines = 0;
+    let mut buffer = vec![0; CHUNK as usize];
+
+    if count == 0 {
+        return Ok(len);
+    }
+
+    while position > 0 {
+        let size = CHUNK.min(position);
+        position -= size;
+        file.seek(SeekFrom::Start(position))?;
+        file.read_exact(&mut buffer[..size as usize])?;
+
+        for i in (0..size as usize).rev() {
+            // 文件末尾的换行符属于最后一行，不算作分隔
+            if buffer[i] == b'\n' && position + i as u64 + 1 != len {
+                newlines += 1;
+                if newlines == count {
+                    return Ok(position + i as u64 + 1);
+                }
+            }
+        }
+    }
+
+    Ok(0)
+}
+
+/// 以 `hexdump -C` 的格式输出文件内容，Head/Tail 按字节计数
+pub fn write_hex<W: Write>(path: &Path, range: ViewRange, out: &mut W) -> Result<()> {
+    let mut file = fs::File::open(path).with_path(path)?;
+    let len = file.metadata().with_path(path)?.len();
+
+    let (start, limit) = match range {
+        ViewRange::All => (0, len),
+        ViewRange::Head(count) => (0, (count as u64).min(len)),
+        ViewRange::Tail(count) => (len.saturating_sub(count as u64), (count as u64).min(len)),
+        ViewRange::Lines(..) => {
+            return Err(FileUtilsError::InvalidInput("十六进制模式不支持 --lines，请使用 --head/--tail 指定字节数".to_string()));
+        },
+    };
+
+    file.seek(SeekFrom::Start(start)).with_path(path)?;
+    let mut reader = BufReader::new(file.take(limit));
+    let mut chunk = [0; HEX_WIDTH];
+    let mut offset = start;
+
+    loop {
+        let size = read_chunk(&mut reader, &mut chunk).with_path(path)?;
+        if size == 0 {
+            break;
+        }
+        writeln!(out, "{}", hex_line(offset, &chunk[..size])).with_path("<stdout>")?;
+        offset += size as u64;
+    }
+    writeln!(out, "{:08x}", offset).with_path("<stdout>")?;
+
+    Ok(())
+}
+
+/// 尽量读满一行的字节，只有到达末尾时才会少于 buffer 的长度
+fn read_chunk<R: Read>(reader: &mut R, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+/// "00000010  48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 0a           |Hello, world.|"
+fn hex_line(offset: u64, bytes: &[u8]) -> String {
+    let mut hex = String::new();
+    for i in 0..HEX_WIDTH {
+        match bytes.get(i) {
+            Some(byte) => hex.push_str(&format!("{:02x} ", byte)),
+            None => hex.push_str("   "),
+        }
+        if i == HEX_WIDTH / 2 - 1 {
+            hex.push(' ');
+        }
+    }
+
+    let ascii: String = bytes
+        .iter()
+        .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+        .collect();
+
+    format!("{:08x}  {} |{}|", offset, hex, ascii)
+}
+
+/// 像 `tail -f` 一样持续输出追加的内容；文件被截断时从头开始。出错或在 shell 中按下 Ctrl-C 时返回
+pub fn follow<W: Write>(path: &Path, mut position: u64, out: &mut W) -> Result<()> {
+    while !interrupt::requested() {
+        position = copy_appended(path, position, out)?;
+        thread::sleep(Duration::from_millis(250));
+    }
+    Ok(())
+}
+
+/// 输出 position 之后新增的内容，返回新的位置
+fn copy_appended<W: Write>(path: &Path, position: u64, out: &mut W) -> Result<u64> {
+    let mut file = fs::File::open(path).with_path(path)?;
+    let len = file.metadata().with_path(path)?.len();
+
+    let position = if len < position {
+        eprintln!("⚠️  {} 被截断，从头开始显示", path.display());
+        0
+    } else {
+        position
+    };
+
+    if len > position {
+        file.seek(SeekFrom::Start(position)).with_path(path)?;
+        io::copy(&mut file.take(len - position), out).with_path("<stdout>")?;
+        out.flush().with_path("<stdout>")?;
+    }
+
+    Ok(len)
+}
+
+/// grep 输出中的一行；is_match 为 false 的是上下文行
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GrepLine {
+    pub line: usize,
+    #[serde(rename = "match")]
+    pub is_match: bool,
+    pub text: String,
+}
+
+/// 一个文件中的搜索结果
+#[derive(Debug, Clone, Serialize)]
+pub struct GrepResult {
+    pub path: PathBuf,
+    /// 匹配的行数；二进制文件为 1 表示匹配
+    pub matches: usize,
+    /// 二进制文件只报告是否匹配，不输出内容
+    pub binary: bool,
+    /// 按行号排列，上下文重叠的部分已合并
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub lines: Vec<GrepLine>,
+}
+
+/// 在一个文件中搜索，没有匹配时返回 None
+pub fn grep_file(path: &Path, regex: &Regex, context: usize) -> Result<Option<GrepResult>> {
+    let bytes = fs::read(path).with_path(path)?;
+
+    if is_binary_bytes(&bytes) {
+        let matched = regex.is_match(&String::from_utf8_lossy(&bytes));
+        return Ok(matched.then(|| GrepResult { path: path.to_path_buf(), matches: 1, binary: true, lines: Vec::new() }));
+    }
+
+    let text = String::from_utf8_lossy(&bytes);
+    let lines: Vec<&str> = text.lines().collect();
+    let matched: Vec<usize> = (0..lines.len()).filter(|&i| regex.is_match(lines[i])).collect();
+
+    if matched.is_empty() {
+        return Ok(None);
+    }
+
+    // 每个匹配行连同前后 context 行，重叠或相邻的区间合并输出
+    let mut output = Vec::new();
+    let mut next = 0;
+    for &i in &matched {
+        let start = i.saturating_sub(context).max(next);
+        let end = (i + context + 1).min(lines.len());
+        for (j, line) in lines.iter().enumerate().take(end).skip(start) {
+            output.push(GrepLine { line: j + 1, is_match: false, text: line.to_string() });
+        }
+        next = next.max(end);
+    }
+    for line in &mut output {
+        line.is_match = matched.binary_search(&(line.line - 1)).is_ok();
+    }
+
+    Ok(Some(GrepResult {
+        path: path.to_path_buf(),
+        matches: matched.len(),
+        binary: false,
+        lines: output,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn view(path: &Path, range: ViewRange) -> String {
+        let mut out = Vec::new();
+        write_lines(path, range, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_view_ranges() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("lines.txt");
+        let text: String = (1..=20).map(|i| format!("line {}\n", i)).collect();
+        fs::write(&path, &text).unwrap();
+
+        assert_eq!(view(&path, ViewRange::All), text);
+        assert_eq!(view(&path, ViewRange::Head(2)), "line 1\nline 2\n");
+        assert_eq!(view(&path, ViewRange::Tail(2)), "line 19\nline 20\n");
+        assert_eq!(view(&path, ViewRange::Tail(50)), text);
+        assert_eq!(view(&path, ViewRange::parse_lines("3:4").unwrap()), "line 3\nline 4\n");
+        assert_eq!(view(&path, ViewRange::parse_lines("19:").unwrap()), "line 19\nline 20\n");
+        assert_eq!(view(&path, ViewRange::parse_lines("7").unwrap()), "line 7\n");
+
+        // 没有结尾换行的文件
+        fs::write(&path, "a\nb\nc").unwrap();
+        assert_eq!(view(&path, ViewRange::Tail(2)), "b\nc");
+
+        assert!(ViewRange::parse_lines("5:2").is_err());
+        assert!(ViewRange::parse_lines("0:2").is_err());
+        assert!(ViewRange::parse_lines("x").is_err());
+    }
+
+    #[test]
+    fn test_hex_dump() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("data.bin");
+        fs::write(&path, b"Hello, world!\n\x00\x01\xff").unwrap();
+        assert!(is_binary(&path).unwrap());
+
+        let mut out = Vec::new();
+        write_hex(&path, ViewRange::All, &mut out).unwrap();
+        let dump = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!(lines[0], "00000000  48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 21 0a 00 01  |Hello, world!...|");
+        assert_eq!(lines[1], "00000010  ff                                                |.|");
+        assert_eq!(lines[2], "00000011");
+
+        let mut out = Vec::new();
+        write_hex(&path, ViewRange::Tail(3), &mut out).unwrap();
+        assert!(String::from_utf8(out).unwrap().starts_with("0000000e  00 01 ff"));
+    }
+
+    #[test]
+    fn test_copy_appended() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("log.txt");
+        fs::write(&path, "first\n").unwrap();
+
+        let mut out = Vec::new();
+        let position = copy_appended(&path, 6, &mut out).unwrap();
+        assert!(out.is_empty());
+
+        fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(b"second\n").unwrap();
+        let position = copy_appended(&path, position, &mut out).unwrap();
+        assert_eq!(out, b"second\n");
+
+        // 截断后从头开始
+        fs::write(&path, "new\n").unwrap();
+        out.clear();
+        assert_eq!(copy_appended(&path, position, &mut out).unwrap(), 4);
+        assert_eq!(out, b"new\n");
+    }
+
+    #[test]
+    fn test_grep_context_and_binary() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("code.rs");
+        fs::write(&path, "a\nTODO one\nb\nc\nTODO two\nd\ne\nf\ng\nTODO three\n").unwrap();
+        let regex = Regex::new("TODO").unwrap();
+
+        let result = grep_file(&path, &regex, 1).unwrap().unwrap();
+        assert_eq!(result.matches, 3);
+        let numbered: Vec<(usize, bool)> = result.lines.iter().map(|l| (l.line, l.is_match)).collect();
+        // 第 2 和第 5 行的上下文相邻，合并为一段
+        assert_eq!(numbered, [(1, false), (2, true), (3, false), (4, false), (5, true), (6, false), (9, false), (10, true)]);
+
+        assert!(grep_file(&path, &Regex::new("missing").unwrap(), 0).unwrap().is_none());
+
+        let binary = dir.path().join("blob.bin");
+        fs::write(&binary, b"\x00\x01TODO\x02").unwrap();
+        let result = grep_file(&binary, &regex, 0).unwrap().unwrap();
+        assert!(result.binary && result.lines.is_empty());
+    }
+}
@@ -0,0 +1,162 @@
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// file-utils 的错误类型，每个变体对应一个不同的进程退出码
+#[derive(Debug)]
+pub enum FileUtilsError {
+    /// 命令用法或参数错误
+    InvalidInput(String),
+    NotFound { path: PathBuf },
+    AlreadyExists { path: PathBuf },
+    PermissionDenied { path: PathBuf, source: io::Error },
+    NotADirectory { path: PathBuf },
+    /// 需要文件的地方给出了目录
+    IsADirectory { path: PathBuf },
+    /// `--root` 模式下路径超出根目录
+    OutsideRoot { path: PathBuf, root: PathBuf },
+    NotInTrash { name: String },
+    /// 批量重命名时多个文件会得到同一个名字
+    RenameConflict { target: PathBuf, sources: Vec<PathBuf> },
+    /// 操作日志、.trashinfo 等数据文件内容损坏
+    Corrupt { path: PathBuf, message: String },
+    Serialize(serde_json::Error),
+    /// 归档文件无法读取或写入（格式错误、不支持的压缩方式等）
+    Archive { path: PathBuf, message: String },
+    /// 其他 I/O 错误
+    Io { path: PathBuf, source: io::Error },
+}
+
+pub type Result<T, E = FileUtilsError> = std::result::Result<T, E>;
+
+/// 参数错误的退出码，命令行用法错误也使用它
+pub const EXIT_USAGE: i32 = 2;
+
+impl FileUtilsError {
+    /// 按 I/O 错误的种类归类，并附上出错的路径
+    pub fn from_io<P: AsRef<Path>>(error: io::Error, path: P) -> Self {
+        let path = path.as_ref().to_path_buf();
+
+        match error.kind() {
+            io::ErrorKind::NotFound => FileUtilsError::NotFound { path },
+            io::ErrorKind::AlreadyExists => FileUtilsError::AlreadyExists { path },
+            io::ErrorKind::PermissionDenied => FileUtilsError::PermissionDenied { path, source: error },
+            io::ErrorKind::NotADirectory => FileUtilsError::NotADirectory { path },
+            io::ErrorKind::IsADirectory => FileUtilsError::IsADirectory { path },
+            _ => FileUtilsError::Io { path, source: error },
+        }
+    }
+
+    /// 进程退出码
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            FileUtilsError::Io { .. } => 1,
+            FileUtilsError::InvalidInput(_) => EXIT_USAGE,
+            FileUtilsError::NotFound { .. } => 3,
+            FileUtilsError::AlreadyExists { .. } => 4,
+            FileUtilsError::PermissionDenied { .. } => 5,
+            FileUtilsError::NotADirectory { .. } => 6,
+            FileUtilsError::IsADirectory { .. } => 7,
+            FileUtilsError::OutsideRoot { .. } => 8,
+            FileUtilsError::NotInTrash { .. } => 9,
+            FileUtilsError::Corrupt { .. } => 10,
+            FileUtilsError::Serialize(_) => 11,
+            FileUtilsError::RenameConflict { .. } => 12,
+            FileUtilsError::Archive { .. } => 13,
+        }
+    }
+}
+
+impl fmt::Display for FileUtilsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FileUtilsError::InvalidInput(message) => write!(f, "{}", message),
+            FileUtilsError::NotFound { path } => write!(f, "路径不存在: {}", path.display()),
+            FileUtilsError::AlreadyExists { path } => write!(f, "路径已存在: {}", path.display()),
+            FileUtilsError::PermissionDenied { path, source } => {
+                write!(f, "权限不足: {} ({})", path.display(), source)
+            },
+            FileUtilsError::NotADirectory { path } => write!(f, "{} 不是目录", path.display()),
+            FileUtilsError::IsADirectory { path } => write!(f, "{} 是目录，此操作只支持文件", path.display()),
+            FileUtilsError::OutsideRoot { path, root } => {
+                write!(f, "路径超出根目录 {}: {}", root.display(), path.display())
+            },
+            FileUtilsError::NotInTrash { name } => write!(f, "回收站中没有: {}", name),
+            FileUtilsError::RenameConflict { target, sources } => {
+                let names: Vec<String> = sources.iter().map(|s| s.display().to_string()).collect();
+                write!(f, "重命名冲突: {} 都会被命名为 {}", names.join("、"), target.display())
+            },
+            FileUtilsError::Corrupt { path, message } => write!(f, "{} 格式错误: {}", path.display(), message),
+            FileUtilsError::Serialize(e) => write!(f, "JSON 序列化失败: {}", e),
+            FileUtilsError::Archive { path, message } => write!(f, "归档 {} 处理失败: {}", path.display(), message),
+            FileUtilsError::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+        }
+    }
+}
+
+impl std::error::Error for FileUtilsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FileUtilsError::PermissionDenied { source, .. } | FileUtilsError::Io { source, .. } => Some(source),
+            FileUtilsError::Serialize(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for FileUtilsError {
+    fn from(error: serde_json::Error) -> Self {
+        FileUtilsError::Serialize(error)
+    }
+}
+
+/// 为 `io::Result` 附加路径上下文
+pub trait PathContext<T> {
+    fn with_path<P: AsRef<Path>>(self, path: P) -> Result<T>;
+}
+
+impl<T> PathContext<T> for io::Result<T> {
+    fn with_path<P: AsRef<Path>>(self, path: P) -> Result<T> {
+        self.map_err(|error| FileUtilsError::from_io(error, path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::fs;
+
+    #[test]
+    fn test_from_io_classifies_kind() {
+        let missing = fs::read("/definitely/missing/file").with_path("/definitely/missing/file");
+        assert!(matches!(missing, Err(FileUtilsError::NotFound { ref path }) if path == Path::new("/definitely/missing/file")));
+
+        let other = FileUtilsError::from_io(io::Error::other("boom"), "x");
+        assert!(matches!(other, FileUtilsError::Io { .. }));
+        assert_eq!(other.to_string(), "x: boom");
+    }
+
+    #[test]
+    fn test_exit_codes_are_distinct() {
+        let errors = [
+            FileUtilsError::InvalidInput(String::new()),
+            FileUtilsError::NotFound { path: PathBuf::new() },
+            FileUtilsError::AlreadyExists { path: PathBuf::new() },
+            FileUtilsError::PermissionDenied { path: PathBuf::new(), source: io::Error::other("") },
+            FileUtilsError::NotADirectory { path: PathBuf::new() },
+            FileUtilsError::IsADirectory { path: PathBuf::new() },
+            FileUtilsError::OutsideRoot { path: PathBuf::new(), root: PathBuf::new() },
+            FileUtilsError::NotInTrash { name: String::new() },
+            FileUtilsError::Corrupt { path: PathBuf::new(), message: String::new() },
+            FileUtilsError::Serialize(serde_json::from_str::<u8>("x").unwrap_err()),
+            FileUtilsError::Io { path: PathBuf::new(), source: io::Error::other("") },
+            FileUtilsError::RenameConflict { target: PathBuf::new(), sources: Vec::new() },
+            FileUtilsError::Archive { path: PathBuf::new(), message: String::new() },
+        ];
+
+        let codes: HashSet<i32> = errors.iter().map(FileUtilsError::exit_code).collect();
+        assert_eq!(codes.len(), errors.len());
+        assert!(!codes.contains(&0));
+    }
+}
@@ -1,60 +1,119 @@
+use std::collections::{BTreeSet, HashMap};
 use std::fs;
 use std::io;
-use std::path::{Path, PathBuf};
-use std::time::SystemTime;
+use std::path::{self, Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, UNIX_EPOCH};
 
-use crate::utils;
+use crate::archive;
+use crate::checksum::{self, HashAlgorithm};
+use crate::content::{self, GrepResult, ViewOptions, ViewRange};
+use crate::error::{FileUtilsError, PathContext, Result};
+use crate::interrupt;
+use crate::journal::{Journal, Operation};
+use crate::links::{self, Ancestors, Seen};
+use crate::permissions::{self, ModeSpec, Owner};
+use crate::records::{FileInfo, FileKind, FileRecord, TreeNode};
+use crate::rename::{self, RenameRules};
+use crate::sandbox::Sandbox;
+use crate::sync::{self, Filter, SyncOptions};
+use crate::trash::Trash;
+use crate::utils::{self, move_path, TimeStyle};
+use crate::watch::{self, ChangeEvent, WatchOptions, Watcher};
 
-pub struct FileManager;
+pub struct FileManager {
+    dry_run: bool,
+    time_style: TimeStyle,
+    journal: Journal,
+    trash: Trash,
+    sandbox: Option<Sandbox>,
+    follow_links: bool,
+}
 
 impl FileManager {
     pub fn new() -> Self {
-        FileManager
+        FileManager {
+            dry_run: false,
+            time_style: TimeStyle::default(),
+            journal: Journal::open_default(),
+            trash: Trash::open_default(),
+            sandbox: None,
+            follow_links: false,
+        }
+    }
+    
+    /// 使用指定的操作日志
+    #[cfg(test)]
+    pub fn with_journal(mut self, journal: Journal) -> Self {
+        self.journal = journal;
+        self
+    }
+    
+    /// 使用指定的回收站
+    #[cfg(test)]
+    pub fn with_trash(mut self, trash: Trash) -> Self {
+        self.trash = trash;
+        self
+    }
+    
+    /// 演练模式：只打印计划执行的操作，不修改文件系统
+    pub fn dry_run(mut self, enabled: bool) -> Self {
+        self.dry_run = enabled;
+        self
+    }
+    
+    /// 限制所有路径都必须位于沙箱根目录之内（`--root`）
+    pub fn sandbox(mut self, sandbox: Option<Sandbox>) -> Self {
+        self.sandbox = sandbox;
+        self
+    }
+    
+    /// list/info/find/size 遍历时跟随符号链接（`--follow`），默认只处理链接本身
+    pub fn follow_links(mut self, enabled: bool) -> Self {
+        self.follow_links = enabled;
+        self
+    }
+    
+    /// 设置回收站列表等显示时间的风格
+    pub fn time_style(mut self, style: TimeStyle) -> Self {
+        self.time_style = style;
+        self
     }
     
     /// 复制文件
-    pub fn copy(&self, source: &str, destination: &str) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn copy(&self, source: &str, destination: &str) -> Result<()> {
         let source_path = Path::new(source);
         let dest_path = Path::new(destination);
+        self.guard(source_path)?;
         
         if !source_path.exists() {
-            return Err(format!("源文件不存在: {}", source).into());
+            return Err(FileUtilsError::NotFound { path: source_path.to_path_buf() });
         }
         
         if !source_path.is_file() {
-            return Err("只能复制文件，不能复制目录".into());
+            return Err(FileUtilsError::IsADirectory { path: source_path.to_path_buf() });
         }
         
-        // 如果目标是目录，则在目录中创建同名文件
-        let final_dest = if dest_path.is_dir() {
-            let filename = source_path.file_name()
-                .ok_or("无法获取源文件名")?;
-            dest_path.join(filename)
-        } else {
-            // 确保目标目录存在
-            if let Some(parent) = dest_path.parent() {
-                fs::create_dir_all(parent)?;
-            }
-            dest_path.to_path_buf()
-        };
+        let final_dest = resolve_destination(source_path, dest_path)?;
+        self.guard(&final_dest)?;
+        let overwrite = final_dest.exists();
         
-        // 检查是否会覆盖现有文件
-        if final_dest.exists() {
-            print!("目标文件已存在，是否覆盖? (y/N): ");
-            io::Write::flush(&mut io::stdout())?;
-            
-            let mut input = String::new();
-            io::stdin().read_line(&mut input)?;
-            
-            if !input.trim().to_lowercase().starts_with('y') {
-                println!("操作已取消");
-                return Ok(());
-            }
+        if self.dry_run {
+            self.print_plan(&Operation::Copy {
+                source: source_path.to_path_buf(),
+                destination: final_dest.clone(),
+                backup: if overwrite { Some(PathBuf::new()) } else { None },
+            });
+            return Ok(());
         }
         
-        fs::copy(source_path, &final_dest)?;
+        // 检查是否会覆盖现有文件
+        if overwrite && !confirm("目标文件已存在，是否覆盖? (y/N): ")? {
+            println!("操作已取消");
+            return Ok(());
+        }
         
-        let size = fs::metadata(&final_dest)?.len();
+        let size = self.copy_file(source_path, &final_dest)?;
         println!("✅ 文件复制成功: {} -> {} ({})", 
                 source, 
                 final_dest.display(),
@@ -64,39 +123,51 @@ impl FileManager {
     }
     
     /// 移动/重命名文件
-    pub fn move_file(&self, source: &str, destination: &str) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn move_file(&self, source: &str, destination: &str) -> Result<()> {
         let source_path = Path::new(source);
         let dest_path = Path::new(destination);
+        self.guard(source_path)?;
         
         if !source_path.exists() {
-            return Err(format!("源文件不存在: {}", source).into());
+            return Err(FileUtilsError::NotFound { path: source_path.to_path_buf() });
         }
         
-        let final_dest = if dest_path.is_dir() {
-            let filename = source_path.file_name()
-                .ok_or("无法获取源文件名")?;
-            dest_path.join(filename)
-        } else {
-            if let Some(parent) = dest_path.parent() {
-                fs::create_dir_all(parent)?;
-            }
-            dest_path.to_path_buf()
-        };
+        let final_dest = resolve_destination(source_path, dest_path)?;
+        self.guard(&final_dest)?;
+        
+        if self.dry_run {
+            self.print_plan(&Operation::Move {
+                source: source_path.to_path_buf(),
+                destination: final_dest,
+            });
+            return Ok(());
+        }
         
         if final_dest.exists() {
-            print!("目标文件已存在，是否覆盖? (y/N): ");
-            io::Write::flush(&mut io::stdout())?;
-            
-            let mut input = String::new();
-            io::stdin().read_line(&mut input)?;
-            
-            if !input.trim().to_lowercase().starts_with('y') {
+            if !confirm("目标文件已存在，是否覆盖? (y/N): ")? {
                 println!("操作已取消");
                 return Ok(());
             }
+            
+            // 被覆盖的文件同样移入回收站，并单独记录为一次删除
+            let trashed = self.trash.put(&final_dest)?;
+            self.journal.record(Operation::Delete {
+                path: path::absolute(&final_dest).with_path(&final_dest)?,
+                trashed,
+            })?;
+        }
+        
+        if let Some(parent) = final_dest.parent() {
+            fs::create_dir_all(parent).with_path(parent)?;
         }
         
-        fs::rename(source_path, &final_dest)?;
+        let source_abs = path::absolute(source_path).with_path(source_path)?;
+        move_path(source_path, &final_dest)?;
+        
+        self.journal.record(Operation::Move {
+            source: source_abs,
+            destination: path::absolute(&final_dest).with_path(&final_dest)?,
+        })?;
         
         println!("✅ 文件移动成功: {} -> {}", source, final_dest.display());
         
@@ -104,217 +175,599 @@ impl FileManager {
     }
     
     /// 删除文件
-    pub fn delete(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    ///
+    /// 默认移入回收站，可通过 undo 或 trash restore 恢复；permanent 为 true 时直接永久删除。
+    pub fn delete(&self, path: &str, permanent: bool) -> Result<()> {
         let file_path = Path::new(path);
+        self.guard(file_path)?;
         
         if !file_path.exists() {
-            return Err(format!("文件不存在: {}", path).into());
+            return Err(FileUtilsError::NotFound { path: file_path.to_path_buf() });
         }
         
-        print!("确定要删除 {} 吗? (y/N): ", path);
-        io::Write::flush(&mut io::stdout())?;
-        
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
+        if self.dry_run {
+            if permanent {
+                println!("[演练] 永久删除 {}", file_path.display());
+            } else {
+                self.print_plan(&Operation::Delete {
+                    path: file_path.to_path_buf(),
+                    trashed: PathBuf::new(),
+                });
+            }
+            return Ok(());
+        }
         
-        if !input.trim().to_lowercase().starts_with('y') {
+        let prompt = if permanent {
+            format!("确定要永久删除 {} 吗? 此操作无法撤销 (y/N): ", path)
+        } else {
+            format!("确定要删除 {} 吗? (y/N): ", path)
+        };
+        if !confirm(&prompt)? {
             println!("操作已取消");
             return Ok(());
         }
         
-        if file_path.is_file() {
-            fs::remove_file(file_path)?;
-            println!("✅ 文件删除成功: {}", path);
-        } else if file_path.is_dir() {
-            fs::remove_dir_all(file_path)?;
-            println!("✅ 目录删除成功: {}", path);
+        let is_dir = file_path.is_dir();
+        
+        if permanent {
+            if is_dir {
+                fs::remove_dir_all(file_path).with_path(file_path)?;
+            } else {
+                fs::remove_file(file_path).with_path(file_path)?;
+            }
+        } else {
+            self.trash_path(file_path)?;
+        }
+        
+        let kind = if is_dir { "目录" } else { "文件" };
+        if permanent {
+            println!("✅ {}已永久删除: {}", kind, path);
+        } else {
+            println!("✅ {}已移入回收站: {}", kind, path);
         }
         
         Ok(())
     }
     
-    /// 列出目录内容
-    pub fn list(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let dir_path = Path::new(path);
+    /// 复制一个文件并记入操作日志，不做任何询问；被覆盖的文件先移入回收站以便撤销
+    fn copy_file(&self, source: &Path, destination: &Path) -> Result<u64> {
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent).with_path(parent)?;
+        }
         
-        if !dir_path.exists() {
-            return Err(format!("路径不存在: {}", path).into());
+        let backup = if destination.exists() {
+            Some(self.trash.put(destination)?)
+        } else {
+            None
+        };
+        
+        fs::copy(source, destination).with_path(destination)?;
+        
+        self.journal.record(Operation::Copy {
+            source: path::absolute(source).with_path(source)?,
+            destination: path::absolute(destination).with_path(destination)?,
+            backup,
+        })?;
+        
+        Ok(fs::metadata(destination).with_path(destination)?.len())
+    }
+    
+    /// 把文件或目录移入回收站并记入操作日志
+    fn trash_path(&self, path: &Path) -> Result<()> {
+        let original = path::absolute(path).with_path(path)?;
+        let trashed = self.trash.put(path)?;
+        
+        self.journal.record(Operation::Delete {
+            path: original,
+            trashed,
+        })
+    }
+    
+    /// 列出回收站内容
+    pub fn trash_list(&self) -> Result<()> {
+        let items = self.trash.list()?;
+        
+        if items.is_empty() {
+            println!("🗑️  回收站是空的");
+            return Ok(());
         }
         
-        if !dir_path.is_dir() {
-            return Err(format!("{} 不是目录", path).into());
+        println!("🗑️  回收站内容");
+        println!("{}", "=".repeat(60));
+        
+        for item in &items {
+            println!("{:24} {:>12}  {}",
+                    item.name,
+                    utils::format_time(UNIX_EPOCH + Duration::from_secs(item.deleted_at.max(0) as u64), &self.time_style),
+                    item.original_path.display());
         }
         
-        println!("📁 目录内容: {}", dir_path.display());
         println!("{}", "=".repeat(60));
+        println!("总计: {} 项", items.len());
         
-        let mut entries: Vec<_> = fs::read_dir(dir_path)?.collect::<Result<Vec<_>, _>>()?;
-        entries.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+        Ok(())
+    }
+    
+    /// 从回收站恢复条目到原位置
+    pub fn trash_restore(&self, name: &str) -> Result<()> {
+        let item = self.trash.read_info(name)?;
+        self.guard(&item.original_path)?;
         
-        for entry in entries {
-            let metadata = entry.metadata()?;
-            let file_name = entry.file_name();
-            let file_name_str = file_name.to_string_lossy();
-            
-            let (icon, type_str) = if metadata.is_dir() {
-                ("📁", "DIR")
-            } else {
-                ("📄", "FILE")
-            };
-            
-            let size = if metadata.is_file() {
-                utils::format_size(metadata.len())
-            } else {
-                "-".to_string()
-            };
-            
-            let modified = metadata.modified()
-                .map(utils::format_time)
-                .unwrap_or_else(|_| "未知".to_string());
-            
-            println!("{} {:4} {:>10} {:19} {}", 
-                    icon, type_str, size, modified, file_name_str);
+        if self.dry_run {
+            println!("[演练] 恢复 {} -> {}", name, item.original_path.display());
+            return Ok(());
         }
         
+        let restored = self.trash.restore(name)?;
+        println!("✅ 已恢复: {}", restored.display());
+        
         Ok(())
     }
     
-    /// 显示文件信息
-    pub fn info(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let file_path = Path::new(path);
-        
-        if !file_path.exists() {
-            return Err(format!("文件不存在: {}", path).into());
+    /// 清空回收站；older_than 为 Some 时只清理删除时间早于该秒数的条目
+    pub fn trash_empty(&self, older_than: Option<u64>) -> Result<()> {
+        if self.dry_run {
+            let now = utils::current_timestamp();
+            for item in self.trash.list()? {
+                if older_than.is_none_or(|age| now - item.deleted_at >= age as i64) {
+                    println!("[演练] 永久删除 {} ({})", item.name, item.original_path.display());
+                }
+            }
+            return Ok(());
         }
         
-        let metadata = fs::metadata(file_path)?;
-        let abs_path = fs::canonicalize(file_path)?;
+        let removed = self.trash.empty(older_than)?;
+        println!("✅ 已从回收站永久删除 {} 项", removed.len());
+        
+        Ok(())
+    }
+    
+    /// 撤销最近的 count 次操作
+    pub fn undo(&self, count: usize) -> Result<()> {
+        let entries = self.journal.entries()?;
         
-        println!("📋 文件信息");
-        println!("{}", "=".repeat(40));
-        println!("文件名: {}", file_path.file_name().unwrap().to_string_lossy());
-        println!("路径: {}", file_path.display());
-        println!("绝对路径: {}", abs_path.display());
-        println!("类型: {}", if metadata.is_dir() { "目录" } else { "文件" });
+        if entries.is_empty() {
+            println!("没有可以撤销的操作");
+            return Ok(());
+        }
         
-        if metadata.is_file() {
-            println!("大小: {} ({} 字节)", utils::format_size(metadata.len()), metadata.len());
+        if self.dry_run {
+            for entry in entries.iter().rev().take(count) {
+                println!("[演练] 撤销: {}", entry.operation);
+            }
+            return Ok(());
+        }
+        
+        // 从最新的记录开始逐条撤销，每成功一条就从日志中移除
+        let mut undone = 0;
+        for entry in entries.iter().rev().take(count) {
+            self.revert(&entry.operation)?;
+            self.journal.remove_last()?;
+            println!("↩️  已撤销: {}", entry.operation);
+            undone += 1;
         }
         
-        if let Ok(created) = metadata.created() {
-            println!("创建时间: {}", utils::format_time(created));
+        println!("✅ 共撤销 {} 个操作", undone);
+        
+        Ok(())
+    }
+    
+    /// 显示操作日志
+    pub fn history(&self) -> Result<()> {
+        let entries = self.journal.entries()?;
+        
+        if entries.is_empty() {
+            println!("📜 操作日志为空");
+            return Ok(());
         }
         
-        if let Ok(modified) = metadata.modified() {
-            println!("修改时间: {}", utils::format_time(modified));
+        println!("📜 操作日志 (最新的在最后)");
+        println!("{}", "=".repeat(60));
+        
+        for (index, entry) in entries.iter().enumerate() {
+            println!("{:3}. {}", index + 1, entry.operation);
         }
         
-        if let Ok(accessed) = metadata.accessed() {
-            println!("访问时间: {}", utils::format_time(accessed));
+        Ok(())
+    }
+    
+    /// 执行单个操作的逆操作
+    fn revert(&self, operation: &Operation) -> Result<()> {
+        for path in operation.paths() {
+            self.guard(path)?;
         }
         
-        // Unix 特有的权限信息
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            let permissions = metadata.permissions();
-            println!("权限: {:o}", permissions.mode());
+        match operation {
+            Operation::Copy { destination, backup, .. } => {
+                if destination.exists() {
+                    fs::remove_file(destination).with_path(destination)?;
+                }
+                if let Some(backup) = backup {
+                    self.restore_from_trash(backup, destination)?;
+                }
+            },
+            Operation::Move { source, destination } => {
+                if source.exists() {
+                    return Err(FileUtilsError::AlreadyExists { path: source.clone() });
+                }
+                if let Some(parent) = source.parent() {
+                    fs::create_dir_all(parent).with_path(parent)?;
+                }
+                move_path(destination, source)?;
+            },
+            Operation::Delete { path, trashed } => {
+                self.restore_from_trash(trashed, path)?;
+            },
+            Operation::Link { link, .. } => {
+                if link.symlink_metadata().is_ok() {
+                    fs::remove_file(link).with_path(link)?;
+                }
+            },
         }
         
         Ok(())
     }
     
-    /// 创建空文件
-    pub fn create(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    /// 把回收站中的文件移回指定位置
+    fn restore_from_trash(&self, trashed: &Path, destination: &Path) -> Result<()> {
+        // 已经通过 trash restore 恢复过了
+        if trashed.symlink_metadata().is_err() && destination.symlink_metadata().is_ok() {
+            return Ok(());
+        }
+
+        match self.trash.name_of(trashed) {
+            Some(name) => self.trash.restore_to(&name, destination),
+            None => Err(FileUtilsError::NotInTrash { name: trashed.display().to_string() }),
+        }
+    }
+    
+    /// 设置了 `--root` 时，拒绝根目录之外的路径
+    fn guard(&self, path: &Path) -> Result<()> {
+        if let Some(sandbox) = &self.sandbox {
+            sandbox.check(path)?;
+        }
+        Ok(())
+    }
+    
+    fn print_plan(&self, operation: &Operation) {
+        println!("[演练] {}", operation);
+    }
+    
+    /// 列出目录内容，按 options 过滤隐藏文件并排序
+    pub fn list(&self, path: &str, options: &ListOptions) -> Result<Vec<FileRecord>> {
+        let dir_path = Path::new(path);
+        self.guard(dir_path)?;
+        
+        if !dir_path.exists() {
+            return Err(FileUtilsError::NotFound { path: dir_path.to_path_buf() });
+        }
+        
+        if !dir_path.is_dir() {
+            return Err(FileUtilsError::NotADirectory { path: dir_path.to_path_buf() });
+        }
+        
+        read_records(dir_path, options, self.follow_links)
+    }
+    
+    /// 以树的形式列出目录，depth 为 None 时不限深度；只有 `--follow` 时才进入符号链接指向的目录
+    pub fn tree(&self, path: &str, depth: Option<usize>, options: &ListOptions) -> Result<TreeNode> {
+        let dir_path = Path::new(path);
+        self.guard(dir_path)?;
+        
+        if !dir_path.is_dir() {
+            return Err(FileUtilsError::NotADirectory { path: dir_path.to_path_buf() });
+        }
+        
+        let mut root = FileRecord::from_path(dir_path)?;
+        root.name = path.to_string();
+        
+        let mut ancestors = Ancestors::default();
+        ancestors.enter(dir_path, &fs::metadata(dir_path).with_path(dir_path)?);
+        
+        Ok(TreeNode {
+            record: root,
+            children: self.build_tree(dir_path, depth, options, &mut ancestors)?,
+        })
+    }
+    
+    fn build_tree(&self, dir: &Path, depth: Option<usize>, options: &ListOptions, ancestors: &mut Ancestors) -> Result<Vec<TreeNode>> {
+        if depth == Some(0) {
+            return Ok(Vec::new());
+        }
+        
+        let mut nodes = Vec::new();
+        for record in read_records(dir, options, self.follow_links)? {
+            let mut children = Vec::new();
+            if record.kind == FileKind::Dir {
+                // 跟随的符号链接可能指向根目录之外，也可能形成循环
+                if self.follow_links && fs::symlink_metadata(&record.path).with_path(&record.path)?.is_symlink() {
+                    self.guard(&record.path)?;
+                }
+                let metadata = links::metadata(&record.path, self.follow_links)?;
+                if ancestors.enter(&record.path, &metadata) {
+                    children = self.build_tree(&record.path, depth.map(|d| d - 1), options, ancestors)?;
+                    ancestors.leave();
+                }
+            }
+            nodes.push(TreeNode { record, children });
+        }
+        
+        Ok(nodes)
+    }
+    
+    /// 获取文件信息；不跟随时显示符号链接本身
+    pub fn info(&self, path: &str) -> Result<FileInfo> {
+        let file_path = Path::new(path);
+        self.guard(file_path)?;
+        
+        if file_path.symlink_metadata().is_err() {
+            return Err(FileUtilsError::NotFound { path: file_path.to_path_buf() });
+        }
+        
+        FileInfo::from_path(file_path, self.follow_links)
+    }
+    
+    /// 创建空文件，mode 为 Some 时在新文件的默认权限上应用该模式
+    pub fn create(&self, path: &str, mode: Option<&ModeSpec>) -> Result<()> {
         let file_path = Path::new(path);
+        self.guard(file_path)?;
         
         if file_path.exists() {
-            return Err(format!("文件已存在: {}", path).into());
+            return Err(FileUtilsError::AlreadyExists { path: file_path.to_path_buf() });
         }
         
         // 确保父目录存在
         if let Some(parent) = file_path.parent() {
-            fs::create_dir_all(parent)?;
+            fs::create_dir_all(parent).with_path(parent)?;
         }
         
-        fs::File::create(file_path)?;
-        println!("✅ 文件创建成功: {}", path);
+        fs::File::create(file_path).with_path(file_path)?;
+        
+        match mode {
+            Some(mode) => {
+                let metadata = fs::metadata(file_path).with_path(file_path)?;
+                let bits = mode.apply(permissions::mode_of(&metadata).unwrap_or(0), false);
+                permissions::set_mode(file_path, bits)?;
+                println!("✅ 文件创建成功: {} (权限 {:04o})", path, bits);
+            },
+            None => println!("✅ 文件创建成功: {}", path),
+        }
         
         Ok(())
     }
     
     /// 创建目录
-    pub fn create_dir(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn create_dir(&self, path: &str) -> Result<()> {
         let dir_path = Path::new(path);
+        self.guard(dir_path)?;
         
         if dir_path.exists() {
-            return Err(format!("目录已存在: {}", path).into());
+            return Err(FileUtilsError::AlreadyExists { path: dir_path.to_path_buf() });
         }
         
-        fs::create_dir_all(dir_path)?;
+        fs::create_dir_all(dir_path).with_path(dir_path)?;
         println!("✅ 目录创建成功: {}", path);
         
         Ok(())
     }
     
-    /// 查找文件
-    pub fn find(&self, dir: &str, pattern: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let search_dir = Path::new(dir);
+    /// 创建链接；symbolic 时创建符号链接并原样保存 target，否则创建硬链接。
+    /// link 是已有目录时在其中使用 target 的文件名
+    pub fn link(&self, target: &str, link: &str, symbolic: bool) -> Result<()> {
+        let target_path = Path::new(target);
+        let link_path = resolve_destination(target_path, Path::new(link))?;
+        self.guard(&link_path)?;
         
-        if !search_dir.exists() {
-            return Err(format!("目录不存在: {}", dir).into());
+        // 相对的符号链接目标相对于链接所在目录解析
+        let resolved = match link_path.parent() {
+            Some(parent) if symbolic => parent.join(target_path),
+            _ => target_path.to_path_buf(),
+        };
+        self.guard(&resolved)?;
+        
+        if !symbolic {
+            match fs::symlink_metadata(target_path) {
+                Err(_) => return Err(FileUtilsError::NotFound { path: target_path.to_path_buf() }),
+                Ok(metadata) if metadata.is_dir() => return Err(FileUtilsError::IsADirectory { path: target_path.to_path_buf() }),
+                Ok(_) => {},
+            }
         }
         
-        if !search_dir.is_dir() {
-            return Err(format!("{} 不是目录", dir).into());
+        if link_path.symlink_metadata().is_ok() {
+            return Err(FileUtilsError::AlreadyExists { path: link_path });
         }
         
-        println!("🔍 在 {} 中搜索 \"{}\"", dir, pattern);
-        println!("{}", "=".repeat(40));
+        let operation = Operation::Link {
+            target: target_path.to_path_buf(),
+            link: link_path.clone(),
+            symbolic,
+        };
+        
+        if self.dry_run {
+            self.print_plan(&operation);
+            return Ok(());
+        }
         
-        let matches = self.find_files_recursive(search_dir, pattern)?;
+        links::create(target_path, &link_path, symbolic)?;
+        self.journal.record(operation.clone())?;
+        println!("✅ {}", operation);
         
-        if matches.is_empty() {
-            println!("未找到匹配的文件");
-        } else {
-            for file_path in matches {
-                let metadata = fs::metadata(&file_path)?;
-                let size = if metadata.is_file() {
-                    utils::format_size(metadata.len())
-                } else {
-                    "DIR".to_string()
-                };
-                println!("📄 {} ({})", file_path.display(), size);
+        Ok(())
+    }
+    
+    /// 修改权限；recursive 时不进入目录中的符号链接，也不修改链接指向的文件
+    pub fn chmod(&self, path: &str, mode: &ModeSpec, recursive: bool) -> Result<()> {
+        let target = Path::new(path);
+        self.guard(target)?;
+        
+        let mut changed = 0;
+        walk_entries(target, recursive, self.follow_links, &mut |entry, metadata| {
+            if metadata.is_symlink() {
+                // 符号链接本身没有权限可改
+                if entry == target {
+                    println!("⚠️  跳过符号链接: {} (使用 --follow 修改它指向的文件)", entry.display());
+                }
+                return Ok(());
+            }
+            
+            let old = permissions::mode_of(metadata).unwrap_or(0);
+            let new = mode.apply(old, metadata.is_dir());
+            if new == old {
+                return Ok(());
+            }
+            
+            if self.dry_run {
+                println!("[演练] 权限 {:04o} → {:04o}: {}", old, new, entry.display());
+            } else {
+                permissions::set_mode(entry, new)?;
+                println!("🔐 权限 {:04o} → {:04o}: {}", old, new, entry.display());
+            }
+            changed += 1;
+            Ok(())
+        })?;
+        
+        if recursive && !self.dry_run {
+            println!("✅ 已修改 {} 个条目的权限", changed);
+        }
+        Ok(())
+    }
+    
+    /// 修改所有者；recursive 时修改目录中的符号链接本身而不是其指向的文件
+    pub fn chown(&self, path: &str, owner: &Owner, recursive: bool) -> Result<()> {
+        let target = Path::new(path);
+        self.guard(target)?;
+        
+        let mut changed = 0;
+        walk_entries(target, recursive, self.follow_links, &mut |entry, metadata| {
+            let (uid, gid) = permissions::owner_of(metadata).unwrap_or((0, 0));
+            let (new_uid, new_gid) = (owner.uid.unwrap_or(uid), owner.gid.unwrap_or(gid));
+            if (new_uid, new_gid) == (uid, gid) {
+                return Ok(());
+            }
+            
+            let change = format!(
+                "所有者 {} → {}: {}",
+                permissions::owner_string(uid, gid),
+                permissions::owner_string(new_uid, new_gid),
+                entry.display()
+            );
+            if self.dry_run {
+                println!("[演练] {}", change);
+            } else {
+                permissions::set_owner(entry, owner, !metadata.is_symlink())?;
+                println!("👤 {}", change);
+            }
+            changed += 1;
+            Ok(())
+        })?;
+        
+        if recursive && !self.dry_run {
+            println!("✅ 已修改 {} 个条目的所有者", changed);
+        }
+        Ok(())
+    }
+    
+    /// 把访问和修改时间设为 time（默认当前时间），文件不存在时创建空文件
+    pub fn touch(&self, path: &str, time: Option<i64>, recursive: bool) -> Result<()> {
+        let target = Path::new(path);
+        self.guard(target)?;
+        
+        let timestamp = time.unwrap_or_else(utils::current_timestamp);
+        let shown = utils::format_timestamp(timestamp, utils::local_offset(timestamp), &TimeStyle::FullIso);
+        
+        if fs::symlink_metadata(target).is_err() {
+            if self.dry_run {
+                println!("[演练] 创建 {} (时间 {})", path, shown);
+                return Ok(());
             }
+            fs::File::create_new(target).with_path(target)?;
+            println!("✅ 文件创建成功: {}", path);
         }
         
+        let mut count = 0;
+        walk_entries(target, recursive, self.follow_links, &mut |entry, metadata| {
+            if self.dry_run {
+                println!("[演练] 时间 → {}: {}", shown, entry.display());
+            } else {
+                permissions::set_times(entry, timestamp, !metadata.is_symlink())?;
+            }
+            count += 1;
+            Ok(())
+        })?;
+        
+        if !self.dry_run {
+            println!("🕒 已把 {} 个条目的时间设为 {}", count, shown);
+        }
         Ok(())
     }
     
-    /// 递归查找文件
-    fn find_files_recursive(&self, dir: &Path, pattern: &str) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
-        let mut matches = Vec::new();
+    /// 查找文件
+    pub fn find(&self, dir: &str, pattern: &str) -> Result<Vec<FileRecord>> {
+        let search_dir = Path::new(dir);
+        self.guard(search_dir)?;
+        
+        if !search_dir.exists() {
+            return Err(FileUtilsError::NotFound { path: search_dir.to_path_buf() });
+        }
+        
+        if !search_dir.is_dir() {
+            return Err(FileUtilsError::NotADirectory { path: search_dir.to_path_buf() });
+        }
+        
+        Ok(self.find_entries(search_dir, pattern)?
+            .iter()
+            .map(|(path, metadata)| FileRecord::from_metadata(path, metadata))
+            .collect())
+    }
+    
+    /// 递归查找普通文件；不跟随符号链接时链接会被跳过
+    fn find_files_recursive(&self, dir: &Path, pattern: &str) -> Result<Vec<PathBuf>> {
+        Ok(self.find_entries(dir, pattern)?
+            .into_iter()
+            .filter(|(_, metadata)| metadata.is_file())
+            .map(|(path, _)| path)
+            .collect())
+    }
+    
+    /// 递归查找名称匹配的非目录条目及其元数据
+    fn find_entries(&self, dir: &Path, pattern: &str) -> Result<Vec<(PathBuf, fs::Metadata)>> {
+        let mut ancestors = Ancestors::default();
+        ancestors.enter(dir, &fs::metadata(dir).with_path(dir)?);
         
-        for entry in fs::read_dir(dir)? {
-            let entry = entry?;
+        let mut matches = Vec::new();
+        self.collect_entries(dir, pattern, &mut ancestors, &mut matches)?;
+        Ok(matches)
+    }
+    
+    fn collect_entries(
+        &self,
+        dir: &Path,
+        pattern: &str,
+        ancestors: &mut Ancestors,
+        matches: &mut Vec<(PathBuf, fs::Metadata)>,
+    ) -> Result<()> {
+        for entry in fs::read_dir(dir).with_path(dir)? {
+            let entry = entry.with_path(dir)?;
             let path = entry.path();
             
-            if path.is_dir() {
-                // 递归搜索子目录
-                let mut sub_matches = self.find_files_recursive(&path, pattern)?;
-                matches.append(&mut sub_matches);
-            } else if path.is_file() {
-                // 检查文件名是否匹配模式
-                if let Some(filename) = path.file_name() {
-                    let filename_str = filename.to_string_lossy();
-                    if self.pattern_match(&filename_str, pattern) {
-                        matches.push(path);
-                    }
+            // 跟随的符号链接可能指向根目录之外
+            if self.follow_links && entry.file_type().with_path(&path)?.is_symlink() {
+                self.guard(&path)?;
+            }
+            
+            let metadata = links::metadata(&path, self.follow_links)?;
+            if metadata.is_dir() {
+                // 递归搜索子目录，跳过符号链接造成的循环
+                if ancestors.enter(&path, &metadata) {
+                    self.collect_entries(&path, pattern, ancestors, matches)?;
+                    ancestors.leave();
                 }
+            } else if self.pattern_match(&entry.file_name().to_string_lossy(), pattern) {
+                matches.push((path, metadata));
             }
         }
         
-        Ok(matches)
+        Ok(())
     }
     
     /// 简单的模式匹配（支持 * 通配符）
@@ -329,13 +782,11 @@ impl FileManager {
         }
         
         // 处理简单的 *.ext 模式
-        if pattern.starts_with('*') {
-            let suffix = &pattern[1..];
+        if let Some(suffix) = pattern.strip_prefix('*') {
             return filename.ends_with(suffix);
         }
         
-        if pattern.ends_with('*') {
-            let prefix = &pattern[..pattern.len()-1];
+        if let Some(prefix) = pattern.strip_suffix('*') {
             return filename.starts_with(prefix);
         }
         
@@ -343,41 +794,1100 @@ impl FileManager {
         filename.contains(&pattern.replace('*', ""))
     }
     
-    /// 计算文件或目录大小
-    pub fn calculate_size(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let target_path = Path::new(path);
+    /// 显示文件内容；二进制文件自动改用十六进制转储，follow 时像 `tail -f` 一样持续输出
+    pub fn view(&self, path: &str, options: &ViewOptions) -> Result<()> {
+        let file_path = Path::new(path);
+        self.guard(file_path)?;
         
-        if !target_path.exists() {
-            return Err(format!("路径不存在: {}", path).into());
+        if !file_path.exists() {
+            return Err(FileUtilsError::NotFound { path: file_path.to_path_buf() });
+        }
+        
+        if file_path.is_dir() {
+            return Err(FileUtilsError::IsADirectory { path: file_path.to_path_buf() });
         }
         
-        let size = self.get_size_recursive(target_path)?;
+        let binary = content::is_binary(file_path)?;
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
         
-        println!("📊 大小统计: {}", path);
-        println!("{}", "=".repeat(30));
-        println!("总大小: {} ({} 字节)", utils::format_size(size), size);
+        if options.hex || binary {
+            if options.follow {
+                return Err(FileUtilsError::InvalidInput("-f 只支持文本文件".to_string()));
+            }
+            if !options.hex {
+                eprintln!("📦 {} 是二进制文件，以十六进制显示", path);
+            }
+            return content::write_hex(file_path, options.range, &mut out);
+        }
+        
+        // 与 tail -f 相同，跟随时默认先显示最后 10 行
+        let range = match (options.follow, options.range) {
+            (true, ViewRange::All) => ViewRange::Tail(10),
+            (_, range) => range,
+        };
+        content::write_lines(file_path, range, &mut out)?;
+        
+        if options.follow {
+            let position = fs::metadata(file_path).with_path(file_path)?.len();
+            content::follow(file_path, position, &mut out)?;
+        }
         
         Ok(())
     }
     
-    /// 递归计算目录大小
-    fn get_size_recursive(&self, path: &Path) -> Result<u64, Box<dyn std::error::Error>> {
-        let metadata = fs::metadata(path)?;
+    /// 在文件或目录中搜索匹配正则表达式的行；目录使用与 find 相同的遍历，pattern 筛选文件名
+    pub fn grep(&self, regex: &str, path: &str, ignore_case: bool, context: usize, pattern: Option<&str>) -> Result<Vec<GrepResult>> {
+        let search_path = Path::new(path);
+        self.guard(search_path)?;
         
-        if metadata.is_file() {
-            Ok(metadata.len())
-        } else if metadata.is_dir() {
-            let mut total_size = 0;
-            
-            for entry in fs::read_dir(path)? {
-                let entry = entry?;
-                let entry_size = self.get_size_recursive(&entry.path())?;
-                total_size += entry_size;
-            }
-            
-            Ok(total_size)
-        } else {
-            Ok(0)
+        if !search_path.exists() {
+            return Err(FileUtilsError::NotFound { path: search_path.to_path_buf() });
         }
+        
+        let regex = regex::RegexBuilder::new(regex)
+            .case_insensitive(ignore_case)
+            .build()
+            .map_err(|e| FileUtilsError::InvalidInput(format!("无效的正则表达式: {}", e)))?;
+        
+        let mut files = if search_path.is_dir() {
+            self.find_files_recursive(search_path, pattern.unwrap_or("*"))?
+        } else {
+            vec![search_path.to_path_buf()]
+        };
+        files.sort();
+        
+        let mut results = Vec::new();
+        for file in files {
+            if let Some(result) = content::grep_file(&file, &regex, context)? {
+                results.push(result);
+            }
+        }
+        
+        Ok(results)
+    }
+    
+    /// 计算文件摘要；目录会递归计算其中每个文件，manifest 不为空时写出清单
+    ///
+    /// 清单格式与 sha256sum/b3sum 相同：`<摘要>  <相对路径>`。
+    pub fn hash(&self, path: &str, algorithm: HashAlgorithm, manifest: Option<&str>) -> Result<()> {
+        let target_path = Path::new(path);
+        self.guard(target_path)?;
+        if let Some(manifest) = manifest {
+            self.guard(Path::new(manifest))?;
+        }
+        
+        if !target_path.exists() {
+            return Err(FileUtilsError::NotFound { path: target_path.to_path_buf() });
+        }
+        
+        let mut lines = Vec::new();
+        
+        if target_path.is_dir() {
+            let mut files = self.find_files_recursive(target_path, "*")?;
+            files.sort();
+            
+            for file in files {
+                let digest = checksum::hash_file(&file, algorithm)?;
+                let relative = file.strip_prefix(target_path).unwrap_or(&file);
+                lines.push(format!("{}  {}", digest, relative.display()));
+            }
+        } else {
+            let digest = checksum::hash_file(target_path, algorithm)?;
+            lines.push(format!("{}  {}", digest, path));
+        }
+        
+        for line in &lines {
+            println!("{}", line);
+        }
+        
+        if let Some(manifest) = manifest {
+            let mut content = lines.join("\n");
+            content.push('\n');
+            fs::write(manifest, content).with_path(manifest)?;
+            println!("✅ {} 清单已写入: {} ({} 个文件)", algorithm, manifest, lines.len());
+        }
+        
+        Ok(())
+    }
+    
+    /// 查找内容完全相同的文件
+    pub fn dupes(&self, dir: &str, algorithm: HashAlgorithm) -> Result<()> {
+        let search_dir = Path::new(dir);
+        self.guard(search_dir)?;
+        
+        if !search_dir.is_dir() {
+            return Err(FileUtilsError::NotADirectory { path: search_dir.to_path_buf() });
+        }
+        
+        let groups = self.find_duplicates(search_dir, algorithm)?;
+        
+        if groups.is_empty() {
+            println!("没有发现重复文件");
+            return Ok(());
+        }
+        
+        println!("🔁 重复文件: {}", dir);
+        println!("{}", "=".repeat(60));
+        
+        let mut reclaimable = 0;
+        for group in &groups {
+            println!("{} × {} 个文件:", utils::format_size(group.size), group.files.len());
+            for file in &group.files {
+                println!("    {}", file.display());
+            }
+            reclaimable += group.reclaimable();
+        }
+        
+        println!("{}", "=".repeat(60));
+        println!("共 {} 组重复文件，删除多余副本可释放 {}", groups.len(), utils::format_size(reclaimable));
+        
+        Ok(())
+    }
+    
+    /// 先按大小分组，再只对大小相同的文件计算摘要
+    fn find_duplicates(&self, dir: &Path, algorithm: HashAlgorithm) -> Result<Vec<DuplicateGroup>> {
+        let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+        let mut seen = Seen::default();
+        
+        for file in self.find_files_recursive(dir, "*")? {
+            let metadata = fs::metadata(&file).with_path(&file)?;
+            let size = metadata.len();
+            // 空文件都相同，没有比较的意义；硬链接是同一个文件，删掉也不会释放空间
+            if size > 0 && seen.first_visit(&metadata) {
+                by_size.entry(size).or_default().push(file);
+            }
+        }
+        
+        let mut groups = Vec::new();
+        
+        for (size, files) in by_size {
+            if files.len() < 2 {
+                continue;
+            }
+            
+            let mut by_hash: HashMap<String, Vec<PathBuf>> = HashMap::new();
+            for file in files {
+                by_hash.entry(checksum::hash_file(&file, algorithm)?).or_default().push(file);
+            }
+            
+            for (_, mut same) in by_hash {
+                if same.len() > 1 {
+                    same.sort();
+                    groups.push(DuplicateGroup { size, files: same });
+                }
+            }
+        }
+        
+        // 可释放空间最多的组排在前面
+        groups.sort_by(|a, b| {
+            b.reclaimable().cmp(&a.reclaimable()).then_with(|| a.files.cmp(&b.files))
+        });
+        
+        Ok(groups)
+    }
+    
+    /// 比较两个文件或两个目录树，返回它们是否相同
+    pub fn diff(&self, left: &str, right: &str) -> Result<bool> {
+        let left_path = Path::new(left);
+        let right_path = Path::new(right);
+        
+        for path in [left_path, right_path] {
+            self.guard(path)?;
+            if !path.exists() {
+                return Err(FileUtilsError::NotFound { path: path.to_path_buf() });
+            }
+        }
+        
+        if left_path.is_file() && right_path.is_file() {
+            let same = checksum::same_content(left_path, right_path)?;
+            if same {
+                println!("✅ 文件内容相同: {} 和 {}", left, right);
+            } else {
+                println!("❌ 文件内容不同: {} 和 {}", left, right);
+            }
+            return Ok(same);
+        }
+        
+        if !(left_path.is_dir() && right_path.is_dir()) {
+            return Err(FileUtilsError::InvalidInput("只能比较两个文件或两个目录".to_string()));
+        }
+        
+        let diff = self.compare_trees(left_path, right_path)?;
+        
+        if diff.is_empty() {
+            println!("✅ 目录内容相同: {} 和 {}", left, right);
+            return Ok(true);
+        }
+        
+        println!("🔍 目录差异: {} ↔ {}", left, right);
+        println!("{}", "=".repeat(60));
+        for path in &diff.only_left {
+            println!("- 仅在 {}: {}", left, path.display());
+        }
+        for path in &diff.only_right {
+            println!("+ 仅在 {}: {}", right, path.display());
+        }
+        for path in &diff.changed {
+            println!("~ 内容不同: {}", path.display());
+        }
+        println!("{}", "=".repeat(60));
+        println!("仅左侧 {} 个，仅右侧 {} 个，内容不同 {} 个",
+                diff.only_left.len(), diff.only_right.len(), diff.changed.len());
+        
+        Ok(false)
+    }
+    
+    /// 比较两个目录树中的文件（按相对路径对齐）
+    fn compare_trees(&self, left: &Path, right: &Path) -> Result<TreeDiff> {
+        let relative_files = |root: &Path| -> Result<BTreeSet<PathBuf>> {
+            Ok(self.find_files_recursive(root, "*")?
+                .into_iter()
+                .filter_map(|file| file.strip_prefix(root).ok().map(Path::to_path_buf))
+                .collect())
+        };
+        
+        let left_files = relative_files(left)?;
+        let right_files = relative_files(right)?;
+        
+        let mut diff = TreeDiff {
+            only_left: left_files.difference(&right_files).cloned().collect(),
+            only_right: right_files.difference(&left_files).cloned().collect(),
+            changed: Vec::new(),
+        };
+        
+        for relative in left_files.intersection(&right_files) {
+            if !checksum::same_content(&left.join(relative), &right.join(relative))? {
+                diff.changed.push(relative.clone());
+            }
+        }
+        
+        Ok(diff)
+    }
+    
+    /// 批量重命名目录中的文件
+    ///
+    /// 先显示预览表格，确认后整批执行；任何一个失败都会回滚已完成的部分。
+    /// 文件按 options 排序后依次编号，pattern 用于筛选文件名（与 find 相同）。
+    pub fn rename(&self, dir: &str, pattern: Option<&str>, rules: &RenameRules, options: &ListOptions) -> Result<()> {
+        let dir_path = Path::new(dir);
+        self.guard(dir_path)?;
+        
+        if !dir_path.is_dir() {
+            return Err(FileUtilsError::NotADirectory { path: dir_path.to_path_buf() });
+        }
+        
+        if rules.is_empty() {
+            return Err(FileUtilsError::InvalidInput(
+                "rename 至少需要一条规则: --find/--regex、--template、--case 或 --ext".to_string(),
+            ));
+        }
+        
+        let names: Vec<String> = read_records(dir_path, options, false)?
+            .into_iter()
+            .filter(|record| record.kind == FileKind::File)
+            .filter(|record| pattern.is_none_or(|pattern| self.pattern_match(&record.name, pattern)))
+            .map(|record| record.name)
+            .collect();
+        
+        let renames = rename::plan(dir_path, &names, rules)?;
+        
+        if renames.is_empty() {
+            println!("没有需要重命名的文件");
+            return Ok(());
+        }
+        
+        println!("✏️  重命名预览: {}", dir);
+        println!("{}", "=".repeat(60));
+        
+        let width = renames.iter().map(|r| file_name_of(&r.from).chars().count()).max().unwrap_or(0);
+        for r in &renames {
+            let from = file_name_of(&r.from);
+            let padding = " ".repeat(width - from.chars().count());
+            println!("  {}{}  ->  {}", from, padding, file_name_of(&r.to));
+        }
+        
+        println!("{}", "=".repeat(60));
+        println!("共 {} 个文件需要重命名，{} 个不变", renames.len(), names.len() - renames.len());
+        
+        if self.dry_run {
+            return Ok(());
+        }
+        
+        if !confirm(&format!("确定要重命名这 {} 个文件吗? (y/N): ", renames.len()))? {
+            println!("操作已取消");
+            return Ok(());
+        }
+        
+        rename::execute(&renames)?;
+        
+        // 每个文件单独记录为一次移动，undo N 可以逐个撤销
+        for r in &renames {
+            self.journal.record(Operation::Move {
+                source: path::absolute(&r.from).with_path(&r.from)?,
+                destination: path::absolute(&r.to).with_path(&r.to)?,
+            })?;
+        }
+        
+        println!("✅ 已重命名 {} 个文件 (可用 undo {} 撤销)", renames.len(), renames.len());
+        
+        Ok(())
+    }
+    
+    /// 把源目录同步到目标目录：只复制新增或变化的文件，不逐个询问
+    ///
+    /// 复制后保留源文件的修改时间，下次按大小和时间比较时不会重复传输。
+    /// 被覆盖和被 `--delete` 删除的文件都移入回收站，可以用 undo 撤销。
+    pub fn sync(&self, source: &str, destination: &str, options: &SyncOptions) -> Result<()> {
+        let source_path = Path::new(source);
+        let dest_path = Path::new(destination);
+        self.guard(source_path)?;
+        self.guard(dest_path)?;
+        
+        if !source_path.is_dir() {
+            return Err(FileUtilsError::NotADirectory { path: source_path.to_path_buf() });
+        }
+        
+        let plan = sync::plan(source_path, dest_path, options)?;
+        let prefix = if self.dry_run { "[演练] " } else { "" };
+        
+        println!("🔄 同步: {} -> {}", source, destination);
+        println!("{}", "=".repeat(60));
+        
+        for transfer in &plan.transfers {
+            let action = if transfer.update { "更新" } else { "新增" };
+            println!("{}{} {} ({})", prefix, action, transfer.relative.display(), utils::format_size(transfer.size));
+            
+            if !self.dry_run {
+                let from = source_path.join(&transfer.relative);
+                let to = dest_path.join(&transfer.relative);
+                self.copy_file(&from, &to)?;
+                
+                let modified = fs::metadata(&from).with_path(&from)?.modified().with_path(&from)?;
+                fs::File::open(&to).and_then(|file| file.set_modified(modified)).with_path(&to)?;
+            }
+        }
+        
+        for relative in &plan.extraneous {
+            println!("{}删除 {}", prefix, relative.display());
+            
+            if !self.dry_run {
+                self.trash_path(&dest_path.join(relative))?;
+            }
+        }
+        
+        for relative in &plan.skipped {
+            println!("跳过 {} (符号链接或特殊文件)", relative.display());
+        }
+        
+        let created = plan.transfers.iter().filter(|t| !t.update).count();
+        println!("{}", "=".repeat(60));
+        println!("📊 新增 {} 个，更新 {} 个，删除 {} 个，未变 {} 个，{}传输 {}",
+                created,
+                plan.transfers.len() - created,
+                plan.extraneous.len(),
+                plan.unchanged,
+                if self.dry_run { "将" } else { "" },
+                utils::format_size(plan.transfer_bytes()));
+        
+        Ok(())
+    }
+    
+    /// 持续监控目录的变化，每批防抖合并后的事件交给 report 输出
+    ///
+    /// 设置了 exec 时每批事件之后运行一次命令。出错或在 shell 中按下 Ctrl-C 时返回。
+    pub fn watch<F>(&self, dir: &str, options: &WatchOptions, mut report: F) -> Result<()>
+    where
+        F: FnMut(&[ChangeEvent]) -> Result<()>,
+    {
+        let dir_path = Path::new(dir);
+        self.guard(dir_path)?;
+        
+        if !dir_path.is_dir() {
+            return Err(FileUtilsError::NotADirectory { path: dir_path.to_path_buf() });
+        }
+        
+        let filter = Filter::new(&options.include, &options.exclude)?;
+        let mut watcher = Watcher::new(dir_path, filter, options.debounce)?;
+        
+        // Ctrl-C 打断 poll 时 next_batch 返回空列表，回到这里检查标志
+        while !interrupt::requested() {
+            let events = watcher.next_batch(None)?;
+            if events.is_empty() {
+                continue;
+            }
+            
+            report(&events)?;
+            
+            if let Some(command) = &options.exec {
+                watch::run_command(command, &events)?;
+            }
+        }
+        Ok(())
+    }
+    
+    /// 把文件或目录打包为 .tar、.tar.gz/.tgz 或 .zip
+    pub fn pack(&self, source: &str, archive_path: &str) -> Result<()> {
+        let source_path = Path::new(source);
+        let archive_file = Path::new(archive_path);
+        self.guard(source_path)?;
+        self.guard(archive_file)?;
+        
+        if fs::symlink_metadata(source_path).is_err() {
+            return Err(FileUtilsError::NotFound { path: source_path.to_path_buf() });
+        }
+        
+        if archive_file.exists() {
+            return Err(FileUtilsError::AlreadyExists { path: archive_file.to_path_buf() });
+        }
+        
+        archive::ArchiveFormat::from_path(archive_file)?;
+        
+        if self.dry_run {
+            println!("[演练] 打包 {} -> {}", source, archive_path);
+            return Ok(());
+        }
+        
+        let count = archive::pack(source_path, archive_file)?;
+        let size = fs::metadata(archive_file).with_path(archive_file)?.len();
+        println!("📦 已打包 {} 个条目: {} ({})", count, archive_path, utils::format_size(size));
+        
+        Ok(())
+    }
+    
+    /// 把归档解包到目录，拒绝会逃出目标目录的条目，不覆盖已有文件
+    pub fn unpack(&self, archive_path: &str, dest: &str) -> Result<()> {
+        let archive_file = Path::new(archive_path);
+        let dest_path = Path::new(dest);
+        self.guard(archive_file)?;
+        self.guard(dest_path)?;
+        
+        if self.dry_run {
+            let records = archive::list(archive_file)?;
+            println!("[演练] 解包 {} 个条目: {} -> {}", records.len(), archive_path, dest);
+            return Ok(());
+        }
+        
+        let count = archive::unpack(archive_file, dest_path)?;
+        println!("📂 已解包 {} 个条目到 {}", count, dest);
+        
+        Ok(())
+    }
+    
+    /// 列出归档中的条目
+    pub fn archive_list(&self, archive_path: &str) -> Result<Vec<FileRecord>> {
+        let archive_file = Path::new(archive_path);
+        self.guard(archive_file)?;
+        
+        archive::list(archive_file)
+    }
+    
+    /// 计算文件或目录大小，目录的 size 为递归总大小
+    pub fn calculate_size(&self, path: &str) -> Result<FileRecord> {
+        let target_path = Path::new(path);
+        self.guard(target_path)?;
+        
+        // 参数本身是符号链接时同样按 follow_links 处理，不跟随时只计算链接本身
+        let metadata = links::metadata(target_path, self.follow_links)?;
+        let mut record = FileRecord::from_metadata(target_path, &metadata);
+        record.size = self.get_size_recursive(target_path, &metadata, &mut Ancestors::default(), &mut Seen::default())?;
+        
+        Ok(record)
+    }
+    
+    /// 递归计算目录大小；同一个文件（硬链接）只计算一次，跟随符号链接时跳过循环
+    fn get_size_recursive(&self, path: &Path, metadata: &fs::Metadata, ancestors: &mut Ancestors, seen: &mut Seen) -> Result<u64> {
+        if metadata.is_file() {
+            Ok(if seen.first_visit(metadata) { metadata.len() } else { 0 })
+        } else if metadata.is_dir() {
+            if !ancestors.enter(path, metadata) {
+                return Ok(0);
+            }
+            
+            let mut total_size = 0;
+            
+            for entry in fs::read_dir(path).with_path(path)? {
+                let entry = entry.with_path(path)?;
+                if self.follow_links && entry.file_type().with_path(entry.path())?.is_symlink() {
+                    self.guard(&entry.path())?;
+                }
+                let entry_metadata = links::metadata(&entry.path(), self.follow_links)?;
+                total_size += self.get_size_recursive(&entry.path(), &entry_metadata, ancestors, seen)?;
+            }
+            
+            ancestors.leave();
+            Ok(total_size)
+        } else {
+            Ok(0)
+        }
+    }
+}
+
+/// 一组内容相同的文件
+#[derive(Debug)]
+struct DuplicateGroup {
+    size: u64,
+    files: Vec<PathBuf>,
+}
+
+impl DuplicateGroup {
+    /// 只保留一份副本时可释放的空间
+    fn reclaimable(&self) -> u64 {
+        self.size * (self.files.len() as u64 - 1)
+    }
+}
+
+/// 两个目录树的差异，路径均相对于各自的根目录
+#[derive(Debug)]
+struct TreeDiff {
+    only_left: Vec<PathBuf>,
+    only_right: Vec<PathBuf>,
+    changed: Vec<PathBuf>,
+}
+
+impl TreeDiff {
+    fn is_empty(&self) -> bool {
+        self.only_left.is_empty() && self.only_right.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// list 的排序字段，对应 `--sort`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SortKey {
+    #[default]
+    Name,
+    Size,
+    Mtime,
+    Ext,
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "name" => Ok(SortKey::Name),
+            "size" => Ok(SortKey::Size),
+            "mtime" | "time" => Ok(SortKey::Mtime),
+            "ext" | "extension" => Ok(SortKey::Ext),
+            other => Err(format!("无效的排序字段: {} (可选: name, size, mtime, ext)", other)),
+        }
+    }
+}
+
+/// list 和 tree 共用的过滤与排序选项
+#[derive(Debug, Clone, Default)]
+pub struct ListOptions {
+    /// 是否显示以点开头的隐藏文件
+    pub all: bool,
+    pub sort: SortKey,
+    pub reverse: bool,
+}
+
+impl ListOptions {
+    fn sort(&self, records: &mut [FileRecord]) {
+        // 名称总是作为次要排序键，保证结果稳定
+        match self.sort {
+            SortKey::Name => records.sort_by(|a, b| a.name.cmp(&b.name)),
+            SortKey::Size => records.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name))),
+            SortKey::Mtime => records.sort_by(|a, b| b.mtime.cmp(&a.mtime).then_with(|| a.name.cmp(&b.name))),
+            SortKey::Ext => records.sort_by(|a, b| a.extension().cmp(b.extension()).then_with(|| a.name.cmp(&b.name))),
+        }
+        
+        if self.reverse {
+            records.reverse();
+        }
+    }
+}
+
+/// 读取一个目录下的条目（不跟随符号链接），过滤并排序
+fn read_records(dir: &Path, options: &ListOptions, follow: bool) -> Result<Vec<FileRecord>> {
+    let mut records = Vec::new();
+    
+    for entry in fs::read_dir(dir).with_path(dir)? {
+        let entry = entry.with_path(dir)?;
+        let record = FileRecord::from_metadata(&entry.path(), &links::metadata(&entry.path(), follow)?);
+        if options.all || !record.is_hidden() {
+            records.push(record);
+        }
+    }
+    
+    options.sort(&mut records);
+    Ok(records)
+}
+
+impl Default for FileManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 如果目标是目录，则在目录中使用源文件名
+fn resolve_destination(source: &Path, destination: &Path) -> Result<PathBuf> {
+    if destination.is_dir() {
+        let filename = source.file_name()
+            .ok_or_else(|| FileUtilsError::InvalidInput(format!("无法获取源文件名: {}", source.display())))?;
+        Ok(destination.join(filename))
+    } else {
+        Ok(destination.to_path_buf())
+    }
+}
+
+/// 先访问 path 本身，recursive 时再按名称顺序访问目录内容；
+/// path 本身是符号链接时只在 follow 时跟随，目录内的符号链接作为普通条目传给 visit 而不进入
+fn walk_entries(path: &Path, recursive: bool, follow: bool, visit: &mut dyn FnMut(&Path, &fs::Metadata) -> Result<()>) -> Result<()> {
+    let metadata = links::metadata(path, follow)?;
+    visit(path, &metadata)?;
+    
+    if recursive && metadata.is_dir() {
+        walk_children(path, visit)?;
+    }
+    Ok(())
+}
+
+fn walk_children(dir: &Path, visit: &mut dyn FnMut(&Path, &fs::Metadata) -> Result<()>) -> Result<()> {
+    let mut entries = fs::read_dir(dir)
+        .with_path(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<io::Result<Vec<_>>>()
+        .with_path(dir)?;
+    entries.sort();
+    
+    for entry in entries {
+        let metadata = fs::symlink_metadata(&entry).with_path(&entry)?;
+        visit(&entry, &metadata)?;
+        if metadata.is_dir() {
+            walk_children(&entry, visit)?;
+        }
+    }
+    Ok(())
+}
+
+fn file_name_of(path: &Path) -> String {
+    path.file_name().unwrap_or_default().to_string_lossy().to_string()
+}
+
+/// 询问用户确认
+fn confirm(prompt: &str) -> Result<bool> {
+    print!("{}", prompt);
+    io::Write::flush(&mut io::stdout()).with_path("<stdout>")?;
+    
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).with_path("<stdin>")?;
+    
+    Ok(input.trim().to_lowercase().starts_with('y'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    
+    fn manager_in(dir: &TempDir) -> FileManager {
+        FileManager::new()
+            .with_journal(Journal::new(dir.path().join("data")))
+            .with_trash(Trash::new(dir.path().join("Trash")))
+    }
+    
+    fn path_str(path: &Path) -> &str {
+        path.to_str().unwrap()
+    }
+    
+    #[test]
+    fn test_undo_copy() {
+        let dir = TempDir::new().unwrap();
+        let manager = manager_in(&dir);
+        let source = dir.path().join("a.txt");
+        let dest = dir.path().join("b.txt");
+        fs::write(&source, "hello").unwrap();
+        
+        manager.copy(path_str(&source), path_str(&dest)).unwrap();
+        assert!(dest.exists());
+        
+        manager.undo(1).unwrap();
+        assert!(!dest.exists());
+        assert!(source.exists());
+    }
+    
+    #[test]
+    fn test_undo_move_restores_source() {
+        let dir = TempDir::new().unwrap();
+        let manager = manager_in(&dir);
+        let source = dir.path().join("a.txt");
+        let dest = dir.path().join("sub").join("b.txt");
+        fs::write(&source, "hello").unwrap();
+        
+        manager.move_file(path_str(&source), path_str(&dest)).unwrap();
+        assert!(!source.exists());
+        assert!(dest.exists());
+        
+        manager.undo(1).unwrap();
+        assert_eq!(fs::read_to_string(&source).unwrap(), "hello");
+        assert!(!dest.exists());
+    }
+    
+    #[test]
+    fn test_undo_multiple_in_reverse_order() {
+        let dir = TempDir::new().unwrap();
+        let manager = manager_in(&dir);
+        let a = dir.path().join("a.txt");
+        let b = dir.path().join("b.txt");
+        let c = dir.path().join("c.txt");
+        fs::write(&a, "hello").unwrap();
+        
+        manager.move_file(path_str(&a), path_str(&b)).unwrap();
+        manager.move_file(path_str(&b), path_str(&c)).unwrap();
+        
+        manager.undo(5).unwrap();
+        assert!(a.exists());
+        assert!(!b.exists());
+        assert!(!c.exists());
+        assert!(manager.journal.entries().unwrap().is_empty());
+    }
+    
+    #[test]
+    fn test_undo_delete_restores_from_trash() {
+        let dir = TempDir::new().unwrap();
+        let manager = manager_in(&dir);
+        let file = dir.path().join("a.txt");
+        fs::write(&file, "hello").unwrap();
+        
+        // delete 需要交互确认，这里直接模拟它的效果
+        let trashed = manager.trash.put(&file).unwrap();
+        manager.journal.record(Operation::Delete {
+            path: file.clone(),
+            trashed,
+        }).unwrap();
+        assert!(!file.exists());
+        
+        manager.undo(1).unwrap();
+        assert_eq!(fs::read_to_string(&file).unwrap(), "hello");
+        assert!(manager.trash.list().unwrap().is_empty());
+    }
+    
+    #[test]
+    fn test_find_duplicates() {
+        let dir = TempDir::new().unwrap();
+        let manager = manager_in(&dir);
+        let root = dir.path().join("files");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("a.txt"), "same content").unwrap();
+        fs::write(root.join("sub").join("b.txt"), "same content").unwrap();
+        fs::write(root.join("c.txt"), "diff content").unwrap(); // 大小相同但内容不同
+        fs::write(root.join("d.txt"), "unique").unwrap();
+        
+        let groups = manager.find_duplicates(&root, HashAlgorithm::Sha256).unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].size, 12);
+        assert_eq!(groups[0].reclaimable(), 12);
+        assert_eq!(groups[0].files, vec![root.join("a.txt"), root.join("sub").join("b.txt")]);
+    }
+    
+    #[test]
+    fn test_compare_trees() {
+        let dir = TempDir::new().unwrap();
+        let manager = manager_in(&dir);
+        let left = dir.path().join("left");
+        let right = dir.path().join("right");
+        fs::create_dir_all(left.join("sub")).unwrap();
+        fs::create_dir_all(right.join("sub")).unwrap();
+        fs::write(left.join("same.txt"), "1").unwrap();
+        fs::write(right.join("same.txt"), "1").unwrap();
+        fs::write(left.join("sub").join("changed.txt"), "old").unwrap();
+        fs::write(right.join("sub").join("changed.txt"), "new").unwrap();
+        fs::write(left.join("left_only.txt"), "").unwrap();
+        fs::write(right.join("right_only.txt"), "").unwrap();
+        
+        let diff = manager.compare_trees(&left, &right).unwrap();
+        assert_eq!(diff.only_left, vec![PathBuf::from("left_only.txt")]);
+        assert_eq!(diff.only_right, vec![PathBuf::from("right_only.txt")]);
+        assert_eq!(diff.changed, vec![Path::new("sub").join("changed.txt")]);
+        
+        assert!(manager.compare_trees(&left, &left).unwrap().is_empty());
+    }
+    
+    #[test]
+    fn test_sync_is_idempotent_and_undoable() {
+        let dir = TempDir::new().unwrap();
+        let manager = manager_in(&dir);
+        let src = dir.path().join("src");
+        let dst = dir.path().join("dst");
+        fs::create_dir_all(src.join("sub")).unwrap();
+        fs::create_dir_all(&dst).unwrap();
+        fs::write(src.join("a.txt"), "new").unwrap();
+        fs::write(src.join("sub/b.txt"), "b").unwrap();
+        fs::write(dst.join("a.txt"), "old version").unwrap();
+        fs::write(dst.join("extra.txt"), "extra").unwrap();
+        
+        let options = SyncOptions { delete: true, ..Default::default() };
+        manager.sync(path_str(&src), path_str(&dst), &options).unwrap();
+        assert_eq!(fs::read_to_string(dst.join("a.txt")).unwrap(), "new");
+        assert_eq!(fs::read_to_string(dst.join("sub/b.txt")).unwrap(), "b");
+        assert!(!dst.join("extra.txt").exists());
+        
+        // 修改时间已同步，再次比较时没有需要传输的文件
+        let again = sync::plan(&src, &dst, &options).unwrap();
+        assert!(again.transfers.is_empty() && again.extraneous.is_empty());
+        
+        manager.undo(3).unwrap();
+        assert_eq!(fs::read_to_string(dst.join("a.txt")).unwrap(), "old version");
+        assert_eq!(fs::read_to_string(dst.join("extra.txt")).unwrap(), "extra");
+        assert!(!dst.join("sub/b.txt").exists());
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn test_chmod_and_touch_recursive() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+        
+        let dir = TempDir::new().unwrap();
+        let manager = manager_in(&dir);
+        let root = dir.path().join("site");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("sub/page.html"), "x").unwrap();
+        let outside = dir.path().join("outside.txt");
+        fs::write(&outside, "o").unwrap();
+        fs::set_permissions(&outside, fs::Permissions::from_mode(0o600)).unwrap();
+        symlink(&outside, root.join("link")).unwrap();
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o7777;
+        
+        manager.chmod(path_str(&root), &"a=rX,u+w".parse().unwrap(), true).unwrap();
+        assert_eq!(mode(&root.join("sub")), 0o755);
+        assert_eq!(mode(&root.join("sub/page.html")), 0o644);
+        // 目录中的符号链接不会被跟随
+        assert_eq!(mode(&outside), 0o600);
+        
+        manager.touch(path_str(&root), utils::parse_time("@1600000000"), true).unwrap();
+        let mtime = |path: &Path| fs::symlink_metadata(path).unwrap().modified().unwrap();
+        assert_eq!(mtime(&root.join("sub/page.html")), UNIX_EPOCH + Duration::from_secs(1_600_000_000));
+        assert_eq!(mtime(&root.join("link")), UNIX_EPOCH + Duration::from_secs(1_600_000_000));
+        assert_ne!(mtime(&outside), UNIX_EPOCH + Duration::from_secs(1_600_000_000));
+        
+        let key = root.join("secret.key");
+        manager.create(path_str(&key), Some(&"600".parse().unwrap())).unwrap();
+        assert_eq!(mode(&key), 0o600);
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn test_links_loops_and_hard_link_dedupe() {
+        let dir = TempDir::new().unwrap();
+        let manager = manager_in(&dir);
+        let root = dir.path().join("tree");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("sub/data.bin"), vec![0u8; 1000]).unwrap();
+        
+        manager.link(path_str(&root.join("sub/data.bin")), path_str(&root.join("copy.bin")), false).unwrap();
+        manager.link("..", path_str(&root.join("sub/loop")), true).unwrap();
+        assert!(matches!(manager.link("x", path_str(&root.join("copy.bin")), true), Err(FileUtilsError::AlreadyExists { .. })));
+        assert!(matches!(manager.link(path_str(&root), path_str(&root.join("dir")), false), Err(FileUtilsError::IsADirectory { .. })));
+        
+        // 硬链接只计算一次；跟随时 loop 指回 tree 本身，会被识别为循环而不是无限递归
+        assert_eq!(manager.calculate_size(path_str(&root)).unwrap().size, 1000);
+        let follower = manager_in(&dir).follow_links(true);
+        assert_eq!(follower.calculate_size(path_str(&root)).unwrap().size, 1000);
+        assert_eq!(follower.find(path_str(&root), "*.bin").unwrap().len(), 2);
+        let tree = follower.tree(path_str(&root), None, &ListOptions::default()).unwrap();
+        let sub = tree.children.iter().find(|node| node.record.name == "sub").unwrap();
+        assert!(sub.children.iter().any(|node| node.record.name == "loop" && node.children.is_empty()));
+        
+        let info = manager.info(path_str(&root.join("sub/loop"))).unwrap();
+        assert_eq!(info.record.kind, FileKind::Symlink);
+        assert_eq!(info.record.target, Some(PathBuf::from("..")));
+        assert_eq!(manager.info(path_str(&root.join("copy.bin"))).unwrap().links, Some(2));
+        
+        manager.undo(2).unwrap();
+        assert!(root.join("sub/loop").symlink_metadata().is_err());
+        assert!(!root.join("copy.bin").exists());
+        assert!(root.join("sub/data.bin").exists());
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_argument_respects_follow() {
+        use std::os::unix::fs::PermissionsExt;
+        
+        let dir = TempDir::new().unwrap();
+        let manager = manager_in(&dir);
+        let root = dir.path().join("tree");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("data.bin"), vec![0u8; 1000]).unwrap();
+        let link = dir.path().join("link");
+        manager.link(path_str(&root), path_str(&link), true).unwrap();
+        
+        // 不跟随时与目录中的符号链接一样不计入大小
+        let record = manager.calculate_size(path_str(&link)).unwrap();
+        assert_eq!(record.kind, FileKind::Symlink);
+        assert_eq!(record.size, 0);
+        let follower = manager_in(&dir).follow_links(true);
+        assert_eq!(follower.calculate_size(path_str(&link)).unwrap().size, 1000);
+        
+        // chmod 不跟随时跳过作为参数的链接，跟随时修改它指向的目录
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        manager.chmod(path_str(&link), &"700".parse().unwrap(), false).unwrap();
+        assert_ne!(mode(&root), 0o700);
+        follower.chmod(path_str(&link), &"700".parse().unwrap(), false).unwrap();
+        assert_eq!(mode(&root), 0o700);
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn test_dupes_ignore_hard_links() {
+        let dir = TempDir::new().unwrap();
+        let manager = manager_in(&dir);
+        let root = dir.path().join("files");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("a.txt"), "same content").unwrap();
+        fs::hard_link(root.join("a.txt"), root.join("b.txt")).unwrap();
+        
+        // 同一个 inode 的硬链接不算重复
+        assert!(manager.find_duplicates(&root, HashAlgorithm::Sha256).unwrap().is_empty());
+        
+        fs::write(root.join("c.txt"), "same content").unwrap();
+        let groups = manager.find_duplicates(&root, HashAlgorithm::Sha256).unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].reclaimable(), 12);
+        assert_eq!(groups[0].files.len(), 2);
+        assert!(groups[0].files.contains(&root.join("c.txt")));
+    }
+    
+    #[test]
+    fn test_list_returns_sorted_records() {
+        let dir = TempDir::new().unwrap();
+        let manager = manager_in(&dir);
+        let root = dir.path().join("files");
+        fs::create_dir_all(root.join("b_dir")).unwrap();
+        fs::write(root.join("c.txt"), "12345").unwrap();
+        fs::write(root.join("a.txt"), "").unwrap();
+        
+        let records = manager.list(path_str(&root), &ListOptions::default()).unwrap();
+        let names: Vec<&str> = records.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["a.txt", "b_dir", "c.txt"]);
+        assert_eq!(records[1].kind, FileKind::Dir);
+        assert_eq!(records[2].size, 5);
+        
+        let total = manager.calculate_size(path_str(&root)).unwrap();
+        assert_eq!(total.kind, FileKind::Dir);
+        assert_eq!(total.size, 5);
+    }
+    
+    #[test]
+    fn test_list_options() {
+        let dir = TempDir::new().unwrap();
+        let manager = manager_in(&dir);
+        let root = dir.path().join("files");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("b.rs"), "1").unwrap();
+        fs::write(root.join("a.txt"), "12345").unwrap();
+        fs::write(root.join("c.md"), "123").unwrap();
+        fs::write(root.join(".hidden"), "").unwrap();
+        
+        let names = |options: &ListOptions| -> Vec<String> {
+            manager.list(path_str(&root), options).unwrap().into_iter().map(|r| r.name).collect()
+        };
+        
+        assert_eq!(names(&ListOptions::default()), vec!["a.txt", "b.rs", "c.md"]);
+        assert_eq!(names(&ListOptions { all: true, ..Default::default() }), vec![".hidden", "a.txt", "b.rs", "c.md"]);
+        assert_eq!(names(&ListOptions { sort: SortKey::Size, ..Default::default() }), vec!["a.txt", "c.md", "b.rs"]);
+        assert_eq!(names(&ListOptions { sort: SortKey::Ext, ..Default::default() }), vec!["c.md", "b.rs", "a.txt"]);
+        assert_eq!(
+            names(&ListOptions { sort: SortKey::Ext, reverse: true, ..Default::default() }),
+            vec!["a.txt", "b.rs", "c.md"]
+        );
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn test_tree_depth_and_symlinks() {
+        let dir = TempDir::new().unwrap();
+        let manager = manager_in(&dir);
+        let root = dir.path().join("files");
+        fs::create_dir_all(root.join("a/b/c")).unwrap();
+        fs::write(root.join("a/b/c/deep.txt"), "").unwrap();
+        std::os::unix::fs::symlink("a", root.join("link")).unwrap();
+        
+        let tree = manager.tree(path_str(&root), Some(2), &ListOptions::default()).unwrap();
+        assert_eq!(tree.children.len(), 2);
+        
+        let a = &tree.children[0];
+        assert_eq!(a.children[0].record.name, "b");
+        assert!(a.children[0].children.is_empty());
+        
+        // 符号链接不会被展开
+        let link = &tree.children[1];
+        assert_eq!(link.record.kind, FileKind::Symlink);
+        assert_eq!(link.record.target, Some(PathBuf::from("a")));
+        assert!(link.children.is_empty());
+        
+        let full = manager.tree(path_str(&root), None, &ListOptions::default()).unwrap();
+        assert_eq!(full.children[0].children[0].children[0].children[0].record.name, "deep.txt");
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn test_sandbox_refuses_symlink_escape() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().join("jail");
+        let outside = dir.path().join("outside");
+        fs::create_dir_all(&root).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        fs::write(outside.join("secret.txt"), "secret").unwrap();
+        fs::write(root.join("a.txt"), "hello").unwrap();
+        std::os::unix::fs::symlink(&outside, root.join("escape")).unwrap();
+        
+        let manager = manager_in(&dir).sandbox(Some(Sandbox::new(path_str(&root)).unwrap()));
+        let escaped = |result: Result<()>| matches!(result, Err(FileUtilsError::OutsideRoot { .. }));
+        
+        assert!(escaped(manager.copy(path_str(&root.join("escape/secret.txt")), path_str(&root.join("b.txt")))));
+        assert!(escaped(manager.copy(path_str(&root.join("a.txt")), path_str(&root.join("escape/stolen.txt")))));
+        assert!(escaped(manager.delete(path_str(&root.join("escape/secret.txt")), true)));
+        assert!(escaped(manager.create(path_str(&root.join("../outside/new.txt")), None)));
+        // 默认不跟随符号链接，只有 --follow 时才会因为链接逃出根目录而拒绝
+        assert_eq!(manager.calculate_size(path_str(&root)).unwrap().size, 5);
+        let follower = manager_in(&dir).sandbox(Some(Sandbox::new(path_str(&root)).unwrap())).follow_links(true);
+        assert!(escaped(follower.calculate_size(path_str(&root)).map(|_| ())));
+        assert!(escaped(follower.find(path_str(&root), "*").map(|_| ())));
+        assert!(outside.join("secret.txt").exists());
+        assert!(!outside.join("stolen.txt").exists());
+        assert!(!outside.join("new.txt").exists());
+        
+        assert!(manager.info(path_str(&root.join("a.txt"))).is_ok());
+        assert!(manager.list(path_str(&root), &ListOptions::default()).is_ok());
+    }
+    
+    #[test]
+    fn test_typed_errors() {
+        let dir = TempDir::new().unwrap();
+        let manager = manager_in(&dir);
+        let file = dir.path().join("a.txt");
+        let missing = dir.path().join("missing.txt");
+        fs::write(&file, "hello").unwrap();
+        
+        match manager.info(path_str(&missing)) {
+            Err(FileUtilsError::NotFound { path }) => assert_eq!(path, missing),
+            other => panic!("expected NotFound, got {:?}", other),
+        }
+        assert!(matches!(manager.create(path_str(&file), None), Err(FileUtilsError::AlreadyExists { .. })));
+        assert!(matches!(manager.list(path_str(&file), &ListOptions::default()), Err(FileUtilsError::NotADirectory { .. })));
+        assert!(matches!(manager.copy(path_str(dir.path()), path_str(&missing)), Err(FileUtilsError::IsADirectory { .. })));
+        assert!(matches!(manager.trash_restore("nothing"), Err(FileUtilsError::NotInTrash { .. })));
+    }
+    
+    #[test]
+    fn test_dry_run_does_not_touch_files() {
+        let dir = TempDir::new().unwrap();
+        let manager = manager_in(&dir).dry_run(true);
+        let source = dir.path().join("a.txt");
+        let dest = dir.path().join("b.txt");
+        fs::write(&source, "hello").unwrap();
+        
+        manager.copy(path_str(&source), path_str(&dest)).unwrap();
+        manager.move_file(path_str(&source), path_str(&dest)).unwrap();
+        manager.delete(path_str(&source), false).unwrap();
+        manager.delete(path_str(&source), true).unwrap();
+        
+        assert!(source.exists());
+        assert!(!dest.exists());
+        assert!(manager.journal.entries().unwrap().is_empty());
     }
-}
\ No newline at end of file
+}
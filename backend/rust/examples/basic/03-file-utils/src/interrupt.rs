@@ -0,0 +1,34 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// shell 中按下 Ctrl-C 后置位，持续运行的命令（watch、view -f）看到后返回
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// 按下 Ctrl-C 时进程的退出码习惯上是 128 + SIGINT
+pub const EXIT_INTERRUPTED: i32 = 130;
+
+/// 捕获 SIGINT：之后 Ctrl-C 只设置标志而不结束进程，用于 shell 中回到提示符
+///
+/// 命令行模式不调用它，Ctrl-C 仍按默认行为结束进程
+pub fn catch() {
+    #[cfg(unix)]
+    {
+        extern "C" fn on_sigint(_: libc::c_int) {
+            INTERRUPTED.store(true, Ordering::SeqCst);
+        }
+
+        // SAFETY: 处理函数只写一个原子变量，是异步信号安全的
+        unsafe {
+            libc::signal(libc::SIGINT, on_sigint as *const () as libc::sighandler_t);
+        }
+    }
+}
+
+/// 是否收到了 Ctrl-C
+pub fn requested() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// 取出并清除标志，返回之前是否收到了 Ctrl-C
+pub fn take() -> bool {
+    INTERRUPTED.swap(false, Ordering::SeqCst)
+}
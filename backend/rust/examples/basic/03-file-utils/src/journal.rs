@@ -0,0 +1,237 @@
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::{FileUtilsError, PathContext, Result};
+
+/// 一次已完成的文件操作，记录足够的信息以便撤销
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum Operation {
+    /// 复制文件；如果覆盖了已有文件，原文件被移入回收站的 backup 位置
+    Copy {
+        source: PathBuf,
+        destination: PathBuf,
+        backup: Option<PathBuf>,
+    },
+    /// 移动/重命名文件
+    Move {
+        source: PathBuf,
+        destination: PathBuf,
+    },
+    /// 删除文件，实际被移入回收站的 trashed 位置
+    Delete {
+        path: PathBuf,
+        trashed: PathBuf,
+    },
+    /// 创建符号链接或硬链接，撤销时删除链接本身
+    Link {
+        target: PathBuf,
+        link: PathBuf,
+        symbolic: bool,
+    },
+}
+
+impl Operation {
+    /// 撤销时会改动的用户路径（不含回收站内部的路径）
+    pub fn paths(&self) -> Vec<&Path> {
+        match self {
+            Operation::Copy { destination, .. } => vec![destination.as_path()],
+            Operation::Move { source, destination } => vec![source.as_path(), destination.as_path()],
+            Operation::Delete { path, .. } => vec![path.as_path()],
+            Operation::Link { link, .. } => vec![link.as_path()],
+        }
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operation::Copy { source, destination, backup } => {
+                write!(f, "复制 {} -> {}", source.display(), destination.display())?;
+                if backup.is_some() {
+                    write!(f, " (覆盖已有文件)")?;
+                }
+                Ok(())
+            },
+            Operation::Move { source, destination } => {
+                write!(f, "移动 {} -> {}", source.display(), destination.display())
+            },
+            Operation::Delete { path, .. } => {
+                write!(f, "删除 {}", path.display())
+            },
+            Operation::Link { target, link, symbolic: true } => {
+                write!(f, "创建符号链接 {} -> {}", link.display(), target.display())
+            },
+            Operation::Link { target, link, symbolic: false } => {
+                write!(f, "创建硬链接 {} => {}", link.display(), target.display())
+            },
+        }
+    }
+}
+
+/// 日志中的一条记录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub timestamp: u64,
+    #[serde(flatten)]
+    pub operation: Operation,
+}
+
+/// 操作日志：每行一条 JSON 记录，追加写入
+pub struct Journal {
+    path: PathBuf,
+}
+
+impl Journal {
+    /// 在指定的数据目录中创建日志
+    pub fn new<P: AsRef<Path>>(data_dir: P) -> Self {
+        Journal {
+            path: data_dir.as_ref().join("journal.jsonl"),
+        }
+    }
+
+    /// 使用默认数据目录创建日志
+    ///
+    /// 优先使用 `FILE_UTILS_HOME`，其次是 `$XDG_DATA_HOME/file-utils`，
+    /// 最后是 `~/.local/share/file-utils`。
+    pub fn open_default() -> Self {
+        Journal::new(default_data_dir())
+    }
+
+    /// 追加一条操作记录
+    pub fn record(&self, operation: Operation) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).with_path(parent)?;
+        }
+
+        let entry = JournalEntry {
+            timestamp: current_timestamp(),
+            operation,
+        };
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_path(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(&entry)?).with_path(&self.path)?;
+
+        Ok(())
+    }
+
+    /// 读取全部记录（按时间先后）
+    pub fn entries(&self) -> Result<Vec<JournalEntry>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = fs::read_to_string(&self.path).with_path(&self.path)?;
+        let mut entries = Vec::new();
+
+        for (index, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry = serde_json::from_str(line).map_err(|e| FileUtilsError::Corrupt {
+                path: self.path.clone(),
+                message: format!("第 {} 行: {}", index + 1, e),
+            })?;
+            entries.push(entry);
+        }
+
+        Ok(entries)
+    }
+
+    /// 移除最后一条记录
+    pub fn remove_last(&self) -> Result<Option<JournalEntry>> {
+        let mut entries = self.entries()?;
+        let last = entries.pop();
+
+        if last.is_some() {
+            let mut content = String::new();
+            for entry in &entries {
+                content.push_str(&serde_json::to_string(entry)?);
+                content.push('\n');
+            }
+            fs::write(&self.path, content).with_path(&self.path)?;
+        }
+
+        Ok(last)
+    }
+}
+
+/// 日志、shell 历史等数据文件所在的目录
+pub fn default_data_dir() -> PathBuf {
+    if let Some(dir) = env::var_os("FILE_UTILS_HOME") {
+        return PathBuf::from(dir);
+    }
+
+    if let Some(dir) = env::var_os("XDG_DATA_HOME") {
+        return PathBuf::from(dir).join("file-utils");
+    }
+
+    let home = env::var_os("HOME").map(PathBuf::from).unwrap_or_else(|| PathBuf::from("."));
+    home.join(".local").join("share").join("file-utils")
+}
+
+fn current_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_record_and_read_entries() {
+        let dir = TempDir::new().unwrap();
+        let journal = Journal::new(dir.path());
+
+        assert!(journal.entries().unwrap().is_empty());
+
+        journal.record(Operation::Move {
+            source: PathBuf::from("a.txt"),
+            destination: PathBuf::from("b.txt"),
+        }).unwrap();
+        journal.record(Operation::Delete {
+            path: PathBuf::from("c.txt"),
+            trashed: PathBuf::from("trash/c.txt"),
+        }).unwrap();
+
+        let entries = journal.entries().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(matches!(entries[0].operation, Operation::Move { .. }));
+        assert!(matches!(entries[1].operation, Operation::Delete { .. }));
+    }
+
+    #[test]
+    fn test_remove_last() {
+        let dir = TempDir::new().unwrap();
+        let journal = Journal::new(dir.path());
+
+        assert!(journal.remove_last().unwrap().is_none());
+
+        journal.record(Operation::Copy {
+            source: PathBuf::from("a.txt"),
+            destination: PathBuf::from("b.txt"),
+            backup: None,
+        }).unwrap();
+        journal.record(Operation::Move {
+            source: PathBuf::from("b.txt"),
+            destination: PathBuf::from("c.txt"),
+        }).unwrap();
+
+        let last = journal.remove_last().unwrap().unwrap();
+        assert!(matches!(last.operation, Operation::Move { .. }));
+        assert_eq!(journal.entries().unwrap().len(), 1);
+    }
+}
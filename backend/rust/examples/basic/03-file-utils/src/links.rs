@@ -0,0 +1,117 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use crate::error::{PathContext, Result};
+
+/// 读取元数据；follow 时跟随符号链接，悬空的链接退回链接本身
+pub fn metadata(path: &Path, follow: bool) -> Result<fs::Metadata> {
+    if follow {
+        if let Ok(metadata) = fs::metadata(path) {
+            return Ok(metadata);
+        }
+    }
+    fs::symlink_metadata(path).with_path(path)
+}
+
+/// 文件在系统中的唯一标识 (设备号, inode)，非 Unix 平台为 None
+#[cfg(unix)]
+pub fn file_id(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+pub fn file_id(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// 硬链接数，非 Unix 平台为 None
+#[cfg(unix)]
+pub fn link_count(metadata: &fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.nlink())
+}
+
+#[cfg(not(unix))]
+pub fn link_count(_metadata: &fs::Metadata) -> Option<u64> {
+    None
+}
+
+/// 创建符号链接或硬链接；符号链接的 target 按原样保存，相对路径相对于链接所在目录
+pub fn create(target: &Path, link: &Path, symbolic: bool) -> Result<()> {
+    if !symbolic {
+        return fs::hard_link(target, link).with_path(link);
+    }
+
+    #[cfg(unix)]
+    return std::os::unix::fs::symlink(target, link).with_path(link);
+
+    #[cfg(not(unix))]
+    Err(crate::error::FileUtilsError::InvalidInput("符号链接仅支持 Unix 平台".to_string()))
+}
+
+/// 遍历时从起点到当前位置经过的目录，跟随符号链接时用来发现循环
+#[derive(Debug, Default)]
+pub struct Ancestors(Vec<Option<(u64, u64)>>);
+
+impl Ancestors {
+    /// 进入目录；目录已在当前路径上（即形成循环）时打印警告并返回 false
+    pub fn enter(&mut self, path: &Path, metadata: &fs::Metadata) -> bool {
+        let id = file_id(metadata);
+        if id.is_some() && self.0.contains(&id) {
+            eprintln!("⚠️  跳过符号链接循环: {}", path.display());
+            return false;
+        }
+        self.0.push(id);
+        true
+    }
+
+    pub fn leave(&mut self) {
+        self.0.pop();
+    }
+}
+
+/// 已统计过的文件，硬链接和指向同一文件的符号链接只计算一次
+#[derive(Debug, Default)]
+pub struct Seen(HashSet<(u64, u64)>);
+
+impl Seen {
+    /// 第一次遇到该文件时返回 true
+    pub fn first_visit(&mut self, metadata: &fs::Metadata) -> bool {
+        file_id(metadata).is_none_or(|id| self.0.insert(id))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_hard_links_are_seen_once_and_loops_detected() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("a.txt");
+        fs::write(&file, "hello").unwrap();
+        create(&file, &dir.path().join("b.txt"), false).unwrap();
+        create(Path::new("a.txt"), &dir.path().join("c.txt"), true).unwrap();
+
+        let mut seen = Seen::default();
+        assert!(seen.first_visit(&metadata(&file, false).unwrap()));
+        assert!(!seen.first_visit(&metadata(&dir.path().join("b.txt"), false).unwrap()));
+        assert!(!seen.first_visit(&metadata(&dir.path().join("c.txt"), true).unwrap()));
+        assert!(seen.first_visit(&metadata(&dir.path().join("c.txt"), false).unwrap()));
+        assert_eq!(link_count(&metadata(&file, false).unwrap()), Some(2));
+
+        // 悬空的链接在 follow 时退回链接本身
+        create(Path::new("missing"), &dir.path().join("dangling"), true).unwrap();
+        assert!(metadata(&dir.path().join("dangling"), true).unwrap().is_symlink());
+
+        create(Path::new("."), &dir.path().join("self"), true).unwrap();
+        let mut ancestors = Ancestors::default();
+        assert!(ancestors.enter(dir.path(), &metadata(dir.path(), true).unwrap()));
+        assert!(!ancestors.enter(&dir.path().join("self"), &metadata(&dir.path().join("self"), true).unwrap()));
+        ancestors.leave();
+        assert!(ancestors.enter(&dir.path().join("self"), &metadata(&dir.path().join("self"), true).unwrap()));
+    }
+}
@@ -1,132 +1,1051 @@
 use std::env;
-use std::fs;
-use std::io::{self, Write};
-use std::path::{Path, PathBuf};
+use std::ops::Deref;
+use std::process;
 
+mod archive;
+mod checksum;
+mod content;
+mod error;
 mod file_operations;
+mod interrupt;
+mod journal;
+mod links;
+mod output;
+mod permissions;
+mod records;
+mod rename;
+mod sandbox;
+mod shell;
+mod sync;
+mod trash;
 mod utils;
+mod watch;
 
-use file_operations::FileManager;
+use checksum::HashAlgorithm;
+use content::{ViewOptions, ViewRange};
+use error::{FileUtilsError, EXIT_USAGE};
+use file_operations::{FileManager, ListOptions, SortKey};
+use output::{Output, OutputFormat};
+use permissions::{ModeSpec, Owner};
+use rename::{CaseConversion, RenameRules, Substitution};
+use sandbox::Sandbox;
+use sync::{CompareMode, SyncOptions};
+use utils::TimeStyle;
+use watch::WatchOptions;
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    process::exit(run(env::args().collect()));
+}
+
+/// 全局选项解析之后交给各命令的参数
+struct Context {
+    manager: FileManager,
+    output: Output,
+    /// 取出全局选项之前的参数，shell 从中得到作用于每条命令的选项
+    original: Vec<String>,
+}
+
+/// 命令的处理函数，参数中 args[0] 是程序名，args[1] 是命令名，返回退出码
+type Handler = fn(Context, Args) -> i32;
+
+/// 所有命令和别名；命令行和 shell 都按这张表分发，shell 也用它补全命令名
+const COMMANDS: [(&str, Handler); 36] = [
+    ("copy", cmd_copy), ("cp", cmd_copy),
+    ("move", cmd_move), ("mv", cmd_move),
+    ("delete", cmd_delete), ("del", cmd_delete), ("rm", cmd_delete),
+    ("list", cmd_list), ("ls", cmd_list),
+    ("info", cmd_info),
+    ("create", cmd_create),
+    ("mkdir", cmd_mkdir),
+    ("find", cmd_find),
+    ("rename", cmd_rename),
+    ("ln", cmd_ln),
+    ("chmod", cmd_chmod),
+    ("chown", cmd_chown),
+    ("touch", cmd_touch),
+    ("sync", cmd_sync),
+    ("watch", cmd_watch),
+    ("pack", cmd_pack),
+    ("unpack", cmd_unpack),
+    ("view", cmd_view), ("cat", cmd_view),
+    ("grep", cmd_grep),
+    ("size", cmd_size),
+    ("hash", cmd_hash),
+    ("dupes", cmd_dupes),
+    ("diff", cmd_diff),
+    ("undo", cmd_undo),
+    ("history", cmd_history),
+    ("trash", cmd_trash),
+    ("shell", cmd_shell),
+    ("help", cmd_help), ("-h", cmd_help), ("--help", cmd_help),
+];
+
+/// 所有命令名和别名
+fn command_names() -> impl Iterator<Item = &'static str> {
+    COMMANDS.iter().map(|(name, _)| *name)
+}
+
+/// 解析并执行一条命令，返回退出码
+fn run(args: Vec<String>) -> i32 {
+    run_with_defaults(args, Vec::new())
+}
+
+/// 执行一条命令，defaults 中的选项在命令没有给出同名选项时使用；shell 中的每一行经由这里分发
+///
+/// 这里只解析作用于所有命令的全局选项，其余选项由各命令自己取出
+fn run_with_defaults(words: Vec<String>, defaults: Vec<String>) -> i32 {
+    let original = words.clone();
+    let mut args = Args { words, defaults };
+    let dry_run = args.flag("--dry-run");
+    let format = match args.option("--format").map(|format| format.parse::<OutputFormat>()) {
+        None => OutputFormat::default(),
+        Some(Ok(format)) => format,
+        Some(Err(e)) => {
+            eprintln!("错误: {}", e);
+            return EXIT_USAGE;
+        }
+    };
+    let time_style = match args.option("--time-style").map(|style| style.parse::<TimeStyle>()) {
+        None => TimeStyle::default(),
+        Some(Ok(style)) => style,
+        Some(Err(e)) => {
+            eprintln!("错误: {}", e);
+            return EXIT_USAGE;
+        }
+    };
+    
+    let sandbox = match args.option("--root").map(|root| Sandbox::new(&root)) {
+        None => None,
+        Some(Ok(sandbox)) => Some(sandbox),
+        Some(Err(e)) => {
+            eprintln!("错误: --root {}", e);
+            return e.exit_code();
+        }
+    };
     
     if args.len() < 2 {
         show_help(&args[0]);
-        return;
+        return 0;
     }
     
-    let manager = FileManager::new();
+    let context = Context {
+        manager: FileManager::new()
+            .dry_run(dry_run)
+            .time_style(time_style.clone())
+            .sandbox(sandbox),
+        output: Output::new(format, time_style),
+        original,
+    };
     
-    match args[1].as_str() {
-        "copy" | "cp" => {
-            if args.len() < 4 {
-                eprintln!("错误: copy 命令需要源文件和目标路径");
-                eprintln!("用法: {} copy <源文件> <目标路径>", args[0]);
-                return;
-            }
-            handle_result(manager.copy(&args[2], &args[3]));
-        },
-        "move" | "mv" => {
-            if args.len() < 4 {
-                eprintln!("错误: move 命令需要源文件和目标路径");
-                eprintln!("用法: {} move <源文件> <目标路径>", args[0]);
-                return;
-            }
-            handle_result(manager.move_file(&args[2], &args[3]));
-        },
-        "delete" | "del" | "rm" => {
-            if args.len() < 3 {
-                eprintln!("错误: delete 命令需要文件路径");
-                eprintln!("用法: {} delete <文件路径>", args[0]);
-                return;
-            }
-            handle_result(manager.delete(&args[2]));
-        },
-        "list" | "ls" => {
-            let path = if args.len() > 2 { &args[2] } else { "." };
-            handle_result(manager.list(path));
-        },
-        "info" => {
-            if args.len() < 3 {
-                eprintln!("错误: info 命令需要文件路径");
-                eprintln!("用法: {} info <文件路径>", args[0]);
-                return;
-            }
-            handle_result(manager.info(&args[2]));
-        },
-        "create" => {
-            if args.len() < 3 {
-                eprintln!("错误: create 命令需要文件路径");
-                eprintln!("用法: {} create <文件路径>", args[0]);
-                return;
+    match COMMANDS.iter().find(|(name, _)| *name == args[1]) {
+        Some((_, handler)) => handler(context, args),
+        // 各命令的选项要写在命令之后，写在前面时给出明确的提示
+        None if args[1].starts_with('-') => {
+            eprintln!("错误: {} 不是全局选项，需要写在命令之后", args[1]);
+            eprintln!("用法: {} [全局选项] <命令> [参数和选项...]", args[0]);
+            EXIT_USAGE
+        }
+        None => {
+            eprintln!("未知命令: {}", args[1]);
+            show_help(&args[0]);
+            EXIT_USAGE
+        }
+    }
+}
+
+fn cmd_copy(ctx: Context, args: Args) -> i32 {
+    if args.len() < 4 {
+        eprintln!("错误: copy 命令需要源文件和目标路径");
+        eprintln!("用法: {} copy <源文件> <目标路径>", args[0]);
+        return EXIT_USAGE;
+    }
+    handle_result(ctx.manager.copy(&args[2], &args[3]))
+}
+
+fn cmd_move(ctx: Context, args: Args) -> i32 {
+    if args.len() < 4 {
+        eprintln!("错误: move 命令需要源文件和目标路径");
+        eprintln!("用法: {} move <源文件> <目标路径>", args[0]);
+        return EXIT_USAGE;
+    }
+    handle_result(ctx.manager.move_file(&args[2], &args[3]))
+}
+
+fn cmd_delete(ctx: Context, mut args: Args) -> i32 {
+    let permanent = args.flag("--permanent");
+    if args.len() < 3 {
+        eprintln!("错误: delete 命令需要文件路径");
+        eprintln!("用法: {} delete <文件路径>", args[0]);
+        return EXIT_USAGE;
+    }
+    handle_result(ctx.manager.delete(&args[2], permanent))
+}
+
+fn cmd_list(ctx: Context, mut args: Args) -> i32 {
+    let manager = match take_follow(&mut args) {
+        Ok(follow) => ctx.manager.follow_links(follow),
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            return e.exit_code();
+        }
+    };
+    let depth = match args.option("--depth").map(|depth| depth.parse::<usize>()) {
+        None => None,
+        Some(Ok(depth)) => Some(depth),
+        Some(Err(_)) => {
+            eprintln!("错误: --depth 需要一个非负整数");
+            return EXIT_USAGE;
+        }
+    };
+    let tree = args.flag("--tree");
+    let list_options = match take_list_options(&mut args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            return e.exit_code();
+        }
+    };
+    let output = ctx.output.long(args.flag("-l"));
+    let path = if args.len() > 2 { &args[2] } else { "." };
+    if tree {
+        handle_result(manager.tree(path, depth, &list_options).and_then(|root| output.tree(&root)))
+    } else {
+        handle_result(manager.list(path, &list_options).and_then(|records| output.list(path, &records)))
+    }
+}
+
+fn cmd_info(ctx: Context, mut args: Args) -> i32 {
+    let manager = match take_follow(&mut args) {
+        Ok(follow) => ctx.manager.follow_links(follow),
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            return e.exit_code();
+        }
+    };
+    if args.len() < 3 {
+        eprintln!("错误: info 命令需要文件路径");
+        eprintln!("用法: {} info <文件路径>", args[0]);
+        return EXIT_USAGE;
+    }
+    handle_result(manager.info(&args[2]).and_then(|info| ctx.output.info(&info)))
+}
+
+fn cmd_create(ctx: Context, mut args: Args) -> i32 {
+    let mode = match args.option("--mode").map(|mode| mode.parse::<ModeSpec>()) {
+        None => None,
+        Some(Ok(mode)) => Some(mode),
+        Some(Err(e)) => {
+            eprintln!("错误: {}", e);
+            return EXIT_USAGE;
+        }
+    };
+    if args.len() < 3 {
+        eprintln!("错误: create 命令需要文件路径");
+        eprintln!("用法: {} create <文件路径> [--mode <权限>]", args[0]);
+        return EXIT_USAGE;
+    }
+    handle_result(ctx.manager.create(&args[2], mode.as_ref()))
+}
+
+fn cmd_mkdir(ctx: Context, args: Args) -> i32 {
+    if args.len() < 3 {
+        eprintln!("错误: mkdir 命令需要目录路径");
+        eprintln!("用法: {} mkdir <目录路径>", args[0]);
+        return EXIT_USAGE;
+    }
+    handle_result(ctx.manager.create_dir(&args[2]))
+}
+
+fn cmd_find(ctx: Context, mut args: Args) -> i32 {
+    let manager = match take_follow(&mut args) {
+        Ok(follow) => ctx.manager.follow_links(follow),
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            return e.exit_code();
+        }
+    };
+    if args.len() < 4 {
+        eprintln!("错误: find 命令需要搜索目录和文件名");
+        eprintln!("用法: {} find <目录> <文件名模式>", args[0]);
+        return EXIT_USAGE;
+    }
+    handle_result(manager.find(&args[2], &args[3])
+        .and_then(|records| ctx.output.find(&args[2], &args[3], &records)))
+}
+
+fn cmd_rename(ctx: Context, mut args: Args) -> i32 {
+    let pattern = args.option("--match");
+    let rules = match take_rename_rules(&mut args) {
+        Ok(rules) => rules,
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            return e.exit_code();
+        }
+    };
+    let list_options = match take_list_options(&mut args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            return e.exit_code();
+        }
+    };
+    if args.len() < 3 {
+        eprintln!("错误: rename 命令需要目录");
+        eprintln!("用法: {} rename <目录> [--match <模式>] [--find <文本> | --regex <正则>] [--replace <文本>] [--template <模板>] [--case <大小写>] [--ext <扩展名>]", args[0]);
+        return EXIT_USAGE;
+    }
+    handle_result(ctx.manager.rename(&args[2], pattern.as_deref(), &rules, &list_options))
+}
+
+fn cmd_ln(ctx: Context, mut args: Args) -> i32 {
+    let symbolic = args.flag("-s") | args.flag("--symbolic");
+    if args.len() < 4 {
+        eprintln!("错误: ln 命令需要链接目标和链接路径");
+        eprintln!("用法: {} ln [-s] <目标> <链接路径>", args[0]);
+        return EXIT_USAGE;
+    }
+    handle_result(ctx.manager.link(&args[2], &args[3], symbolic))
+}
+
+fn cmd_chmod(ctx: Context, mut args: Args) -> i32 {
+    let manager = match take_follow(&mut args) {
+        Ok(follow) => ctx.manager.follow_links(follow),
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            return e.exit_code();
+        }
+    };
+    let recursive = args.flag("-R") | args.flag("--recursive");
+    if args.len() < 4 {
+        eprintln!("错误: chmod 命令需要权限和路径");
+        eprintln!("用法: {} chmod <权限> <路径> [-R]", args[0]);
+        return EXIT_USAGE;
+    }
+    match args[2].parse::<ModeSpec>() {
+        Ok(mode) => handle_result(manager.chmod(&args[3], &mode, recursive)),
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            EXIT_USAGE
+        }
+    }
+}
+
+fn cmd_chown(ctx: Context, mut args: Args) -> i32 {
+    let manager = match take_follow(&mut args) {
+        Ok(follow) => ctx.manager.follow_links(follow),
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            return e.exit_code();
+        }
+    };
+    let recursive = args.flag("-R") | args.flag("--recursive");
+    if args.len() < 4 {
+        eprintln!("错误: chown 命令需要所有者和路径");
+        eprintln!("用法: {} chown <用户>[:<组>] <路径> [-R]", args[0]);
+        return EXIT_USAGE;
+    }
+    match args[2].parse::<Owner>() {
+        Ok(owner) => handle_result(manager.chown(&args[3], &owner, recursive)),
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            EXIT_USAGE
+        }
+    }
+}
+
+fn cmd_touch(ctx: Context, mut args: Args) -> i32 {
+    let manager = match take_follow(&mut args) {
+        Ok(follow) => ctx.manager.follow_links(follow),
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            return e.exit_code();
+        }
+    };
+    let recursive = args.flag("-R") | args.flag("--recursive");
+    let time = match args.option("--date").map(|date| utils::parse_time(&date)) {
+        None => None,
+        Some(Some(time)) => Some(time),
+        Some(None) => {
+            eprintln!("错误: 无效的时间 (例如 2024-03-15、2024-03-15 14:30、2024-03-15T14:30:00+08:00、@1700000000)");
+            return EXIT_USAGE;
+        }
+    };
+    if args.len() < 3 {
+        eprintln!("错误: touch 命令需要文件路径");
+        eprintln!("用法: {} touch <路径> [--date <时间>] [-R]", args[0]);
+        return EXIT_USAGE;
+    }
+    handle_result(manager.touch(&args[2], time, recursive))
+}
+
+fn cmd_sync(ctx: Context, mut args: Args) -> i32 {
+    let options = take_sync_options(&mut args);
+    if args.len() < 4 {
+        eprintln!("错误: sync 命令需要源目录和目标目录");
+        eprintln!("用法: {} sync <源目录> <目标目录> [--checksum] [--delete] [--include <模式>] [--exclude <模式>]", args[0]);
+        return EXIT_USAGE;
+    }
+    handle_result(ctx.manager.sync(&args[2], &args[3], &options))
+}
+
+fn cmd_watch(ctx: Context, mut args: Args) -> i32 {
+    let options = match take_watch_options(&mut args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            return e.exit_code();
+        }
+    };
+    let dir = if args.len() > 2 { &args[2] } else { "." };
+    ctx.output.watch_start(dir);
+    handle_result(ctx.manager.watch(dir, &options, |events| ctx.output.events(events)))
+}
+
+fn cmd_pack(ctx: Context, args: Args) -> i32 {
+    if args.len() < 4 {
+        eprintln!("错误: pack 命令需要源路径和归档文件");
+        eprintln!("用法: {} pack <源路径> <归档.tar.gz|.tgz|.tar|.zip>", args[0]);
+        return EXIT_USAGE;
+    }
+    handle_result(ctx.manager.pack(&args[2], &args[3]))
+}
+
+fn cmd_unpack(ctx: Context, mut args: Args) -> i32 {
+    let list_archive = args.flag("--list");
+    if args.len() < 3 {
+        eprintln!("错误: unpack 命令需要归档文件");
+        eprintln!("用法: {} unpack <归档> [目标目录] [--list]", args[0]);
+        return EXIT_USAGE;
+    }
+    if list_archive {
+        let output = ctx.output.long(args.flag("-l"));
+        handle_result(ctx.manager.archive_list(&args[2]).and_then(|records| output.list(&args[2], &records)))
+    } else {
+        let dest = if args.len() > 3 { &args[3] } else { "." };
+        handle_result(ctx.manager.unpack(&args[2], dest))
+    }
+}
+
+fn cmd_view(ctx: Context, mut args: Args) -> i32 {
+    let options = match take_view_options(&mut args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            return e.exit_code();
+        }
+    };
+    if args.len() < 3 {
+        eprintln!("错误: view 命令需要文件路径");
+        eprintln!("用法: {} view <文件> [--head N | --tail N | --lines A:B] [--hex] [-f]", args[0]);
+        return EXIT_USAGE;
+    }
+    handle_result(ctx.manager.view(&args[2], &options))
+}
+
+fn cmd_grep(ctx: Context, mut args: Args) -> i32 {
+    let manager = match take_follow(&mut args) {
+        Ok(follow) => ctx.manager.follow_links(follow),
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            return e.exit_code();
+        }
+    };
+    let pattern = args.option("--match");
+    let ignore_case = args.flag("-i") | args.flag("--ignore-case");
+    let context = match args.option("-C").or_else(|| args.option("--context")) {
+        None => 0,
+        Some(context) => match context.parse::<usize>() {
+            Ok(context) => context,
+            Err(_) => {
+                eprintln!("错误: --context 需要一个非负整数");
+                return EXIT_USAGE;
             }
-            handle_result(manager.create(&args[2]));
         },
-        "mkdir" => {
-            if args.len() < 3 {
-                eprintln!("错误: mkdir 命令需要目录路径");
-                eprintln!("用法: {} mkdir <目录路径>", args[0]);
-                return;
+    };
+    if args.len() < 3 {
+        eprintln!("错误: grep 命令需要搜索模式");
+        eprintln!("用法: {} grep <正则> [路径] [-i] [-C 行数] [--match <文件名模式>]", args[0]);
+        return EXIT_USAGE;
+    }
+    let path = if args.len() > 3 { &args[3] } else { "." };
+    handle_result(manager.grep(&args[2], path, ignore_case, context, pattern.as_deref())
+        .and_then(|results| ctx.output.grep(&results, context)))
+}
+
+fn cmd_size(ctx: Context, mut args: Args) -> i32 {
+    let manager = match take_follow(&mut args) {
+        Ok(follow) => ctx.manager.follow_links(follow),
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            return e.exit_code();
+        }
+    };
+    if args.len() < 3 {
+        eprintln!("错误: size 命令需要路径");
+        eprintln!("用法: {} size <路径>", args[0]);
+        return EXIT_USAGE;
+    }
+    handle_result(manager.calculate_size(&args[2]).and_then(|record| ctx.output.size(&record)))
+}
+
+fn cmd_hash(ctx: Context, mut args: Args) -> i32 {
+    let manager = match take_follow(&mut args) {
+        Ok(follow) => ctx.manager.follow_links(follow),
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            return e.exit_code();
+        }
+    };
+    let algorithm = match take_algorithm(&mut args) {
+        Ok(algorithm) => algorithm,
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            return e.exit_code();
+        }
+    };
+    let manifest = args.option("--manifest");
+    if args.len() < 3 {
+        eprintln!("错误: hash 命令需要路径");
+        eprintln!("用法: {} hash <路径> [--algorithm sha256|blake3] [--manifest <清单文件>]", args[0]);
+        return EXIT_USAGE;
+    }
+    handle_result(manager.hash(&args[2], algorithm, manifest.as_deref()))
+}
+
+fn cmd_dupes(ctx: Context, mut args: Args) -> i32 {
+    let manager = match take_follow(&mut args) {
+        Ok(follow) => ctx.manager.follow_links(follow),
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            return e.exit_code();
+        }
+    };
+    let algorithm = match take_algorithm(&mut args) {
+        Ok(algorithm) => algorithm,
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            return e.exit_code();
+        }
+    };
+    let dir = if args.len() > 2 { &args[2] } else { "." };
+    handle_result(manager.dupes(dir, algorithm))
+}
+
+fn cmd_diff(ctx: Context, mut args: Args) -> i32 {
+    let manager = match take_follow(&mut args) {
+        Ok(follow) => ctx.manager.follow_links(follow),
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            return e.exit_code();
+        }
+    };
+    if args.len() < 4 {
+        eprintln!("错误: diff 命令需要两个路径");
+        eprintln!("用法: {} diff <路径A> <路径B>", args[0]);
+        return EXIT_USAGE;
+    }
+    // 与 diff(1) 一致：相同返回 0，不同返回 1，出错时返回错误对应的退出码
+    match manager.diff(&args[2], &args[3]) {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(e) => handle_result::<()>(Err(e)),
+    }
+}
+
+fn cmd_undo(ctx: Context, args: Args) -> i32 {
+    let count = if args.len() > 2 {
+        match args[2].parse::<usize>() {
+            Ok(count) => count,
+            Err(_) => {
+                eprintln!("错误: 撤销次数必须是数字");
+                eprintln!("用法: {} undo [次数]", args[0]);
+                return EXIT_USAGE;
             }
-            handle_result(manager.create_dir(&args[2]));
-        },
-        "find" => {
+        }
+    } else {
+        1
+    };
+    handle_result(ctx.manager.undo(count))
+}
+
+fn cmd_history(ctx: Context, _args: Args) -> i32 {
+    handle_result(ctx.manager.history())
+}
+
+fn cmd_trash(ctx: Context, mut args: Args) -> i32 {
+    let older_than = args.option("--older-than");
+    match args.get(2).map(String::as_str) {
+        None | Some("list") => handle_result(ctx.manager.trash_list()),
+        Some("restore") => {
             if args.len() < 4 {
-                eprintln!("错误: find 命令需要搜索目录和文件名");
-                eprintln!("用法: {} find <目录> <文件名模式>", args[0]);
-                return;
-            }
-            handle_result(manager.find(&args[2], &args[3]));
-        },
-        "size" => {
-            if args.len() < 3 {
-                eprintln!("错误: size 命令需要路径");
-                eprintln!("用法: {} size <路径>", args[0]);
-                return;
+                eprintln!("错误: trash restore 需要条目名称");
+                eprintln!("用法: {} trash restore <名称>", args[0]);
+                return EXIT_USAGE;
             }
-            handle_result(manager.calculate_size(&args[2]));
+            handle_result(ctx.manager.trash_restore(&args[3]))
         },
-        "help" | "-h" | "--help" => {
-            show_help(&args[0]);
+        Some("empty") => {
+            let older_than = match older_than.as_deref().map(utils::parse_duration) {
+                None => None,
+                Some(Some(seconds)) => Some(seconds),
+                Some(None) => {
+                    eprintln!("错误: 无效的时长，例如 30d、12h、2w");
+                    return EXIT_USAGE;
+                }
+            };
+            handle_result(ctx.manager.trash_empty(older_than))
         },
-        _ => {
-            eprintln!("未知命令: {}", args[1]);
-            show_help(&args[0]);
+        Some(other) => {
+            eprintln!("未知的 trash 子命令: {}", other);
+            eprintln!("用法: {} trash [list|restore <名称>|empty [--older-than <时长>]]", args[0]);
+            EXIT_USAGE
         }
     }
 }
 
-fn handle_result<T>(result: Result<T, Box<dyn std::error::Error>>) {
+fn cmd_shell(ctx: Context, args: Args) -> i32 {
+    // 除了选项和跟在 `--name` 后面的值，shell 不接受其他参数
+    let positional = args[2..].iter().enumerate().any(|(index, arg)| {
+        !arg.starts_with('-') && (index == 0 || !args[index + 1].starts_with("--") || args[index + 1].contains('='))
+    });
+    if positional {
+        eprintln!("错误: shell 不接受位置参数");
+        eprintln!("用法: {} shell [选项...]", args[0]);
+        return EXIT_USAGE;
+    }
+    // 启动 shell 时给出的选项（如 --dry-run、--root、--follow）作为其中每条命令的默认选项
+    let mut defaults = ctx.original[1..].to_vec();
+    if let Some(index) = defaults.iter().position(|arg| arg == "shell") {
+        defaults.remove(index);
+    }
+    shell::run(&args[0], defaults)
+}
+
+fn cmd_help(_ctx: Context, args: Args) -> i32 {
+    show_help(&args[0]);
+    0
+}
+
+/// 打印错误并返回该错误对应的退出码，成功时返回 0
+fn handle_result<T>(result: Result<T, FileUtilsError>) -> i32 {
     match result {
-        Ok(_) => {},
-        Err(e) => eprintln!("❌ 错误: {}", e),
+        Ok(_) => 0,
+        Err(e) => {
+            eprintln!("❌ 错误: {}", e);
+            e.exit_code()
+        },
+    }
+}
+
+/// 从参数列表中取出 rename 的规则选项
+fn take_rename_rules(args: &mut Args) -> Result<RenameRules, FileUtilsError> {
+    let replace = args.option("--replace").unwrap_or_default();
+    let substitution = match (args.option("--find"), args.option("--regex")) {
+        (Some(_), Some(_)) => {
+            return Err(FileUtilsError::InvalidInput("--find 和 --regex 不能同时使用".to_string()));
+        },
+        (Some(find), None) if find.is_empty() => {
+            return Err(FileUtilsError::InvalidInput("--find 不能为空".to_string()));
+        },
+        (Some(find), None) => Some(Substitution::Literal { find, replace }),
+        (None, Some(pattern)) => Some(Substitution::regex(&pattern, &replace)?),
+        (None, None) => None,
+    };
+    
+    let start = match args.option("--start") {
+        None => 1,
+        Some(start) => start
+            .parse()
+            .map_err(|_| FileUtilsError::InvalidInput(format!("无效的起始编号: {}", start)))?,
+    };
+    
+    let case = args.option("--case")
+        .map(|case| case.parse::<CaseConversion>())
+        .transpose()
+        .map_err(FileUtilsError::InvalidInput)?;
+    
+    Ok(RenameRules {
+        substitution,
+        template: args.option("--template"),
+        start,
+        case,
+        extension: args.option("--ext"),
+    })
+}
+
+/// 取出 list 和 rename 的排序与隐藏文件选项；只在这两个命令中解析，
+/// 其他命令的 `-a`、`-r` 等参数原样保留
+fn take_list_options(args: &mut Args) -> Result<ListOptions, FileUtilsError> {
+    let sort = args.option("--sort")
+        .map(|key| key.parse::<SortKey>())
+        .transpose()
+        .map_err(FileUtilsError::InvalidInput)?
+        .unwrap_or_default();
+    
+    Ok(ListOptions {
+        all: args.flag("-a") | args.flag("--all"),
+        sort,
+        reverse: args.flag("-r") | args.flag("--reverse"),
+    })
+}
+
+/// 取出 view 的选项，--head、--tail、--lines 只能使用一个
+fn take_view_options(args: &mut Args) -> Result<ViewOptions, FileUtilsError> {
+    let count = |name: &str, value: String| {
+        value
+            .parse::<usize>()
+            .map_err(|_| FileUtilsError::InvalidInput(format!("{} 需要一个非负整数", name)))
+    };
+    
+    let ranges = [
+        args.option("--head").map(|n| count("--head", n).map(ViewRange::Head)),
+        args.option("--tail").map(|n| count("--tail", n).map(ViewRange::Tail)),
+        args.option("--lines").map(|lines| ViewRange::parse_lines(&lines)),
+    ];
+    let mut ranges = ranges.into_iter().flatten();
+    let range = ranges.next().transpose()?.unwrap_or_default();
+    if ranges.next().is_some() {
+        return Err(FileUtilsError::InvalidInput("--head、--tail 和 --lines 只能使用一个".to_string()));
+    }
+    
+    Ok(ViewOptions {
+        range,
+        hex: args.flag("--hex"),
+        follow: args.flag("-f") | args.flag("--tail-follow"),
+    })
+}
+
+/// 一条命令的参数；shell 中还带有启动时给出的选项，命令中给出同名选项时以命令中的为准
+struct Args {
+    words: Vec<String>,
+    defaults: Vec<String>,
+}
+
+impl Args {
+    /// 取出开关参数，命令中和默认选项中出现任一处都算
+    fn flag(&mut self, flag: &str) -> bool {
+        take_flag(&mut self.words, flag) | take_flag(&mut self.defaults, flag)
+    }
+    
+    /// 取出带值的选项，命令中给出时丢弃默认选项中的值
+    fn option(&mut self, name: &str) -> Option<String> {
+        let default = take_option(&mut self.defaults, name);
+        take_option(&mut self.words, name).or(default)
+    }
+    
+    /// 取出可以重复出现的选项，命令中给出时丢弃默认选项中的值
+    fn options(&mut self, name: &str) -> Vec<String> {
+        let defaults = take_options(&mut self.defaults, name);
+        let values = take_options(&mut self.words, name);
+        if values.is_empty() { defaults } else { values }
+    }
+}
+
+/// 位置参数，args[0] 是程序名，args[1] 是命令名
+impl Deref for Args {
+    type Target = Vec<String>;
+    
+    fn deref(&self) -> &Vec<String> {
+        &self.words
+    }
+}
+
+/// 取出 `-L`/`--follow` 和 `--no-follow`；命令中给出任一个时忽略默认选项中的另一个。
+/// 只有遍历目录或处理符号链接的命令解析它
+fn take_follow(args: &mut Args) -> Result<bool, FileUtilsError> {
+    let parse = |words: &mut Vec<String>| match (take_flag(words, "-L") | take_flag(words, "--follow"), take_flag(words, "--no-follow")) {
+        (true, true) => Err(FileUtilsError::InvalidInput("--follow 和 --no-follow 不能同时使用".to_string())),
+        (true, false) => Ok(Some(true)),
+        (false, true) => Ok(Some(false)),
+        (false, false) => Ok(None),
+    };
+    let default = parse(&mut args.defaults)?;
+    Ok(parse(&mut args.words)?.or(default).unwrap_or(false))
+}
+
+/// 取出 hash 和 dupes 使用的摘要算法
+fn take_algorithm(args: &mut Args) -> Result<HashAlgorithm, FileUtilsError> {
+    Ok(args
+        .option("--algorithm")
+        .map(|name| name.parse::<HashAlgorithm>())
+        .transpose()
+        .map_err(FileUtilsError::InvalidInput)?
+        .unwrap_or_default())
+}
+
+/// 取出 sync 的比较方式、删除和过滤选项
+fn take_sync_options(args: &mut Args) -> SyncOptions {
+    SyncOptions {
+        compare: if args.flag("--checksum") { CompareMode::Checksum } else { CompareMode::SizeMtime },
+        delete: args.flag("--delete"),
+        include: args.options("--include"),
+        exclude: args.options("--exclude"),
     }
 }
 
+/// 取出 watch 的过滤、防抖和 exec 选项
+fn take_watch_options(args: &mut Args) -> Result<WatchOptions, FileUtilsError> {
+    let debounce = match args.option("--debounce") {
+        None => watch::DEFAULT_DEBOUNCE,
+        Some(ms) => ms
+            .parse::<u64>()
+            .map(std::time::Duration::from_millis)
+            .map_err(|_| FileUtilsError::InvalidInput("--debounce 需要毫秒数".to_string()))?,
+    };
+    
+    Ok(WatchOptions {
+        include: args.options("--include"),
+        exclude: args.options("--exclude"),
+        debounce,
+        exec: args.option("--exec"),
+    })
+}
+
+/// 从参数列表中取出一个带值的选项，支持 `--name value` 和 `--name=value`
+fn take_option(args: &mut Vec<String>, name: &str) -> Option<String> {
+    let prefix = format!("{}=", name);
+    
+    if let Some(index) = args.iter().position(|arg| arg.starts_with(&prefix)) {
+        let arg = args.remove(index);
+        return Some(arg[prefix.len()..].to_string());
+    }
+    
+    let index = args.iter().position(|arg| arg == name)?;
+    if index + 1 < args.len() {
+        args.remove(index);
+        Some(args.remove(index))
+    } else {
+        args.remove(index);
+        None
+    }
+}
+
+/// 取出可以重复出现的选项的所有值
+fn take_options(args: &mut Vec<String>, name: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    while let Some(value) = take_option(args, name) {
+        values.push(value);
+    }
+    values
+}
+
+/// 从参数列表中取出一个开关参数，返回它是否出现过
+fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    let before = args.len();
+    args.retain(|arg| arg != flag);
+    args.len() != before
+}
+
 fn show_help(program: &str) {
     println!("📁 文件操作工具 v0.1.0");
     println!();
-    println!("用法: {} <命令> [参数...]", program);
+    println!("用法: {} [全局选项] <命令> [参数和选项...]", program);
     println!();
     println!("可用命令:");
     println!("  copy <源文件> <目标路径>     复制文件");
     println!("  move <源文件> <目标路径>     移动/重命名文件");
-    println!("  delete <文件路径>           删除文件");
+    println!("  delete <文件路径>           删除文件 (移入回收站)");
     println!("  list [目录]                 列出目录内容 (默认当前目录)");
     println!("  info <文件路径>             显示文件信息");
-    println!("  create <文件路径>           创建空文件");
+    println!("  create <文件路径>           创建空文件 (--mode 指定权限)");
     println!("  mkdir <目录路径>            创建目录");
     println!("  find <目录> <文件名模式>    查找文件");
     println!("  size <路径>                 计算文件/目录大小");
+    println!("  ln [-s] <目标> <链接路径>   创建硬链接，-s 时创建符号链接");
+    println!("  chmod <权限> <路径>         修改权限: 八进制 (755) 或符号形式 (u+x,g-w)");
+    println!("  chown <用户>[:<组>] <路径>  修改所有者，用户和组可以是名称或数字 id");
+    println!("  touch <路径>                更新访问和修改时间，文件不存在时创建");
+    println!("  view <文件>                 显示文件内容 (二进制文件以十六进制显示)");
+    println!("  grep <正则> [路径]          递归搜索文件内容，显示行号");
+    println!("  rename <目录> [规则...]     批量重命名目录中的文件 (先预览再确认)");
+    println!("  sync <源目录> <目标目录>    只复制新增或变化的文件 (不逐个询问)");
+    println!("  watch [目录]                实时报告新建/修改/删除/改名 (默认当前目录)");
+    println!("  pack <源路径> <归档>        打包为 .tar.gz/.tgz/.tar/.zip (保留权限和修改时间)");
+    println!("  unpack <归档> [目录]        解包到目录 (默认当前目录)，拒绝逃出目录的条目");
+    println!("  hash <路径>                 计算文件摘要 (目录会递归计算)");
+    println!("  dupes [目录]                查找重复文件");
+    println!("  diff <路径A> <路径B>        比较两个文件或目录树，相同时退出码为 0，不同为 1");
+    println!("  undo [次数]                 撤销最近的复制/移动/删除/链接 (默认 1 次)");
+    println!("  history                     显示操作日志");
+    println!("  trash [list]                列出回收站内容");
+    println!("  trash restore <名称>        从回收站恢复");
+    println!("  trash empty                 清空回收站");
+    println!("  shell [选项...]             交互模式: 支持 cd/pwd、Tab 补全路径和历史记录，选项作用于其中每条命令");
     println!("  help                        显示此帮助信息");
     println!();
+    println!("全局选项 (可以写在命令前后):");
+    println!("  --dry-run                   只显示将要执行的操作，不实际修改文件");
+    println!("  --root <目录>               沙箱模式: 拒绝访问该目录之外的路径 (会解析符号链接)");
+    println!("  --format <格式>             list/info/find/size/grep/unpack --list/watch 的输出格式: table (默认), json, csv");
+    println!("  --time-style <风格>         时间显示风格: relative (默认), iso, full-iso, +格式");
+    println!();
+    println!("命令选项 (写在命令之后，只对列出的命令有效):");
+    println!("  --permanent                 delete 时永久删除而不是移入回收站");
+    println!("  --older-than <时长>         trash empty 只清理早于该时长的条目 (如 30d)");
+    println!("  --algorithm <算法>          hash/dupes 使用的摘要算法: sha256 (默认), blake3");
+    println!("  --manifest <文件>           hash 时把结果写入清单文件");
+    println!("  -a, --all                   list/rename 时包含以点开头的隐藏文件");
+    println!("  -l                          list/unpack --list 使用长格式 (权限、所有者、所属组)");
+    println!("  --sort <字段>               list/rename 的排序字段: name (默认), size, mtime, ext");
+    println!("  -r, --reverse               list/rename 反向排序");
+    println!("  --tree                      list 以树形显示子目录");
+    println!("  --depth <层数>              --tree 的最大深度");
+    println!("  -L, --follow                list/info/find/size/grep/hash/dupes/diff/chmod/chown/touch 跟随符号链接 (跳过循环)");
+    println!("  --no-follow                 只处理符号链接本身 (默认)");
+    println!("  --match <模式>              rename/grep 只处理匹配的文件名 (如 \"*.jpg\")");
+    println!("  --find <文本>               rename 查找的文本，与 --replace 配合");
+    println!("  --regex <正则>              rename 的正则表达式，--replace 中可用 $1、${{name}}");
+    println!("  --replace <文本>            rename 的替换内容");
+    println!("  --template <模板>           rename 的新文件名模板: {{n}}、{{n:03}}、{{name}}、{{ext}}");
+    println!("  --start <编号>              rename 模板中 {{n}} 的起始编号 (默认 1)");
+    println!("  --case <大小写>             rename 大小写转换: lower, upper, title");
+    println!("  --ext <扩展名>              rename 修改扩展名 (空字符串表示去掉)");
+    println!("  --head <N> / --tail <N>     view 只显示前/后 N 行 (--hex 时为字节数)");
+    println!("  --lines <A:B>               view 显示第 A 到 B 行，如 10:20、10:、:20");
+    println!("  --hex                       view 以十六进制转储显示");
+    println!("  -f, --tail-follow           view 时持续显示追加的内容 (类似 tail -f)");
+    println!("  -i, --ignore-case           grep 忽略大小写");
+    println!("  -C, --context <行数>        grep 显示匹配行前后的上下文");
+    println!("  --checksum                  sync 按内容比较 (默认按大小和修改时间)");
+    println!("  --delete                    sync 删除目标中多余的文件 (移入回收站)");
+    println!("  --include <模式>            sync/watch 只处理匹配的文件，可重复");
+    println!("  --exclude <模式>            sync/watch 跳过匹配的文件或目录，可重复");
+    println!("  --debounce <毫秒>           watch 合并事件的等待时间 (默认 200)");
+    println!("  --exec <命令>               watch 每批变化后运行的命令，变化的路径在 $FILE_UTILS_CHANGED 中");
+    println!("  -R, --recursive             chmod/chown/touch 递归处理目录内容 (不进入符号链接)");
+    println!("  --mode <权限>               create 时设置新文件的权限");
+    println!("  --date <时间>               touch 使用的时间，如 2024-03-15 14:30、@1700000000 (默认现在)");
+    println!("  --list                      unpack 时只列出归档内容，不解包");
+    println!();
     println!("别名:");
-    println!("  cp = copy, mv = move, rm = delete, ls = list");
+    println!("  cp = copy, mv = move, rm = delete, ls = list, cat = view");
     println!();
     println!("示例:");
     println!("  {} copy file.txt backup/", program);
     println!("  {} move old_name.txt new_name.txt", program);
     println!("  {} list /home/user", program);
+    println!("  {} list --time-style=full-iso", program);
+    println!("  {} ls -l -a --sort size", program);
+    println!("  {} ls --tree --depth 2", program);
+    println!("  {} find . \"*.log\" --format json", program);
+    println!("  {} info file.txt --time-style=\"+%Y年%m月%d日 %H:%M\"", program);
     println!("  {} find . \"*.rs\"", program);
-}
\ No newline at end of file
+    println!("  {} delete old.txt --dry-run", program);
+    println!("  {} undo 2", program);
+    println!("  {} trash empty --older-than 30d", program);
+    println!("  {} hash src --algorithm blake3 --manifest SUMS", program);
+    println!("  {} dupes ~/Pictures", program);
+    println!("  {} rename ~/Pictures --match \"*.JPG\" --template \"photo_{{n:03}}.{{ext}}\" --case lower", program);
+    println!("  {} rename . --regex \"^IMG_(\\d+)\" --replace \"img-$1\" --dry-run", program);
+    println!("  {} view app.log --tail 50 -f", program);
+    println!("  {} view image.png --hex --head 64", program);
+    println!("  {} grep \"TODO|FIXME\" src -C 2 --match \"*.rs\"", program);
+    println!("  {} sync src backup/src --delete --exclude target --exclude \"*.tmp\"", program);
+    println!("  {} watch src --exclude target --exec \"cargo test\"", program);
+    println!("  {} watch . --format json", program);
+    println!("  {} pack project project.tar.gz", program);
+    println!("  {} unpack project.zip --list -l", program);
+    println!("  {} unpack project.tar.gz /tmp/restore", program);
+    println!("  {} ln -s ../shared/config.toml config.toml", program);
+    println!("  {} size ~/projects --follow", program);
+    println!("  {} chmod u+x,g-w deploy.sh", program);
+    println!("  {} chmod -R a+rX public", program);
+    println!("  {} chown -R www-data:www-data /srv/site", program);
+    println!("  {} touch notes.txt --date \"2024-03-15 14:30\"", program);
+    println!("  {} create secret.key --mode 600", program);
+    println!("  {} --root /srv/data delete /srv/data/tmp/old.log", program);
+    println!("  {} --root /srv/data shell", program);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn run_in(args: &[&str]) -> i32 {
+        run(std::iter::once("file-utils").chain(args.iter().copied()).map(String::from).collect())
+    }
+
+    #[test]
+    fn test_command_table() {
+        let mut names: Vec<&str> = command_names().collect();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), COMMANDS.len());
+
+        assert_eq!(run_in(&["help"]), 0);
+        assert_eq!(run_in(&["bogus"]), EXIT_USAGE);
+        assert_eq!(run_in(&["copy", "only-source"]), EXIT_USAGE);
+    }
+
+    #[test]
+    fn test_diff_exit_code() {
+        let dir = TempDir::new().unwrap();
+        let path = |name: &str| dir.path().join(name).to_string_lossy().into_owned();
+        fs::write(path("a"), "same").unwrap();
+        fs::write(path("b"), "same").unwrap();
+        fs::write(path("c"), "other").unwrap();
+
+        assert_eq!(run_in(&["diff", &path("a"), &path("b")]), 0);
+        assert_eq!(run_in(&["diff", &path("a"), &path("c")]), 1);
+        assert_eq!(run_in(&["diff", &path("a"), &path("missing")]), 3);
+        assert_eq!(run_in(&["diff", &path("a")]), EXIT_USAGE);
+    }
+
+    #[test]
+    fn test_list_flags_stay_with_list() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().to_string_lossy().into_owned();
+        fs::write(dir.path().join("-l"), "").unwrap();
+
+        assert_eq!(run_in(&["list", &root, "-a", "-r", "-l", "--sort", "size"]), 0);
+        assert_eq!(run_in(&["list", &root, "--sort", "color"]), EXIT_USAGE);
+        // 其他命令的 -l、-a、-r 是普通参数
+        assert_eq!(run_in(&["find", &root, "-l"]), 0);
+        assert_eq!(run_in(&["grep", "-r", &root]), 0);
+    }
+
+    #[test]
+    fn test_command_options_stay_with_their_command() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().to_string_lossy().into_owned();
+        fs::write(dir.path().join("--delete"), "x").unwrap();
+
+        // 只有 sync 解析 --delete，find 和 grep 把它当作文件名模式和路径
+        assert_eq!(run_in(&["find", &root, "--delete"]), 0);
+        assert_eq!(run_in(&["grep", "x", &dir.path().join("--delete").to_string_lossy()]), 0);
+        assert_eq!(run_in(&["grep", "x", "--tree"]), 3);
+        assert!(dir.path().join("--delete").exists());
+
+        assert_eq!(run_in(&["list", &root, "--tree", "--depth", "1", "--follow"]), 0);
+        assert_eq!(run_in(&["list", &root, "--depth", "many"]), EXIT_USAGE);
+        assert_eq!(run_in(&["size", &root, "-L", "--no-follow"]), EXIT_USAGE);
+        assert_eq!(run_in(&["hash", &root, "--algorithm", "bogus"]), EXIT_USAGE);
+
+        // 命令选项写在命令之前时给出提示，而不是当作未知命令
+        assert_eq!(run_in(&["--follow", "list", &root]), EXIT_USAGE);
+        assert_eq!(run_in(&["--dry-run", "list", &root]), 0);
+    }
+
+    #[test]
+    fn test_shell_rejects_positional_arguments() {
+        assert_eq!(run_in(&["shell", "extra"]), EXIT_USAGE);
+        assert_eq!(run_in(&["shell", "--dry-run", "extra"]), EXIT_USAGE);
+        assert_eq!(run_in(&["shell", "--include=*.rs", "extra"]), EXIT_USAGE);
+    }
+
+    #[test]
+    fn test_view_tail_follow_flag() {
+        let strings = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+
+        let mut args = Args { words: strings(&["view", "app.log", "--tail-follow"]), defaults: Vec::new() };
+        assert!(take_view_options(&mut args).unwrap().follow);
+        assert_eq!(*args, ["view", "app.log"]);
+
+        // --follow 表示跟随符号链接，不会让 view 持续输出
+        let mut args = Args { words: strings(&["view", "app.log", "--follow"]), defaults: Vec::new() };
+        assert!(!take_view_options(&mut args).unwrap().follow);
+        assert_eq!(*args, ["view", "app.log", "--follow"]);
+    }
+}
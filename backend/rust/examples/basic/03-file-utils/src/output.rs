@@ -0,0 +1,510 @@
+use serde::Serialize;
+use std::str::FromStr;
+
+use crate::content::GrepResult;
+use crate::error::Result;
+use crate::records::{self, FileInfo, FileKind, FileRecord, TreeNode};
+use crate::utils::{self, TimeStyle};
+use crate::watch::{ChangeEvent, ChangeKind};
+
+/// 输出格式，对应 `--format`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum OutputFormat {
+    /// 带图标的人类可读表格
+    #[default]
+    Table,
+    Json,
+    Csv,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(OutputFormat::Table),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            other => Err(format!("无效的输出格式: {} (可选: table, json, csv)", other)),
+        }
+    }
+}
+
+const CSV_HEADER: [&str; 6] = ["name", "type", "size", "mtime", "mode", "path"];
+const CSV_INFO_HEADER: [&str; 4] = ["absolute_path", "created", "accessed", "links"];
+const CSV_GREP_HEADER: [&str; 4] = ["path", "line", "match", "text"];
+const CSV_EVENT_HEADER: [&str; 6] = ["event", "type", "size", "time", "path", "from"];
+
+/// 把命令返回的记录渲染为指定格式并打印
+pub struct Output {
+    format: OutputFormat,
+    time_style: TimeStyle,
+    long: bool,
+}
+
+impl Output {
+    pub fn new(format: OutputFormat, time_style: TimeStyle) -> Self {
+        Output { format, time_style, long: false }
+    }
+
+    /// 表格输出使用长格式（权限、所有者、所属组）
+    pub fn long(mut self, long: bool) -> Self {
+        self.long = long;
+        self
+    }
+
+    /// list 命令的输出
+    pub fn list(&self, dir: &str, records: &[FileRecord]) -> Result<()> {
+        match self.format {
+            OutputFormat::Json => print_json(&records),
+            OutputFormat::Csv => {
+                print_csv(&CSV_HEADER, records.iter().map(csv_fields));
+                Ok(())
+            },
+            OutputFormat::Table => {
+                println!("📁 目录内容: {}", dir);
+                println!("{}", "=".repeat(60));
+
+                for record in records {
+                    if self.long {
+                        println!("{} {}", self.long_columns(record), display_name(record));
+                        continue;
+                    }
+
+                    let (icon, type_str) = match record.kind {
+                        FileKind::Dir => ("📁", "DIR"),
+                        FileKind::Symlink => ("🔗", "LINK"),
+                        _ => ("📄", "FILE"),
+                    };
+
+                    println!("{} {:4} {:>10} {:19} {}",
+                            icon, type_str, size_column(record), self.time(record.mtime), display_name(record));
+                }
+                Ok(())
+            },
+        }
+    }
+
+    /// list --tree 的输出
+    pub fn tree(&self, root: &TreeNode) -> Result<()> {
+        match self.format {
+            OutputFormat::Json => print_json(root),
+            OutputFormat::Csv => {
+                let mut records = Vec::new();
+                flatten_tree(&root.children, &mut records);
+                print_csv(&CSV_HEADER, records.into_iter().map(csv_fields));
+                Ok(())
+            },
+            OutputFormat::Table => {
+                println!("{}", root.record.name);
+                let (dirs, files) = self.print_tree(&root.children, "");
+                println!();
+                println!("{} 个目录, {} 个文件", dirs, files);
+                Ok(())
+            },
+        }
+    }
+
+    /// 递归打印树的分支，返回 (目录数, 文件数)
+    fn print_tree(&self, nodes: &[TreeNode], prefix: &str) -> (usize, usize) {
+        let mut counts = (0, 0);
+
+        for (i, node) in nodes.iter().enumerate() {
+            let last = i + 1 == nodes.len();
+            let branch = if last { "└── " } else { "├── " };
+            let columns = if self.long {
+                format!("{} ", self.long_columns(&node.record))
+            } else {
+                String::new()
+            };
+            println!("{}{}{}{}", columns, prefix, branch, display_name(&node.record));
+
+            if node.record.kind == FileKind::Dir {
+                counts.0 += 1;
+                let child_prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+                let (dirs, files) = self.print_tree(&node.children, &child_prefix);
+                counts.0 += dirs;
+                counts.1 += files;
+            } else {
+                counts.1 += 1;
+            }
+        }
+
+        counts
+    }
+
+    /// 长格式的前几列：权限 所有者 所属组 大小 修改时间
+    fn long_columns(&self, record: &FileRecord) -> String {
+        let permissions = match record.mode {
+            Some(mode) => utils::permission_string(mode),
+            None => "-".repeat(10),
+        };
+
+        format!("{} {:8} {:8} {:>10} {:19}",
+                permissions,
+                record.owner.as_deref().unwrap_or("-"),
+                record.group.as_deref().unwrap_or("-"),
+                size_column(record),
+                self.time(record.mtime))
+    }
+
+    /// info 命令的输出
+    pub fn info(&self, info: &FileInfo) -> Result<()> {
+        match self.format {
+            OutputFormat::Json => print_json(info),
+            OutputFormat::Csv => {
+                let header: Vec<&str> = CSV_HEADER.iter().chain(CSV_INFO_HEADER.iter()).copied().collect();
+                let mut fields = csv_fields(&info.record);
+                fields.push(info.absolute_path.display().to_string());
+                fields.push(info.created.map(records::iso_time).unwrap_or_default());
+                fields.push(info.accessed.map(records::iso_time).unwrap_or_default());
+                fields.push(info.links.map(|n| n.to_string()).unwrap_or_default());
+                print_csv(&header, std::iter::once(fields));
+                Ok(())
+            },
+            OutputFormat::Table => {
+                let record = &info.record;
+
+                println!("📋 文件信息");
+                println!("{}", "=".repeat(40));
+                println!("文件名: {}", record.name);
+                println!("路径: {}", record.path.display());
+                println!("绝对路径: {}", info.absolute_path.display());
+                println!("类型: {}", match record.kind {
+                    FileKind::Dir => "目录",
+                    FileKind::Symlink => "符号链接",
+                    FileKind::Other => "其他",
+                    FileKind::File => "文件",
+                });
+
+                if let Some(target) = &record.target {
+                    println!("链接目标: {}", target.display());
+                }
+
+                if record.kind == FileKind::File {
+                    println!("大小: {} ({} 字节)", utils::format_size(record.size), record.size);
+                }
+
+                if info.created.is_some() {
+                    println!("创建时间: {}", self.time(info.created));
+                }
+
+                if record.mtime.is_some() {
+                    println!("修改时间: {}", self.time(record.mtime));
+                }
+
+                if info.accessed.is_some() {
+                    println!("访问时间: {}", self.time(info.accessed));
+                }
+
+                if let Some(mode) = record.mode {
+                    println!("权限: {:o}", mode);
+                }
+
+                if let Some(links) = info.links.filter(|&n| n > 1 && record.kind != FileKind::Dir) {
+                    println!("硬链接数: {}", links);
+                }
+                Ok(())
+            },
+        }
+    }
+
+    /// find 命令的输出
+    pub fn find(&self, dir: &str, pattern: &str, records: &[FileRecord]) -> Result<()> {
+        match self.format {
+            OutputFormat::Json => print_json(&records),
+            OutputFormat::Csv => {
+                print_csv(&CSV_HEADER, records.iter().map(csv_fields));
+                Ok(())
+            },
+            OutputFormat::Table => {
+                println!("🔍 在 {} 中搜索 \"{}\"", dir, pattern);
+                println!("{}", "=".repeat(40));
+
+                if records.is_empty() {
+                    println!("未找到匹配的文件");
+                }
+
+                for record in records {
+                    let size = if record.kind == FileKind::File {
+                        utils::format_size(record.size)
+                    } else {
+                        "DIR".to_string()
+                    };
+                    println!("📄 {} ({})", record.path.display(), size);
+                }
+                Ok(())
+            },
+        }
+    }
+
+    /// size 命令的输出
+    pub fn size(&self, record: &FileRecord) -> Result<()> {
+        match self.format {
+            OutputFormat::Json => print_json(record),
+            OutputFormat::Csv => {
+                print_csv(&CSV_HEADER, std::iter::once(csv_fields(record)));
+                Ok(())
+            },
+            OutputFormat::Table => {
+                println!("📊 大小统计: {}", record.path.display());
+                println!("{}", "=".repeat(30));
+                println!("总大小: {} ({} 字节)", utils::format_size(record.size), record.size);
+                Ok(())
+            },
+        }
+    }
+
+    /// grep 命令的输出；表格格式与 grep -n 相同，匹配行用 ":"、上下文行用 "-"，
+    /// 有上下文时不连续的段之间用 "--" 分隔
+    pub fn grep(&self, results: &[GrepResult], context: usize) -> Result<()> {
+        match self.format {
+            OutputFormat::Json => print_json(results),
+            OutputFormat::Csv => {
+                let rows = results.iter().flat_map(|result| {
+                    result.lines.iter().map(move |line| {
+                        vec![
+                            result.path.display().to_string(),
+                            line.line.to_string(),
+                            line.is_match.to_string(),
+                            line.text.clone(),
+                        ]
+                    })
+                });
+                print_csv(&CSV_GREP_HEADER, rows);
+                Ok(())
+            },
+            OutputFormat::Table => {
+                let mut first = true;
+                for result in results {
+                    if result.binary {
+                        println!("二进制文件 {} 匹配", result.path.display());
+                        continue;
+                    }
+
+                    let mut previous = None;
+                    for line in &result.lines {
+                        if context > 0 && !first && previous.is_none_or(|n| n + 1 != line.line) {
+                            println!("--");
+                        }
+                        first = false;
+                        previous = Some(line.line);
+
+                        let separator = if line.is_match { ':' } else { '-' };
+                        println!("{}{}{}{}{}", result.path.display(), separator, line.line, separator, line.text);
+                    }
+                }
+
+                let matches: usize = results.iter().map(|r| r.matches).sum();
+                println!();
+                println!("共 {} 处匹配，{} 个文件", matches, results.len());
+                Ok(())
+            },
+        }
+    }
+
+    /// watch 开始时的标题；CSV 在这里输出表头，之后每批只输出数据行
+    pub fn watch_start(&self, dir: &str) {
+        match self.format {
+            OutputFormat::Json => {},
+            OutputFormat::Csv => println!("{}", CSV_EVENT_HEADER.join(",")),
+            OutputFormat::Table => {
+                println!("👀 正在监控: {} (按 Ctrl+C 退出)", dir);
+                println!("{}", "=".repeat(60));
+            },
+        }
+    }
+
+    /// watch 的一批事件；JSON 格式为每行一个事件 (NDJSON)，便于管道逐行处理
+    pub fn events(&self, events: &[ChangeEvent]) -> Result<()> {
+        match self.format {
+            OutputFormat::Json => {
+                for event in events {
+                    println!("{}", serde_json::to_string(event)?);
+                }
+            },
+            OutputFormat::Csv => {
+                for event in events {
+                    print_csv_row(&event_fields(event));
+                }
+            },
+            OutputFormat::Table => {
+                for event in events {
+                    let (icon, label) = match event.event {
+                        ChangeKind::Created => ("➕", "新建"),
+                        ChangeKind::Modified => ("✏️ ", "修改"),
+                        ChangeKind::Deleted => ("🗑️ ", "删除"),
+                        ChangeKind::Renamed => ("🔀", "改名"),
+                    };
+                    let size = if event.kind == FileKind::File && event.event != ChangeKind::Deleted {
+                        utils::format_size(event.size)
+                    } else {
+                        "-".to_string()
+                    };
+                    let name = match &event.from {
+                        Some(from) => format!("{} -> {}", from.display(), event.path.display()),
+                        None => event.path.display().to_string(),
+                    };
+
+                    println!("{} {} {:>10} {:19} {}", icon, label, size, self.event_time(event.time), name);
+                }
+            },
+        }
+        Ok(())
+    }
+
+    /// 默认的相对时间对实时事件没有意义，改用时:分:秒
+    fn event_time(&self, timestamp: Option<i64>) -> String {
+        match (timestamp, &self.time_style) {
+            (Some(timestamp), TimeStyle::Relative) => {
+                utils::strftime("%H:%M:%S", timestamp, utils::local_offset(timestamp))
+            },
+            _ => self.time(timestamp),
+        }
+    }
+
+    fn time(&self, timestamp: Option<i64>) -> String {
+        match timestamp {
+            Some(timestamp) => utils::format_timestamp(timestamp, utils::local_offset(timestamp), &self.time_style),
+            None => "未知".to_string(),
+        }
+    }
+}
+
+/// 符号链接显示为 "name -> target"
+fn display_name(record: &FileRecord) -> String {
+    match &record.target {
+        Some(target) => format!("{} -> {}", record.name, target.display()),
+        None => record.name.clone(),
+    }
+}
+
+fn size_column(record: &FileRecord) -> String {
+    if record.kind == FileKind::File {
+        utils::format_size(record.size)
+    } else {
+        "-".to_string()
+    }
+}
+
+fn flatten_tree<'a>(nodes: &'a [TreeNode], out: &mut Vec<&'a FileRecord>) {
+    for node in nodes {
+        out.push(&node.record);
+        flatten_tree(&node.children, out);
+    }
+}
+
+fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+fn print_csv<I: Iterator<Item = Vec<String>>>(header: &[&str], rows: I) {
+    println!("{}", header.join(","));
+    for row in rows {
+        print_csv_row(&row);
+    }
+}
+
+fn print_csv_row(row: &[String]) {
+    let escaped: Vec<String> = row.iter().map(|field| csv_escape(field)).collect();
+    println!("{}", escaped.join(","));
+}
+
+fn csv_fields(record: &FileRecord) -> Vec<String> {
+    vec![
+        record.name.clone(),
+        record.kind.as_str().to_string(),
+        record.size.to_string(),
+        record.mtime.map(records::iso_time).unwrap_or_default(),
+        record.mode.map(records::octal_mode).unwrap_or_default(),
+        record.path.display().to_string(),
+    ]
+}
+
+fn event_fields(event: &ChangeEvent) -> Vec<String> {
+    vec![
+        event.event.as_str().to_string(),
+        event.kind.as_str().to_string(),
+        event.size.to_string(),
+        event.time.map(records::iso_time).unwrap_or_default(),
+        event.path.display().to_string(),
+        event.from.as_ref().map(|from| from.display().to_string()).unwrap_or_default(),
+    ]
+}
+
+/// 含逗号、引号或换行的字段用双引号包围，内部引号加倍
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn sample_record() -> FileRecord {
+        FileRecord {
+            name: "a,b.txt".to_string(),
+            kind: FileKind::File,
+            size: 42,
+            mtime: Some(0),
+            mode: Some(0o100644),
+            path: PathBuf::from("dir/a,b.txt"),
+            owner: None,
+            group: None,
+            target: None,
+        }
+    }
+
+    #[test]
+    fn test_csv_escape() {
+        assert_eq!(csv_escape("plain"), "plain");
+        assert_eq!(csv_escape("a,b"), "\"a,b\"");
+        assert_eq!(csv_escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_escape("line\nbreak"), "\"line\nbreak\"");
+    }
+
+    #[test]
+    fn test_csv_fields() {
+        let fields = csv_fields(&sample_record());
+        assert_eq!(fields.len(), CSV_HEADER.len());
+        assert_eq!(fields[1], "file");
+        assert_eq!(fields[2], "42");
+        assert_eq!(fields[4], "0644");
+    }
+
+    #[test]
+    fn test_json_record() {
+        let value = serde_json::to_value(sample_record()).unwrap();
+        assert_eq!(value["name"], "a,b.txt");
+        assert_eq!(value["type"], "file");
+        assert_eq!(value["size"], 42);
+        assert_eq!(value["mode"], "0644");
+        assert_eq!(value["path"], "dir/a,b.txt");
+        assert!(value["mtime"].as_str().unwrap().starts_with("19"));
+    }
+
+    #[test]
+    fn test_display_name_symlink() {
+        let mut record = sample_record();
+        assert_eq!(display_name(&record), "a,b.txt");
+
+        record.kind = FileKind::Symlink;
+        record.target = Some(PathBuf::from("../target.txt"));
+        assert_eq!(display_name(&record), "a,b.txt -> ../target.txt");
+        assert_eq!(serde_json::to_value(&record).unwrap()["target"], "../target.txt");
+    }
+
+    #[test]
+    fn test_output_format_from_str() {
+        assert_eq!("json".parse::<OutputFormat>(), Ok(OutputFormat::Json));
+        assert_eq!("csv".parse::<OutputFormat>(), Ok(OutputFormat::Csv));
+        assert_eq!("table".parse::<OutputFormat>(), Ok(OutputFormat::Table));
+        assert!("xml".parse::<OutputFormat>().is_err());
+    }
+}
@@ -0,0 +1,345 @@
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::error::{FileUtilsError, PathContext, Result};
+
+/// 每类用户可影响的位：u 含 setuid，g 含 setgid，o 含 sticky
+const USER_BITS: u32 = 0o4700;
+const GROUP_BITS: u32 = 0o2070;
+const OTHER_BITS: u32 = 0o1007;
+
+/// chmod 的模式，八进制（如 "755"）或符号形式（如 "u+x,g-w"）
+#[derive(Debug, Clone, PartialEq)]
+pub enum ModeSpec {
+    Octal(u32),
+    Symbolic(Vec<Clause>),
+}
+
+/// 符号模式中逗号分隔的一段，如 "go-w" 或 "u=rw,+X" 中的 "u=rw"
+#[derive(Debug, Clone, PartialEq)]
+pub struct Clause {
+    /// 受影响的位，who 为空时等同于 a
+    who: u32,
+    actions: Vec<Action>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Action {
+    op: char,
+    perms: Perms,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Perms {
+    /// rwxXst 的组合
+    Letters(String),
+    /// 从 u、g 或 o 复制当前权限，如 "g=u"
+    Copy(char),
+}
+
+impl ModeSpec {
+    /// 把模式应用到当前权限位上，返回新的权限位（含特殊位，不含文件类型）
+    pub fn apply(&self, mode: u32, is_dir: bool) -> u32 {
+        let mut mode = mode & 0o7777;
+
+        match self {
+            ModeSpec::Octal(bits) => *bits,
+            ModeSpec::Symbolic(clauses) => {
+                for clause in clauses {
+                    for action in &clause.actions {
+                        let bits = action.perms.bits(mode, is_dir) & clause.who;
+                        mode = match action.op {
+                            '+' => mode | bits,
+                            '-' => mode & !bits,
+                            _ => (mode & !clause.who) | bits,
+                        };
+                    }
+                }
+                mode
+            },
+        }
+    }
+}
+
+impl Perms {
+    fn bits(&self, mode: u32, is_dir: bool) -> u32 {
+        match self {
+            Perms::Copy(class) => {
+                let shift = match class {
+                    'u' => 6,
+                    'g' => 3,
+                    _ => 0,
+                };
+                let bits = (mode >> shift) & 0o7;
+                bits << 6 | bits << 3 | bits
+            },
+            Perms::Letters(letters) => letters.chars().fold(0, |acc, letter| {
+                acc | match letter {
+                    'r' => 0o444,
+                    'w' => 0o222,
+                    'x' => 0o111,
+                    // 只对目录和已有执行位的文件添加执行权限
+                    'X' if is_dir || mode & 0o111 != 0 => 0o111,
+                    's' => 0o6000,
+                    't' => 0o1000,
+                    _ => 0,
+                }
+            }),
+        }
+    }
+}
+
+impl FromStr for ModeSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        if !s.is_empty() && s.chars().all(|c| c.is_digit(8)) {
+            return match u32::from_str_radix(s, 8) {
+                Ok(bits) if s.len() <= 4 => Ok(ModeSpec::Octal(bits)),
+                _ => Err(format!("无效的权限模式: {} (八进制最多 4 位)", s)),
+            };
+        }
+
+        s.split(',')
+            .map(|clause| parse_clause(clause).ok_or_else(|| format!("无效的权限模式: {} (例如 755、u+x,g-w、a=rX)", s)))
+            .collect::<std::result::Result<_, _>>()
+            .map(ModeSpec::Symbolic)
+    }
+}
+
+fn parse_clause(text: &str) -> Option<Clause> {
+    let split = text.find(['+', '-', '='])?;
+    let (who_text, mut rest) = text.split_at(split);
+
+    let who = who_text.chars().try_fold(0, |acc, c| {
+        Some(acc | match c {
+            'u' => USER_BITS,
+            'g' => GROUP_BITS,
+            'o' => OTHER_BITS,
+            'a' => 0o7777,
+            _ => return None,
+        })
+    })?;
+
+    let mut actions = Vec::new();
+    while let Some(op) = rest.chars().next() {
+        let end = rest[1..].find(['+', '-', '=']).map_or(rest.len(), |i| i + 1);
+        let perms = &rest[1..end];
+        let perms = match perms {
+            "u" | "g" | "o" => Perms::Copy(perms.chars().next()?),
+            _ if perms.chars().all(|c| "rwxXst".contains(c)) => Perms::Letters(perms.to_string()),
+            _ => return None,
+        };
+        actions.push(Action { op, perms });
+        rest = &rest[end..];
+    }
+
+    Some(Clause {
+        who: if who == 0 { 0o7777 } else { who },
+        actions,
+    })
+}
+
+/// chown 的目标所有者，形如 "user"、"user:group"、":group"，名称或数字 id 均可
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Owner {
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+}
+
+impl FromStr for Owner {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (user, group) = match s.split_once(':') {
+            Some((user, group)) => (user, group),
+            None => (s, ""),
+        };
+
+        let uid = match user {
+            "" => None,
+            _ => Some(user.parse().ok().or_else(|| user_id(user)).ok_or_else(|| format!("用户不存在: {}", user))?),
+        };
+        let gid = match group {
+            "" => None,
+            _ => Some(group.parse().ok().or_else(|| group_id(group)).ok_or_else(|| format!("用户组不存在: {}", group))?),
+        };
+
+        if uid.is_none() && gid.is_none() {
+            return Err(format!("无效的所有者: {} (例如 alice、alice:staff、:staff、1000:1000)", s));
+        }
+        Ok(Owner { uid, gid })
+    }
+}
+
+/// 通过 getpwnam_r 查询用户 id
+#[cfg(unix)]
+fn user_id(name: &str) -> Option<u32> {
+    let name = std::ffi::CString::new(name).ok()?;
+    let mut buf = vec![0 as libc::c_char; 4096];
+    let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut result: *mut libc::passwd = std::ptr::null_mut();
+
+    // SAFETY: 所有指针都指向本函数内有效的缓冲区
+    let rc = unsafe { libc::getpwnam_r(name.as_ptr(), &mut passwd, buf.as_mut_ptr(), buf.len(), &mut result) };
+    (rc == 0 && !result.is_null()).then_some(passwd.pw_uid)
+}
+
+/// 通过 getgrnam_r 查询组 id
+#[cfg(unix)]
+fn group_id(name: &str) -> Option<u32> {
+    let name = std::ffi::CString::new(name).ok()?;
+    let mut buf = vec![0 as libc::c_char; 4096];
+    let mut group: libc::group = unsafe { std::mem::zeroed() };
+    let mut result: *mut libc::group = std::ptr::null_mut();
+
+    // SAFETY: 同 user_id
+    let rc = unsafe { libc::getgrnam_r(name.as_ptr(), &mut group, buf.as_mut_ptr(), buf.len(), &mut result) };
+    (rc == 0 && !result.is_null()).then_some(group.gr_gid)
+}
+
+#[cfg(not(unix))]
+fn user_id(_name: &str) -> Option<u32> {
+    None
+}
+
+#[cfg(not(unix))]
+fn group_id(_name: &str) -> Option<u32> {
+    None
+}
+
+/// 当前权限位，非 Unix 平台返回 None
+#[cfg(unix)]
+pub fn mode_of(metadata: &fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(metadata.permissions().mode() & 0o7777)
+}
+
+#[cfg(not(unix))]
+pub fn mode_of(_metadata: &fs::Metadata) -> Option<u32> {
+    None
+}
+
+/// 当前的 (uid, gid)，非 Unix 平台返回 None
+#[cfg(unix)]
+pub fn owner_of(metadata: &fs::Metadata) -> Option<(u32, u32)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.uid(), metadata.gid()))
+}
+
+#[cfg(not(unix))]
+pub fn owner_of(_metadata: &fs::Metadata) -> Option<(u32, u32)> {
+    None
+}
+
+/// 以 "用户:组" 的形式显示所有者，查不到名称时显示数字 id
+pub fn owner_string(uid: u32, gid: u32) -> String {
+    #[cfg(unix)]
+    let (user, group) = (crate::records::user_name(uid), crate::records::group_name(gid));
+    #[cfg(not(unix))]
+    let (user, group): (Option<String>, Option<String>) = (None, None);
+
+    format!(
+        "{}:{}",
+        user.unwrap_or_else(|| uid.to_string()),
+        group.unwrap_or_else(|| gid.to_string())
+    )
+}
+
+/// 设置权限位，会跟随符号链接
+#[cfg(unix)]
+pub fn set_mode(path: &Path, mode: u32) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode)).with_path(path)
+}
+
+#[cfg(not(unix))]
+pub fn set_mode(_path: &Path, _mode: u32) -> Result<()> {
+    Err(FileUtilsError::InvalidInput("chmod 仅支持 Unix 平台".to_string()))
+}
+
+/// 修改所有者；follow 为 false 时修改符号链接本身
+#[cfg(unix)]
+pub fn set_owner(path: &Path, owner: &Owner, follow: bool) -> Result<()> {
+    if follow {
+        std::os::unix::fs::chown(path, owner.uid, owner.gid).with_path(path)
+    } else {
+        std::os::unix::fs::lchown(path, owner.uid, owner.gid).with_path(path)
+    }
+}
+
+#[cfg(not(unix))]
+pub fn set_owner(_path: &Path, _owner: &Owner, _follow: bool) -> Result<()> {
+    Err(FileUtilsError::InvalidInput("chown 仅支持 Unix 平台".to_string()))
+}
+
+/// 把访问时间和修改时间都设为 timestamp，不要求文件可读写；follow 为 false 时修改符号链接本身
+#[cfg(unix)]
+pub fn set_times(path: &Path, timestamp: i64, follow: bool) -> Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| FileUtilsError::InvalidInput(format!("路径包含空字符: {}", path.display())))?;
+    let time = libc::timespec { tv_sec: timestamp as libc::time_t, tv_nsec: 0 };
+    let times = [time, time];
+
+    let flags = if follow { 0 } else { libc::AT_SYMLINK_NOFOLLOW };
+
+    // SAFETY: c_path 和 times 在调用期间有效
+    let rc = unsafe { libc::utimensat(libc::AT_FDCWD, c_path.as_ptr(), times.as_ptr(), flags) };
+    if rc != 0 {
+        return Err(std::io::Error::last_os_error()).with_path(path);
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn set_times(path: &Path, timestamp: i64, _follow: bool) -> Result<()> {
+    let time = std::time::UNIX_EPOCH + std::time::Duration::from_secs(timestamp.max(0) as u64);
+    let file = fs::File::options().write(true).open(path).with_path(path)?;
+    file.set_times(fs::FileTimes::new().set_accessed(time).set_modified(time)).with_path(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(spec: &str, mode: u32, is_dir: bool) -> u32 {
+        spec.parse::<ModeSpec>().unwrap().apply(mode, is_dir)
+    }
+
+    #[test]
+    fn test_octal_and_symbolic_modes() {
+        assert_eq!(apply("755", 0o644, false), 0o755);
+        assert_eq!(apply("0600", 0o644, false), 0o600);
+        assert_eq!(apply("u+x,g-w", 0o664, false), 0o744);
+        assert_eq!(apply("+x", 0o644, false), 0o755);
+        assert_eq!(apply("go=", 0o755, false), 0o700);
+        assert_eq!(apply("a=r,u+w", 0o777, false), 0o644);
+        assert_eq!(apply("g=u", 0o740, false), 0o770);
+        assert_eq!(apply("u-w+x", 0o644, false), 0o544);
+        assert_eq!(apply("u+s,+t", 0o755, false), 0o5755);
+
+        // X 只给目录或已可执行的文件加执行位
+        assert_eq!(apply("a+X", 0o644, false), 0o644);
+        assert_eq!(apply("a+X", 0o644, true), 0o755);
+        assert_eq!(apply("a+X", 0o744, false), 0o755);
+
+        for invalid in ["", "8", "12345", "u", "z+x", "u+q", "u+x,"] {
+            assert!(invalid.parse::<ModeSpec>().is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_parse_owner() {
+        assert_eq!("1000:1000".parse(), Ok(Owner { uid: Some(1000), gid: Some(1000) }));
+        assert_eq!(":50".parse(), Ok(Owner { uid: None, gid: Some(50) }));
+        assert_eq!("7".parse(), Ok(Owner { uid: Some(7), gid: None }));
+        assert!(":".parse::<Owner>().is_err());
+        assert!("no-such-user-xyz".parse::<Owner>().is_err());
+
+        #[cfg(unix)]
+        assert_eq!("root:root".parse(), Ok(Owner { uid: Some(0), gid: Some(0) }));
+    }
+}
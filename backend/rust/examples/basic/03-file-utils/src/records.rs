@@ -0,0 +1,247 @@
+use serde::{Serialize, Serializer};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::{PathContext, Result};
+use crate::links;
+use crate::utils::{self, TimeStyle};
+
+/// 文件类型
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileKind {
+    File,
+    Dir,
+    Symlink,
+    Other,
+}
+
+impl FileKind {
+    pub fn from_metadata(metadata: &fs::Metadata) -> Self {
+        if metadata.is_symlink() {
+            FileKind::Symlink
+        } else if metadata.is_dir() {
+            FileKind::Dir
+        } else if metadata.is_file() {
+            FileKind::File
+        } else {
+            FileKind::Other
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileKind::File => "file",
+            FileKind::Dir => "dir",
+            FileKind::Symlink => "symlink",
+            FileKind::Other => "other",
+        }
+    }
+}
+
+/// 一个文件或目录的结构化描述，list/find/size 等命令都返回它
+#[derive(Debug, Clone, Serialize)]
+pub struct FileRecord {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: FileKind,
+    /// 文件为字节数，目录在 size 命令中为递归总大小，其他情况为 0
+    pub size: u64,
+    /// 修改时间（Unix 时间戳），输出为 ISO-8601 字符串
+    #[serde(serialize_with = "serialize_time")]
+    pub mtime: Option<i64>,
+    /// 权限位，仅 Unix 平台，输出为八进制字符串如 "0644"
+    #[serde(serialize_with = "serialize_mode")]
+    pub mode: Option<u32>,
+    pub path: PathBuf,
+    /// 所有者和所属组，仅 Unix 平台
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// 符号链接指向的路径
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<PathBuf>,
+}
+
+impl FileRecord {
+    /// 从路径读取元数据构造记录
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let metadata = fs::metadata(path).with_path(path)?;
+        Ok(FileRecord::from_metadata(path, &metadata))
+    }
+
+    /// 从已有的元数据构造记录；metadata 来自 symlink_metadata 时会记录链接目标
+    pub fn from_metadata(path: &Path, metadata: &fs::Metadata) -> Self {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.display().to_string());
+        let (owner, group) = owner_of(metadata);
+
+        FileRecord {
+            name,
+            kind: FileKind::from_metadata(metadata),
+            size: if metadata.is_file() { metadata.len() } else { 0 },
+            mtime: metadata.modified().ok().map(to_timestamp),
+            mode: mode_of(metadata),
+            path: path.to_path_buf(),
+            owner,
+            group,
+            target: if metadata.is_symlink() { fs::read_link(path).ok() } else { None },
+        }
+    }
+
+    /// 文件扩展名，没有时为空字符串
+    pub fn extension(&self) -> &str {
+        utils::get_file_extension(&self.name).unwrap_or("")
+    }
+
+    /// 是否为隐藏文件（以点开头）
+    pub fn is_hidden(&self) -> bool {
+        self.name.starts_with('.')
+    }
+}
+
+/// tree 视图中的一个节点
+#[derive(Debug, Clone, Serialize)]
+pub struct TreeNode {
+    #[serde(flatten)]
+    pub record: FileRecord,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<TreeNode>,
+}
+
+/// info 命令的详细信息
+#[derive(Debug, Clone, Serialize)]
+pub struct FileInfo {
+    #[serde(flatten)]
+    pub record: FileRecord,
+    pub absolute_path: PathBuf,
+    #[serde(serialize_with = "serialize_time")]
+    pub created: Option<i64>,
+    #[serde(serialize_with = "serialize_time")]
+    pub accessed: Option<i64>,
+    /// 硬链接数，仅 Unix 平台
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub links: Option<u64>,
+}
+
+impl FileInfo {
+    /// follow 为 false 时描述符号链接本身，绝对路径也只规范化到链接所在目录
+    pub fn from_path(path: &Path, follow: bool) -> Result<Self> {
+        let metadata = links::metadata(path, follow)?;
+
+        Ok(FileInfo {
+            record: FileRecord::from_metadata(path, &metadata),
+            absolute_path: absolute_path(path, follow && !metadata.is_symlink())?,
+            created: metadata.created().ok().map(to_timestamp),
+            accessed: metadata.accessed().ok().map(to_timestamp),
+            links: links::link_count(&metadata),
+        })
+    }
+}
+
+fn absolute_path(path: &Path, follow: bool) -> Result<PathBuf> {
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) if !follow => {
+            let parent = if parent.as_os_str().is_empty() { Path::new(".") } else { parent };
+            Ok(fs::canonicalize(parent).with_path(parent)?.join(name))
+        },
+        _ => fs::canonicalize(path).with_path(path),
+    }
+}
+
+pub fn to_timestamp(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs() as i64),
+    }
+}
+
+/// 机器可读的时间格式：本地时区的完整 ISO-8601
+pub fn iso_time(timestamp: i64) -> String {
+    utils::format_timestamp(timestamp, utils::local_offset(timestamp), &TimeStyle::FullIso)
+}
+
+/// 机器可读的权限格式：四位八进制
+pub fn octal_mode(mode: u32) -> String {
+    format!("{:04o}", mode & 0o7777)
+}
+
+pub fn serialize_time<S: Serializer>(time: &Option<i64>, serializer: S) -> Result<S::Ok, S::Error> {
+    match time {
+        Some(timestamp) => serializer.serialize_str(&iso_time(*timestamp)),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn serialize_mode<S: Serializer>(mode: &Option<u32>, serializer: S) -> Result<S::Ok, S::Error> {
+    match mode {
+        Some(mode) => serializer.serialize_str(&octal_mode(*mode)),
+        None => serializer.serialize_none(),
+    }
+}
+
+#[cfg(unix)]
+fn mode_of(metadata: &fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(metadata.permissions().mode())
+}
+
+#[cfg(not(unix))]
+fn mode_of(_metadata: &fs::Metadata) -> Option<u32> {
+    None
+}
+
+/// 所有者和所属组的名字，查不到名字时使用数字 id
+#[cfg(unix)]
+fn owner_of(metadata: &fs::Metadata) -> (Option<String>, Option<String>) {
+    use std::os::unix::fs::MetadataExt;
+    let uid = metadata.uid();
+    let gid = metadata.gid();
+    (
+        Some(user_name(uid).unwrap_or_else(|| uid.to_string())),
+        Some(group_name(gid).unwrap_or_else(|| gid.to_string())),
+    )
+}
+
+#[cfg(not(unix))]
+fn owner_of(_metadata: &fs::Metadata) -> (Option<String>, Option<String>) {
+    (None, None)
+}
+
+/// 通过 getpwuid_r 查询用户名
+#[cfg(unix)]
+pub fn user_name(uid: u32) -> Option<String> {
+    let mut buf = vec![0 as libc::c_char; 4096];
+    let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut result: *mut libc::passwd = std::ptr::null_mut();
+
+    // SAFETY: 所有指针都指向本函数内有效的缓冲区，返回的字符串在 buf 中
+    let rc = unsafe { libc::getpwuid_r(uid, &mut passwd, buf.as_mut_ptr(), buf.len(), &mut result) };
+    if rc != 0 || result.is_null() {
+        return None;
+    }
+
+    let name = unsafe { std::ffi::CStr::from_ptr(passwd.pw_name) };
+    Some(name.to_string_lossy().to_string())
+}
+
+/// 通过 getgrgid_r 查询组名
+#[cfg(unix)]
+pub fn group_name(gid: u32) -> Option<String> {
+    let mut buf = vec![0 as libc::c_char; 4096];
+    let mut group: libc::group = unsafe { std::mem::zeroed() };
+    let mut result: *mut libc::group = std::ptr::null_mut();
+
+    // SAFETY: 同 user_name
+    let rc = unsafe { libc::getgrgid_r(gid, &mut group, buf.as_mut_ptr(), buf.len(), &mut result) };
+    if rc != 0 || result.is_null() {
+        return None;
+    }
+
+    let name = unsafe { std::ffi::CStr::from_ptr(group.gr_name) };
+    Some(name.to_string_lossy().to_string())
+}
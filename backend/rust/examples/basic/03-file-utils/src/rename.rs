@@ -0,0 +1,440 @@
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::str::FromStr;
+
+use crate::error::{FileUtilsError, PathContext, Result};
+use crate::utils;
+
+/// 大小写转换，对应 `--case`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CaseConversion {
+    Lower,
+    Upper,
+    /// 每个单词首字母大写
+    Title,
+}
+
+impl FromStr for CaseConversion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lower" => Ok(CaseConversion::Lower),
+            "upper" => Ok(CaseConversion::Upper),
+            "title" => Ok(CaseConversion::Title),
+            other => Err(format!("无效的大小写转换: {} (可选: lower, upper, title)", other)),
+        }
+    }
+}
+
+impl CaseConversion {
+    fn apply(&self, text: &str) -> String {
+        match self {
+            CaseConversion::Lower => text.to_lowercase(),
+            CaseConversion::Upper => text.to_uppercase(),
+            CaseConversion::Title => {
+                let mut result = String::with_capacity(text.len());
+                let mut word_start = true;
+                for c in text.chars() {
+                    if c.is_alphanumeric() {
+                        if word_start {
+                            result.extend(c.to_uppercase());
+                        } else {
+                            result.extend(c.to_lowercase());
+                        }
+                        word_start = false;
+                    } else {
+                        result.push(c);
+                        word_start = true;
+                    }
+                }
+                result
+            },
+        }
+    }
+}
+
+/// 对文件名的文本替换
+#[derive(Debug, Clone)]
+pub enum Substitution {
+    /// 普通文本，替换所有出现的位置
+    Literal { find: String, replace: String },
+    /// 正则表达式，replace 中可以用 `$1`、`${name}` 引用捕获组
+    Regex { pattern: Regex, replace: String },
+}
+
+impl Substitution {
+    pub fn regex(pattern: &str, replace: &str) -> Result<Self> {
+        let pattern = Regex::new(pattern)
+            .map_err(|e| FileUtilsError::InvalidInput(format!("无效的正则表达式: {}", e)))?;
+        Ok(Substitution::Regex { pattern, replace: replace.to_string() })
+    }
+
+    fn apply(&self, name: &str) -> String {
+        match self {
+            Substitution::Literal { find, replace } => name.replace(find.as_str(), replace),
+            Substitution::Regex { pattern, replace } => pattern.replace_all(name, replace.as_str()).into_owned(),
+        }
+    }
+}
+
+/// 一组重命名规则，按 替换 → 模板 → 大小写 → 扩展名 的顺序应用
+#[derive(Debug, Clone)]
+pub struct RenameRules {
+    pub substitution: Option<Substitution>,
+    /// 新文件名模板，支持 `{n}`、`{n:03}`、`{name}`（不含扩展名）和 `{ext}`
+    pub template: Option<String>,
+    /// `{n}` 的起始编号
+    pub start: usize,
+    pub case: Option<CaseConversion>,
+    /// 新的扩展名，空字符串表示去掉扩展名
+    pub extension: Option<String>,
+}
+
+impl Default for RenameRules {
+    fn default() -> Self {
+        RenameRules {
+            substitution: None,
+            template: None,
+            start: 1,
+            case: None,
+            extension: None,
+        }
+    }
+}
+
+impl RenameRules {
+    pub fn is_empty(&self) -> bool {
+        self.substitution.is_none() && self.template.is_none() && self.case.is_none() && self.extension.is_none()
+    }
+
+    /// 计算第 index 个（从 0 开始）文件的新名字
+    pub fn apply(&self, name: &str, index: usize) -> Result<String> {
+        let mut new_name = match &self.substitution {
+            Some(substitution) => substitution.apply(name),
+            None => name.to_string(),
+        };
+
+        if let Some(template) = &self.template {
+            new_name = expand_template(template, &new_name, self.start + index)?;
+        }
+
+        if let Some(case) = self.case {
+            new_name = case.apply(&new_name);
+        }
+
+        if let Some(extension) = &self.extension {
+            let (stem, _) = split_extension(&new_name);
+            let extension = extension.trim_start_matches('.');
+            new_name = if extension.is_empty() {
+                stem.to_string()
+            } else {
+                format!("{}.{}", stem, extension)
+            };
+        }
+
+        Ok(new_name)
+    }
+}
+
+/// 一次重命名：同一目录中 from -> to
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rename {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+/// 为目录中的一批文件生成重命名计划，名字不变的文件不会出现在计划中
+///
+/// 整批检查冲突：两个文件不能得到同一个新名字，新名字也不能是批次之外的已有文件。
+pub fn plan(dir: &Path, names: &[String], rules: &RenameRules) -> Result<Vec<Rename>> {
+    let mut renames = Vec::new();
+    let mut targets: HashMap<String, Vec<PathBuf>> = HashMap::new();
+
+    for (index, name) in names.iter().enumerate() {
+        let new_name = rules.apply(name, index)?;
+
+        if new_name.is_empty() || new_name == "." || new_name == ".." || Path::new(&new_name).components().count() != 1 {
+            return Err(FileUtilsError::InvalidInput(format!("无效的新文件名: {} -> {:?}", name, new_name)));
+        }
+
+        targets.entry(new_name.clone()).or_default().push(dir.join(name));
+
+        if &new_name != name {
+            renames.push(Rename {
+                from: dir.join(name),
+                to: dir.join(new_name),
+            });
+        }
+    }
+
+    if let Some((target, sources)) = targets.into_iter().find(|(_, sources)| sources.len() > 1) {
+        return Err(FileUtilsError::RenameConflict { target: dir.join(target), sources });
+    }
+
+    // 目标已存在时，只有它自己也在本批次中被改名（或就是源文件本身）才允许
+    let sources: HashSet<&str> = names.iter().map(String::as_str).collect();
+    for rename in &renames {
+        let target_name = rename.to.file_name().unwrap_or_default().to_string_lossy();
+        if rename.to.symlink_metadata().is_ok() && !sources.contains(target_name.as_ref()) {
+            return Err(FileUtilsError::AlreadyExists { path: rename.to.clone() });
+        }
+    }
+
+    Ok(renames)
+}
+
+/// 执行重命名计划，任何一步失败都会把已完成的步骤全部撤回
+pub fn execute(renames: &[Rename]) -> Result<()> {
+    execute_with(renames, |from, to| fs::rename(from, to))
+}
+
+/// 先把所有文件改成临时名字，再改成最终名字，这样 a->b、b->a 这样的交换也能完成
+fn execute_with<F>(renames: &[Rename], mut rename: F) -> Result<()>
+where
+    F: FnMut(&Path, &Path) -> io::Result<()>,
+{
+    let temporaries: Vec<PathBuf> = renames
+        .iter()
+        .enumerate()
+        .map(|(index, r)| {
+            let name = r.from.file_name().unwrap_or_default().to_string_lossy();
+            r.from.with_file_name(format!(".{}.file-utils-rename.{}.{}", name, process::id(), index))
+        })
+        .collect();
+
+    let mut done: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let mut step = |from: &Path, to: &Path| -> Result<()> {
+        if to.symlink_metadata().is_ok() {
+            return Err(FileUtilsError::AlreadyExists { path: to.to_path_buf() });
+        }
+        rename(from, to).with_path(from)?;
+        done.push((from.to_path_buf(), to.to_path_buf()));
+        Ok(())
+    };
+
+    let result = renames
+        .iter()
+        .zip(&temporaries)
+        .try_for_each(|(r, temporary)| step(&r.from, temporary))
+        .and_then(|_| {
+            renames
+                .iter()
+                .zip(&temporaries)
+                .try_for_each(|(r, temporary)| step(temporary, &r.to))
+        });
+
+    if result.is_err() {
+        for (from, to) in done.iter().rev() {
+            if let Err(e) = fs::rename(to, from) {
+                eprintln!("⚠️  回滚失败: {} -> {}: {}", to.display(), from.display(), e);
+            }
+        }
+    }
+
+    result
+}
+
+/// 展开文件名模板，`{{` 和 `}}` 表示字面的花括号
+fn expand_template(template: &str, name: &str, number: usize) -> Result<String> {
+    let (stem, extension) = split_extension(name);
+    let mut result = String::new();
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                result.push('{');
+            },
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                result.push('}');
+            },
+            '{' => {
+                let placeholder: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                let (key, format) = placeholder.split_once(':').unwrap_or((placeholder.as_str(), ""));
+
+                match key {
+                    "n" => result.push_str(&format_number(number, format)?),
+                    "name" => result.push_str(stem),
+                    "ext" => result.push_str(extension.unwrap_or("")),
+                    _ => return Err(FileUtilsError::InvalidInput(format!("未知的模板占位符: {{{}}}", placeholder))),
+                }
+            },
+            _ => result.push(c),
+        }
+    }
+
+    Ok(result)
+}
+
+/// `{n:03}` 补零到 3 位，`{n:3}` 用空格补齐
+fn format_number(number: usize, format: &str) -> Result<String> {
+    if format.is_empty() {
+        return Ok(number.to_string());
+    }
+
+    let width: usize = format
+        .parse()
+        .map_err(|_| FileUtilsError::InvalidInput(format!("无效的编号格式: {{n:{}}}", format)))?;
+
+    Ok(if format.starts_with('0') {
+        format!("{:0width$}", number, width = width)
+    } else {
+        format!("{:width$}", number, width = width)
+    })
+}
+
+/// 拆分文件名和扩展名；以点开头且没有其他点的名字（如 .bashrc）没有扩展名
+fn split_extension(name: &str) -> (&str, Option<&str>) {
+    match utils::get_file_extension(name) {
+        Some(extension) if name.len() > extension.len() + 1 => {
+            (&name[..name.len() - extension.len() - 1], Some(extension))
+        },
+        _ => (name, None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_rules() {
+        let rules = RenameRules {
+            substitution: Some(Substitution::Literal { find: " ".to_string(), replace: "_".to_string() }),
+            ..Default::default()
+        };
+        assert_eq!(rules.apply("my holiday pic.JPG", 0).unwrap(), "my_holiday_pic.JPG");
+
+        let rules = RenameRules {
+            substitution: Some(Substitution::regex(r"^IMG_(\d{4})(\d{2})(\d{2})", "$1-$2-$3").unwrap()),
+            case: Some(CaseConversion::Lower),
+            extension: Some("jpeg".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(rules.apply("IMG_20240315_001.JPG", 0).unwrap(), "2024-03-15_001.jpeg");
+
+        let rules = RenameRules {
+            template: Some("photo_{n:03}.{ext}".to_string()),
+            start: 9,
+            ..Default::default()
+        };
+        assert_eq!(rules.apply("a.jpg", 0).unwrap(), "photo_009.jpg");
+        assert_eq!(rules.apply("b.jpg", 1).unwrap(), "photo_010.jpg");
+
+        let rules = RenameRules {
+            case: Some(CaseConversion::Title),
+            extension: Some(String::new()),
+            ..Default::default()
+        };
+        assert_eq!(rules.apply("hello wORLD-again.txt", 0).unwrap(), "Hello World-Again");
+    }
+
+    #[test]
+    fn test_expand_template() {
+        assert_eq!(expand_template("{name}_{n:2}.{ext}", "a.b.txt", 7).unwrap(), "a.b_ 7.txt");
+        assert_eq!(expand_template("{{{n}}}", "x", 1).unwrap(), "{1}");
+        assert_eq!(expand_template("{name}", ".bashrc", 1).unwrap(), ".bashrc");
+        assert!(expand_template("{size}", "x", 1).is_err());
+    }
+
+    #[test]
+    fn test_plan_detects_collisions() {
+        let dir = TempDir::new().unwrap();
+        for name in ["a.txt", "b.txt", "keep.md"] {
+            fs::write(dir.path().join(name), name).unwrap();
+        }
+
+        // 两个文件得到同一个名字
+        let rules = RenameRules { template: Some("same.txt".to_string()), ..Default::default() };
+        assert!(matches!(
+            plan(dir.path(), &names(&["a.txt", "b.txt"]), &rules),
+            Err(FileUtilsError::RenameConflict { .. })
+        ));
+
+        // 新名字是批次之外的已有文件
+        let rules = RenameRules {
+            substitution: Some(Substitution::Literal { find: "a".to_string(), replace: "keep".to_string() }),
+            extension: Some("md".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(plan(dir.path(), &names(&["a.txt"]), &rules), Err(FileUtilsError::AlreadyExists { .. })));
+
+        // 目标虽然存在，但它自己也在本批次中被改名
+        let rules = RenameRules { extension: Some("md".to_string()), ..Default::default() };
+        let renames = plan(dir.path(), &names(&["keep.md", "a.txt"]), &RenameRules {
+            template: Some("{n}.md".to_string()),
+            ..rules
+        });
+        assert_eq!(renames.unwrap().len(), 2);
+
+        // 不能跳出目录
+        let rules = RenameRules { template: Some("../{name}".to_string()), ..Default::default() };
+        assert!(matches!(plan(dir.path(), &names(&["a.txt"]), &rules), Err(FileUtilsError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_execute_swap() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("a"), "A").unwrap();
+        fs::write(dir.path().join("b"), "B").unwrap();
+
+        let rules = RenameRules {
+            substitution: Some(Substitution::regex("^(a|b)$", "x$1").unwrap()),
+            ..Default::default()
+        };
+        let renames = plan(dir.path(), &names(&["a", "b"]), &rules).unwrap();
+        execute(&renames).unwrap();
+        assert_eq!(fs::read_to_string(dir.path().join("xa")).unwrap(), "A");
+
+        // 交换两个文件的名字
+        let swap = vec![
+            Rename { from: dir.path().join("xa"), to: dir.path().join("xb") },
+            Rename { from: dir.path().join("xb"), to: dir.path().join("xa") },
+        ];
+        execute(&swap).unwrap();
+        assert_eq!(fs::read_to_string(dir.path().join("xa")).unwrap(), "B");
+        assert_eq!(fs::read_to_string(dir.path().join("xb")).unwrap(), "A");
+    }
+
+    #[test]
+    fn test_execute_rolls_back_on_failure() {
+        let dir = TempDir::new().unwrap();
+        for name in ["1.txt", "2.txt", "3.txt"] {
+            fs::write(dir.path().join(name), name).unwrap();
+        }
+
+        let rules = RenameRules { template: Some("file_{n}.txt".to_string()), ..Default::default() };
+        let renames = plan(dir.path(), &names(&["1.txt", "2.txt", "3.txt"]), &rules).unwrap();
+
+        // 第五次重命名（第二阶段的第二步）失败
+        let mut calls = 0;
+        let result = execute_with(&renames, |from, to| {
+            calls += 1;
+            if calls == 5 {
+                return Err(io::Error::other("injected failure"));
+            }
+            fs::rename(from, to)
+        });
+
+        assert!(result.is_err());
+        let mut left: Vec<String> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        left.sort();
+        assert_eq!(left, vec!["1.txt", "2.txt", "3.txt"]);
+        assert_eq!(fs::read_to_string(dir.path().join("2.txt")).unwrap(), "2.txt");
+    }
+}
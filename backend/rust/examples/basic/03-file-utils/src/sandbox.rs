@@ -0,0 +1,174 @@
+use std::fs;
+use std::io;
+use std::path::{self, Component, Path, PathBuf};
+
+use crate::error::{FileUtilsError, PathContext, Result};
+use crate::utils;
+
+/// 解析路径时最多跟随的符号链接层数，与 Linux 的 MAXSYMLINKS 相同
+const MAX_SYMLINKS: usize = 40;
+
+/// `--root` 模式：所有路径参数在解析符号链接后都必须位于根目录之内
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    root: PathBuf,
+}
+
+impl Sandbox {
+    pub fn new<P: AsRef<Path>>(root: P) -> Result<Self> {
+        let root = fs::canonicalize(root.as_ref()).with_path(root)?;
+
+        if !root.is_dir() {
+            return Err(FileUtilsError::NotADirectory { path: root });
+        }
+
+        Ok(Sandbox { root })
+    }
+
+    #[cfg(test)]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// 检查路径是否位于根目录之内，返回解析后的真实路径
+    ///
+    /// 路径不必存在：已存在的部分按真实的符号链接解析，其余部分按组件规范化。
+    pub fn check(&self, path: &Path) -> Result<PathBuf> {
+        let resolved = resolve(path).with_path(path)?;
+
+        match resolved.strip_prefix(&self.root) {
+            Ok(relative) if utils::is_safe_path(relative) => Ok(resolved),
+            _ => Err(FileUtilsError::OutsideRoot {
+                path: path.to_path_buf(),
+                root: self.root.clone(),
+            }),
+        }
+    }
+}
+
+/// 逐个组件解析路径，像内核一样先展开符号链接再处理其后的 ".."
+fn resolve(path: &Path) -> io::Result<PathBuf> {
+    let mut resolved = PathBuf::new();
+    let mut pending = components_reversed(&path::absolute(path)?);
+    let mut links = 0;
+
+    while let Some(part) = pending.pop() {
+        match part.components().next() {
+            Some(Component::Prefix(_)) | Some(Component::RootDir) => resolved.push(&part),
+            Some(Component::CurDir) | None => {},
+            Some(Component::ParentDir) => {
+                resolved.pop();
+            },
+            Some(Component::Normal(name)) => {
+                let candidate = resolved.join(name);
+
+                match fs::symlink_metadata(&candidate) {
+                    Ok(metadata) if metadata.file_type().is_symlink() => {
+                        links += 1;
+                        if links > MAX_SYMLINKS {
+                            return Err(io::Error::other(format!("符号链接层数过多: {}", path.display())));
+                        }
+
+                        let target = fs::read_link(&candidate)?;
+                        if target.is_absolute() {
+                            resolved = PathBuf::new();
+                        }
+                        pending.extend(components_reversed(&target));
+                    },
+                    _ => resolved = candidate,
+                }
+            },
+        }
+    }
+
+    Ok(resolved)
+}
+
+/// 按逆序返回路径的各个组件，方便用作栈
+fn components_reversed(path: &Path) -> Vec<PathBuf> {
+    path.components()
+        .rev()
+        .map(|component| PathBuf::from(component.as_os_str()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn setup() -> (TempDir, Sandbox) {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("jail/sub")).unwrap();
+        fs::create_dir_all(dir.path().join("outside")).unwrap();
+        fs::write(dir.path().join("outside/secret.txt"), "secret").unwrap();
+        let sandbox = Sandbox::new(dir.path().join("jail").to_str().unwrap()).unwrap();
+        (dir, sandbox)
+    }
+
+    fn is_escape(result: Result<PathBuf>) -> bool {
+        matches!(result, Err(FileUtilsError::OutsideRoot { .. }))
+    }
+
+    #[test]
+    fn test_paths_inside_root() {
+        let (_dir, sandbox) = setup();
+        let root = sandbox.root().to_path_buf();
+
+        assert_eq!(sandbox.check(&root).unwrap(), root);
+        assert!(sandbox.check(&root.join("sub/../a..b")).is_ok());
+        assert!(sandbox.check(&root.join("new/dir/file.txt")).is_ok());
+        assert!(sandbox.check(&root.join("./sub/./x")).is_ok());
+    }
+
+    #[test]
+    fn test_dotdot_escape() {
+        let (_dir, sandbox) = setup();
+        let root = sandbox.root().to_path_buf();
+
+        assert!(is_escape(sandbox.check(&root.join("..").join("outside"))));
+        assert!(is_escape(sandbox.check(&root.join("sub/../../outside/secret.txt"))));
+        assert!(is_escape(sandbox.check(&root.join("missing/../../x"))));
+        assert!(is_escape(sandbox.check(Path::new("/etc/passwd"))));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_escape() {
+        use std::os::unix::fs::symlink;
+        let (dir, sandbox) = setup();
+        let root = sandbox.root().to_path_buf();
+
+        // 绝对路径的链接指向根目录之外
+        symlink(dir.path().join("outside"), root.join("abs")).unwrap();
+        assert!(is_escape(sandbox.check(&root.join("abs"))));
+        assert!(is_escape(sandbox.check(&root.join("abs/secret.txt"))));
+        assert!(is_escape(sandbox.check(&root.join("abs/new.txt"))));
+
+        // 相对链接经由 .. 逃逸
+        symlink("../../outside", root.join("sub/rel")).unwrap();
+        assert!(is_escape(sandbox.check(&root.join("sub/rel/secret.txt"))));
+
+        // "link/.." 要先展开链接再回退，而不是按字符串抵消
+        symlink(dir.path().join("outside"), root.join("sub/deep")).unwrap();
+        assert!(is_escape(sandbox.check(&root.join("sub/deep/../outside/secret.txt"))));
+
+        // 链接在根目录之内是允许的
+        symlink("sub", root.join("inner")).unwrap();
+        assert_eq!(sandbox.check(&root.join("inner/x")).unwrap(), root.join("sub/x"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_loop() {
+        use std::os::unix::fs::symlink;
+        let (_dir, sandbox) = setup();
+        let root = sandbox.root().to_path_buf();
+
+        symlink("b", root.join("a")).unwrap();
+        symlink("a", root.join("b")).unwrap();
+
+        let error = sandbox.check(&root.join("a")).unwrap_err();
+        assert!(matches!(error, FileUtilsError::Io { .. }));
+    }
+}
@@ -0,0 +1,426 @@
+use rustyline::completion::{Completer, FilenameCompleter, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{CompletionType, Config, Context, Editor, Helper};
+use std::env;
+use std::path::{Path, PathBuf};
+
+use crate::error::{PathContext, EXIT_USAGE};
+use crate::interrupt;
+use crate::journal;
+
+/// shell 自己处理的命令，其余命令交给与命令行相同的分发
+const BUILTINS: [&str; 4] = ["cd", "pwd", "exit", "quit"];
+
+const HISTORY_SIZE: usize = 1000;
+
+/// 交互式 shell 的状态
+struct Shell {
+    program: String,
+    /// 启动 shell 时给出的全局选项，作为每条命令的默认选项
+    defaults: Vec<String>,
+    previous_dir: Option<PathBuf>,
+    status: i32,
+}
+
+impl Shell {
+    /// 执行一行输入，返回 false 表示退出 shell
+    fn execute(&mut self, line: &str) -> bool {
+        let home = home_dir();
+        let words = match split_line(line, home.as_deref()) {
+            Ok(words) => words,
+            Err(e) => {
+                eprintln!("错误: {}", e);
+                self.status = EXIT_USAGE;
+                return true;
+            },
+        };
+
+        let Some(command) = words.first() else {
+            return true;
+        };
+
+        match command.as_str() {
+            "exit" | "quit" => return false,
+            "pwd" => {
+                self.status = crate::handle_result(env::current_dir().with_path(".").map(|dir| println!("{}", dir.display())));
+            },
+            "cd" => {
+                self.status = self.cd(words.get(1).map(String::as_str), home.as_deref());
+            },
+            "shell" => {
+                eprintln!("已经在 shell 中");
+            },
+            name if !name.starts_with('-') && !crate::command_names().any(|command| command == name) => {
+                eprintln!("未知命令: {} (输入 help 查看可用命令)", name);
+                self.status = EXIT_USAGE;
+            },
+            _ => {
+                let mut args = vec![self.program.clone()];
+                args.extend(words.iter().cloned());
+                interrupt::take();
+                self.status = crate::run_with_defaults(args, self.defaults.clone());
+                // watch、view -f 被 Ctrl-C 打断后回到提示符
+                if interrupt::take() {
+                    println!();
+                    self.status = interrupt::EXIT_INTERRUPTED;
+                }
+
+                if matches!(command.as_str(), "help" | "-h" | "--help") {
+                    println!();
+                    println!("shell 内置命令: cd [目录|-|~]、pwd、exit");
+                }
+            },
+        }
+
+        true
+    }
+
+    /// 切换当前目录；不带参数或 `~` 回到主目录，`-` 回到上一个目录
+    fn cd(&mut self, target: Option<&str>, home: Option<&Path>) -> i32 {
+        let target = match target {
+            None => match home {
+                Some(home) => home.to_path_buf(),
+                None => {
+                    eprintln!("错误: 未设置 HOME");
+                    return EXIT_USAGE;
+                },
+            },
+            Some("-") => match &self.previous_dir {
+                Some(previous) => previous.clone(),
+                None => {
+                    eprintln!("错误: 没有上一个目录");
+                    return EXIT_USAGE;
+                },
+            },
+            Some(dir) => PathBuf::from(dir),
+        };
+
+        let current = env::current_dir().ok();
+        let status = crate::handle_result(env::set_current_dir(&target).with_path(&target));
+        if status == 0 {
+            self.previous_dir = current;
+        }
+        status
+    }
+}
+
+/// 启动交互式 shell，返回最后一条命令的退出码
+pub fn run(program: &str, defaults: Vec<String>) -> i32 {
+    let config = Config::builder()
+        .completion_type(CompletionType::List)
+        .max_history_size(HISTORY_SIZE)
+        .and_then(|builder| builder.history_ignore_dups(true))
+        .map(|builder| builder.build());
+
+    let editor = config.and_then(Editor::<ShellHelper, DefaultHistory>::with_config);
+    let mut editor = match editor {
+        Ok(editor) => editor,
+        Err(e) => {
+            eprintln!("❌ 错误: 无法启动 shell: {}", e);
+            return 1;
+        },
+    };
+    editor.set_helper(Some(ShellHelper::new()));
+
+    // 历史文件不存在或无法读取时从空历史开始
+    let history = journal::default_data_dir().join("shell_history");
+    let _ = editor.load_history(&history);
+
+    let mut shell = Shell {
+        program: program.to_string(),
+        defaults,
+        previous_dir: None,
+        status: 0,
+    };
+
+    // Ctrl-C 只结束正在运行的命令，不结束 shell；输入时 rustyline 自己处理 Ctrl-C
+    interrupt::catch();
+
+    println!("📁 file-utils shell，输入 help 查看命令，exit 或 Ctrl-D 退出");
+
+    loop {
+        match editor.readline(&prompt()) {
+            Ok(line) => {
+                if !line.trim().is_empty() {
+                    let _ = editor.add_history_entry(line.as_str());
+                }
+                if !shell.execute(&line) {
+                    break;
+                }
+            },
+            // Ctrl-C 只放弃当前输入
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => {
+                eprintln!("❌ 错误: {}", e);
+                shell.status = 1;
+                break;
+            },
+        }
+    }
+
+    if let Some(parent) = history.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    if let Err(e) = editor.save_history(&history) {
+        eprintln!("⚠️  无法保存历史记录 {}: {}", history.display(), e);
+    }
+
+    shell.status
+}
+
+/// 提示符中显示当前目录，主目录缩写为 ~
+fn prompt() -> String {
+    let cwd = env::current_dir().unwrap_or_default();
+    let shown = match home_dir().and_then(|home| cwd.strip_prefix(&home).ok().map(Path::to_path_buf)) {
+        Some(relative) if relative.as_os_str().is_empty() => "~".to_string(),
+        Some(relative) => format!("~/{}", relative.display()),
+        None => cwd.display().to_string(),
+    };
+    format!("file-utils {}> ", shown)
+}
+
+fn home_dir() -> Option<PathBuf> {
+    env::var_os("HOME").filter(|home| !home.is_empty()).map(PathBuf::from)
+}
+
+/// 按 shell 的规则拆分一行输入：空白分隔，支持单引号、双引号和反斜杠转义，
+/// 未加引号且以 `~` 开头的词展开为主目录
+fn split_line(line: &str, home: Option<&Path>) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                words.extend(word.take());
+            },
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err("缺少配对的单引号".to_string()),
+                    }
+                }
+            },
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        // 双引号中只有 \" 和 \\ 是转义，其余反斜杠原样保留（方便写正则）
+                        Some('\\') if matches!(chars.peek(), Some('"' | '\\')) => word.extend(chars.next()),
+                        Some(c) => word.push(c),
+                        None => return Err("缺少配对的双引号".to_string()),
+                    }
+                }
+            },
+            '\\' => {
+                let escaped = chars.next().ok_or("行尾不能是反斜杠")?;
+                word.get_or_insert_with(String::new).push(escaped);
+            },
+            '~' if word.is_none() && chars.peek().is_none_or(|c| *c == '/' || c.is_whitespace()) => {
+                let expanded = home.map(|home| home.display().to_string()).unwrap_or_else(|| "~".to_string());
+                word = Some(expanded);
+            },
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+
+    Ok(words)
+}
+
+/// 第一个词补全命令名，其余补全路径
+struct ShellHelper {
+    files: FilenameCompleter,
+    commands: Vec<&'static str>,
+}
+
+impl ShellHelper {
+    fn new() -> Self {
+        // shell 中不能再启动 shell，-h/--help 与 help 重复，都不参与补全
+        let mut commands: Vec<&'static str> = crate::command_names()
+            .filter(|name| !name.starts_with('-') && *name != "shell")
+            .chain(BUILTINS)
+            .collect();
+        commands.sort_unstable();
+        commands.dedup();
+        ShellHelper { files: FilenameCompleter::new(), commands }
+    }
+}
+
+impl Completer for ShellHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let before = &line[..pos];
+        let start = before.len() - before.trim_start().len();
+
+        if before[start..].contains(char::is_whitespace) {
+            return self.files.complete(line, pos, ctx);
+        }
+
+        let prefix = &before[start..];
+        let candidates = self
+            .commands
+            .iter()
+            .filter(|command| command.starts_with(prefix))
+            .map(|command| Pair {
+                display: command.to_string(),
+                replacement: format!("{} ", command),
+            })
+            .collect();
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for ShellHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ShellHelper {}
+
+impl Validator for ShellHelper {}
+
+impl Helper for ShellHelper {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::sync::{Mutex, MutexGuard};
+    use tempfile::TempDir;
+
+    fn new_shell(defaults: &[&str]) -> Shell {
+        Shell {
+            program: "file-utils".to_string(),
+            defaults: defaults.iter().map(|arg| arg.to_string()).collect(),
+            previous_dir: None,
+            status: 0,
+        }
+    }
+
+    fn split(line: &str) -> Vec<String> {
+        split_line(line, Some(Path::new("/home/me"))).unwrap()
+    }
+
+    #[test]
+    fn test_split_line() {
+        assert_eq!(split("  ls  -l   src "), ["ls", "-l", "src"]);
+        assert_eq!(split(r#"copy "my file.txt" 'backup dir/'"#), ["copy", "my file.txt", "backup dir/"]);
+        assert_eq!(split(r"view a\ b.txt"), ["view", "a b.txt"]);
+        assert_eq!(split(r#"grep "\d+ \"x\"" ."#), ["grep", r#"\d+ "x""#, "."]);
+        assert_eq!(split(r#"touch "" x"#), ["touch", "", "x"]);
+        assert_eq!(split("cd ~"), ["cd", "/home/me"]);
+        assert_eq!(split("ls ~/docs a~b '~/x'"), ["ls", "/home/me/docs", "a~b", "~/x"]);
+        assert!(split_line("view 'unterminated", None).is_err());
+        assert!(split_line("view \"unterminated", None).is_err());
+        assert!(split_line("view x\\", None).is_err());
+    }
+
+    /// 修改进程当前目录的测试互斥执行，结束时（包括断言失败时）恢复原来的目录
+    struct CurrentDirGuard {
+        original: PathBuf,
+        _lock: MutexGuard<'static, ()>,
+    }
+
+    static CURRENT_DIR_LOCK: Mutex<()> = Mutex::new(());
+
+    impl CurrentDirGuard {
+        fn new() -> Self {
+            let lock = CURRENT_DIR_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+            CurrentDirGuard { original: env::current_dir().unwrap(), _lock: lock }
+        }
+    }
+
+    impl Drop for CurrentDirGuard {
+        fn drop(&mut self) {
+            let _ = env::set_current_dir(&self.original);
+        }
+    }
+
+    #[test]
+    fn test_builtins() {
+        let dir = TempDir::new().unwrap();
+        // 在 dir 之前恢复目录，不会停留在已删除的临时目录中
+        let guard = CurrentDirGuard::new();
+        let target = dir.path().canonicalize().unwrap();
+        let mut shell = new_shell(&[]);
+
+        assert!(shell.execute(&format!("cd '{}'", target.display())));
+        assert_eq!(shell.status, 0);
+        assert_eq!(env::current_dir().unwrap(), target);
+        assert!(shell.execute("pwd"));
+        assert_eq!(shell.status, 0);
+
+        // 目录不存在时留在原处，上一个目录也不变
+        assert!(shell.execute("cd missing"));
+        assert_eq!(shell.status, 3);
+        assert_eq!(env::current_dir().unwrap(), target);
+
+        assert!(shell.execute("cd -"));
+        assert_eq!(env::current_dir().unwrap(), guard.original);
+        assert_eq!(shell.cd(None, Some(&target)), 0);
+        assert_eq!(env::current_dir().unwrap(), target);
+        assert_eq!(shell.cd(None, None), EXIT_USAGE);
+        assert_eq!(new_shell(&[]).cd(Some("-"), None), EXIT_USAGE);
+
+        assert!(shell.execute("  "));
+        assert!(shell.execute("bogus"));
+        assert_eq!(shell.status, EXIT_USAGE);
+        assert!(shell.execute("shell"));
+        assert!(!shell.execute("exit"));
+        assert!(!shell.execute("quit"));
+    }
+
+    #[test]
+    fn test_defaults_apply_to_every_command() {
+        let dir = TempDir::new().unwrap();
+        let inside = dir.path().join("inside");
+        let outside = dir.path().join("outside");
+        fs::create_dir_all(&inside).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        fs::write(outside.join("old.log"), "x").unwrap();
+        let old_log = outside.join("old.log");
+
+        // 启动 shell 时给出的 --root 和 --dry-run 作用于其中的命令
+        let mut sandboxed = new_shell(&["--root", inside.to_str().unwrap(), "--dry-run"]);
+        sandboxed.execute(&format!("delete '{}'", old_log.display()));
+        assert_eq!(sandboxed.status, 8);
+
+        // 命令中给出的同名选项优先于启动时的选项
+        sandboxed.execute(&format!("delete '{}' --root '{}'", old_log.display(), outside.display()));
+        assert_eq!(sandboxed.status, 0);
+        assert!(old_log.exists());
+
+        // 覆盖带值的选项时，启动时的值不会留下来被当作路径
+        let mut json = new_shell(&["--format", "json"]);
+        json.execute(&format!("ls '{}' --format csv", outside.display()));
+        assert_eq!(json.status, 0);
+        json.execute(&format!("grep x '{}' --format csv", outside.display()));
+        assert_eq!(json.status, 0);
+        json.execute(&format!("ls '{}'", outside.display()));
+        assert_eq!(json.status, 0);
+        json.execute(&format!("ls '{}' --format xml", outside.display()));
+        assert_eq!(json.status, EXIT_USAGE);
+
+        // 命令选项只由使用它的命令取出，其他命令忽略它而不是把它当作路径
+        let mut options = new_shell(&["--delete", "--follow", "--depth", "1"]);
+        options.execute(&format!("size '{}'", outside.display()));
+        assert_eq!(options.status, 0);
+        options.execute(&format!("ls '{}' --tree", outside.display()));
+        assert_eq!(options.status, 0);
+        options.execute(&format!("view '{}'", old_log.display()));
+        assert_eq!(options.status, 0);
+        options.execute(&format!("size '{}' --no-follow", outside.display()));
+        assert_eq!(options.status, 0);
+    }
+}
@@ -0,0 +1,270 @@
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::checksum;
+use crate::error::{FileUtilsError, PathContext, Result};
+use crate::records::to_timestamp;
+
+/// sync 判断文件是否需要传输的方式
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum CompareMode {
+    /// 大小或修改时间不同就传输
+    #[default]
+    SizeMtime,
+    /// 比较文件内容，忽略修改时间
+    Checksum,
+}
+
+/// sync 的选项，对应 `--checksum`、`--delete`、`--include`、`--exclude`
+#[derive(Debug, Clone, Default)]
+pub struct SyncOptions {
+    pub compare: CompareMode,
+    /// 删除目标目录中源目录没有的文件（移入回收站）
+    pub delete: bool,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+/// 由 include/exclude 模式组成的过滤器
+///
+/// 模式同时匹配相对路径和文件名，因此 "*.log" 和 "target" 在任意层级都生效。
+/// 被排除的目录不会进入；include 只作用于文件，为空时包含全部文件。
+pub struct Filter {
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+}
+
+impl Filter {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        Ok(Filter {
+            include: if include.is_empty() { None } else { Some(build_globs(include)?) },
+            exclude: build_globs(exclude)?,
+        })
+    }
+
+    pub fn excluded(&self, relative: &Path) -> bool {
+        matches(&self.exclude, relative)
+    }
+
+    pub fn included(&self, relative: &Path) -> bool {
+        self.include.as_ref().is_none_or(|include| matches(include, relative))
+    }
+}
+
+/// 一次需要传输的文件
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transfer {
+    pub relative: PathBuf,
+    pub size: u64,
+    /// 目标中已有旧版本
+    pub update: bool,
+}
+
+/// 同步计划，路径均相对于各自的根目录
+#[derive(Debug, Default)]
+pub struct SyncPlan {
+    pub transfers: Vec<Transfer>,
+    /// 目标中多余的文件或目录，只在 `--delete` 时计算
+    pub extraneous: Vec<PathBuf>,
+    pub unchanged: usize,
+    /// 源目录中被跳过的符号链接和特殊文件
+    pub skipped: Vec<PathBuf>,
+}
+
+impl SyncPlan {
+    pub fn transfer_bytes(&self) -> u64 {
+        self.transfers.iter().map(|t| t.size).sum()
+    }
+}
+
+/// 比较源目录和目标目录，得出需要传输和删除的文件；目标目录可以不存在
+pub fn plan(source: &Path, dest: &Path, options: &SyncOptions) -> Result<SyncPlan> {
+    let filter = Filter::new(&options.include, &options.exclude)?;
+    let mut plan = SyncPlan::default();
+
+    let mut files = BTreeMap::new();
+    walk_source(source, Path::new(""), &filter, &mut files, &mut plan.skipped)?;
+
+    for (relative, metadata) in files {
+        let target = dest.join(&relative);
+        let needs_transfer = match fs::symlink_metadata(&target) {
+            Err(_) => Some(false),
+            Ok(existing) => {
+                let changed = match options.compare {
+                    CompareMode::SizeMtime => {
+                        existing.len() != metadata.len() || mtime_of(&existing) != mtime_of(&metadata)
+                    },
+                    CompareMode::Checksum => {
+                        !existing.is_file() || !checksum::same_content(&source.join(&relative), &target)?
+                    },
+                };
+                changed.then_some(true)
+            },
+        };
+
+        match needs_transfer {
+            Some(update) => plan.transfers.push(Transfer { relative, size: metadata.len(), update }),
+            None => plan.unchanged += 1,
+        }
+    }
+
+    if options.delete && dest.is_dir() {
+        find_extraneous(source, dest, Path::new(""), &filter, &mut plan.extraneous)?;
+    }
+
+    Ok(plan)
+}
+
+fn walk_source(
+    root: &Path,
+    relative: &Path,
+    filter: &Filter,
+    files: &mut BTreeMap<PathBuf, fs::Metadata>,
+    skipped: &mut Vec<PathBuf>,
+) -> Result<()> {
+    let dir = root.join(relative);
+
+    for entry in fs::read_dir(&dir).with_path(&dir)? {
+        let entry = entry.with_path(&dir)?;
+        let child = relative.join(entry.file_name());
+        if filter.excluded(&child) {
+            continue;
+        }
+
+        let metadata = fs::symlink_metadata(entry.path()).with_path(entry.path())?;
+        if metadata.is_dir() {
+            walk_source(root, &child, filter, files, skipped)?;
+        } else if !metadata.is_file() {
+            // 符号链接不跟随，避免同步到源目录之外的内容
+            skipped.push(child);
+        } else if filter.included(&child) {
+            files.insert(child, metadata);
+        }
+    }
+
+    Ok(())
+}
+
+/// 目标中源目录没有的条目；整个多余的目录只记录一次。被过滤掉的文件不会被删除
+fn find_extraneous(source: &Path, dest: &Path, relative: &Path, filter: &Filter, extraneous: &mut Vec<PathBuf>) -> Result<()> {
+    let dir = dest.join(relative);
+    let mut entries: Vec<_> = fs::read_dir(&dir)
+        .with_path(&dir)?
+        .map(|entry| entry.map(|e| e.file_name()))
+        .collect::<std::io::Result<_>>()
+        .with_path(&dir)?;
+    entries.sort();
+
+    for name in entries {
+        let child = relative.join(name);
+        if filter.excluded(&child) {
+            continue;
+        }
+
+        let target = dest.join(&child);
+        let is_dir = fs::symlink_metadata(&target).with_path(&target)?.is_dir();
+        let in_source = fs::symlink_metadata(source.join(&child)).ok();
+
+        match in_source {
+            Some(metadata) if is_dir && metadata.is_dir() => {
+                find_extraneous(source, dest, &child, filter, extraneous)?;
+            },
+            Some(metadata) if !is_dir && !metadata.is_dir() => {},
+            _ if !is_dir && !filter.included(&child) => {},
+            _ => extraneous.push(child),
+        }
+    }
+
+    Ok(())
+}
+
+fn mtime_of(metadata: &fs::Metadata) -> Option<i64> {
+    metadata.modified().ok().map(to_timestamp)
+}
+
+fn build_globs(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern)
+            .map_err(|e| FileUtilsError::InvalidInput(format!("无效的模式 {}: {}", pattern, e)))?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|e| FileUtilsError::InvalidInput(format!("无效的模式: {}", e)))
+}
+
+fn matches(set: &GlobSet, relative: &Path) -> bool {
+    set.is_match(relative) || relative.file_name().is_some_and(|name| set.is_match(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+    use tempfile::TempDir;
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+        let file = fs::File::open(path).unwrap();
+        file.set_modified(UNIX_EPOCH + Duration::from_secs(1_600_000_000)).unwrap();
+    }
+
+    fn relative_paths(transfers: &[Transfer]) -> Vec<&str> {
+        transfers.iter().map(|t| t.relative.to_str().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_plan_by_size_and_mtime() {
+        let dir = TempDir::new().unwrap();
+        let (src, dst) = (dir.path().join("src"), dir.path().join("dst"));
+        write(&src.join("same.txt"), "same");
+        write(&dst.join("same.txt"), "same");
+        write(&src.join("changed.txt"), "new content");
+        write(&dst.join("changed.txt"), "old");
+        write(&src.join("sub/new.txt"), "new");
+        write(&dst.join("old/stale.txt"), "stale");
+        write(&dst.join("stale.txt"), "stale");
+
+        let result = plan(&src, &dst, &SyncOptions { delete: true, ..Default::default() }).unwrap();
+        assert_eq!(relative_paths(&result.transfers), ["changed.txt", "sub/new.txt"]);
+        assert!(result.transfers[0].update && !result.transfers[1].update);
+        assert_eq!(result.transfer_bytes(), 14);
+        assert_eq!(result.unchanged, 1);
+        assert_eq!(result.extraneous, [PathBuf::from("old"), PathBuf::from("stale.txt")]);
+
+        // 大小和时间相同但内容不同时只有 --checksum 能发现
+        write(&dst.join("same.txt"), "SAME");
+        let quick = plan(&src, &dst, &SyncOptions::default()).unwrap();
+        assert_eq!(quick.unchanged, 1);
+        let thorough = plan(&src, &dst, &SyncOptions { compare: CompareMode::Checksum, ..Default::default() }).unwrap();
+        assert!(relative_paths(&thorough.transfers).contains(&"same.txt"));
+    }
+
+    #[test]
+    fn test_include_exclude() {
+        let dir = TempDir::new().unwrap();
+        let (src, dst) = (dir.path().join("src"), dir.path().join("dst"));
+        write(&src.join("a.rs"), "a");
+        write(&src.join("notes.md"), "n");
+        write(&src.join("target/debug/b.rs"), "b");
+        write(&dst.join("target/keep.rs"), "k");
+        write(&dst.join("extra.md"), "x");
+
+        let options = SyncOptions {
+            delete: true,
+            include: vec!["*.rs".to_string()],
+            exclude: vec!["target".to_string()],
+            ..Default::default()
+        };
+        let result = plan(&src, &dst, &options).unwrap();
+        assert_eq!(relative_paths(&result.transfers), ["a.rs"]);
+        // 被排除的 target 和不在 include 中的 extra.md 都不会被删除
+        assert!(result.extraneous.is_empty());
+
+        assert!(matches!(Filter::new(&["a[".to_string()], &[]), Err(FileUtilsError::InvalidInput(_))));
+    }
+}
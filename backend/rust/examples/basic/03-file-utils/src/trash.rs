@@ -0,0 +1,386 @@
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{self, Path, PathBuf};
+
+use crate::error::{FileUtilsError, PathContext, Result};
+use crate::utils;
+
+/// 回收站中的一个条目
+#[derive(Debug, Clone)]
+pub struct TrashItem {
+    /// 在 files/ 目录中的名字
+    pub name: String,
+    /// 删除前的绝对路径
+    pub original_path: PathBuf,
+    /// 删除时间（Unix 时间戳）
+    pub deleted_at: i64,
+}
+
+/// 遵循 freedesktop.org 回收站规范的主目录回收站
+///
+/// 被删除的文件移动到 `files/`，同名的 `info/<name>.trashinfo` 记录原路径和删除时间，
+/// 与桌面环境的文件管理器共用同一个回收站。
+pub struct Trash {
+    root: PathBuf,
+}
+
+impl Trash {
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Trash {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    /// 用户主目录回收站：`$XDG_DATA_HOME/Trash`，默认 `~/.local/share/Trash`
+    pub fn open_default() -> Self {
+        let data_home = env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| {
+                let home = env::var_os("HOME").map(PathBuf::from).unwrap_or_else(|| PathBuf::from("."));
+                home.join(".local").join("share")
+            });
+        Trash::new(data_home.join("Trash"))
+    }
+
+    fn files_dir(&self) -> PathBuf {
+        self.root.join("files")
+    }
+
+    fn info_dir(&self) -> PathBuf {
+        self.root.join("info")
+    }
+
+    fn info_path(&self, name: &str) -> PathBuf {
+        self.info_dir().join(format!("{}.trashinfo", name))
+    }
+
+    /// 把文件或目录移入回收站，返回它在 files/ 中的路径
+    pub fn put(&self, path: &Path) -> Result<PathBuf> {
+        let original = path::absolute(path).with_path(path)?;
+        let base_name = original
+            .file_name()
+            .ok_or_else(|| FileUtilsError::InvalidInput(format!("无法移入回收站: {}", path.display())))?
+            .to_string_lossy()
+            .to_string();
+
+        fs::create_dir_all(self.files_dir()).with_path(self.files_dir())?;
+        fs::create_dir_all(self.info_dir()).with_path(self.info_dir())?;
+
+        // 先以独占方式创建 .trashinfo 占住名字，再移动文件
+        let mut counter = 1;
+        let (name, mut info_file) = loop {
+            let candidate = if counter == 1 {
+                base_name.clone()
+            } else {
+                format!("{}.{}", base_name, counter)
+            };
+            counter += 1;
+
+            if self.files_dir().join(&candidate).symlink_metadata().is_ok() {
+                continue;
+            }
+
+            match fs::OpenOptions::new().write(true).create_new(true).open(self.info_path(&candidate)) {
+                Ok(file) => break (candidate, file),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(FileUtilsError::from_io(e, self.info_path(&candidate))),
+            }
+        };
+
+        write!(
+            info_file,
+            "[Trash Info]\nPath={}\nDeletionDate={}\n",
+            encode_path(&original),
+            format_deletion_date(utils::current_timestamp())
+        )
+        .with_path(self.info_path(&name))?;
+        drop(info_file);
+
+        let trashed = self.files_dir().join(&name);
+        if let Err(e) = utils::move_path(path, &trashed) {
+            fs::remove_file(self.info_path(&name)).ok();
+            return Err(e);
+        }
+
+        Ok(trashed)
+    }
+
+    /// 列出回收站中的所有条目（最近删除的在前）
+    pub fn list(&self) -> Result<Vec<TrashItem>> {
+        let info_dir = self.info_dir();
+        if !info_dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut items = Vec::new();
+        for entry in fs::read_dir(&info_dir).with_path(&info_dir)? {
+            let path = entry.with_path(&info_dir)?.path();
+            let Some(name) = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .and_then(|n| n.strip_suffix(".trashinfo"))
+            else {
+                continue;
+            };
+
+            // 跳过损坏的 .trashinfo 文件
+            if let Ok(item) = self.read_info(name) {
+                items.push(item);
+            }
+        }
+
+        items.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at).then_with(|| a.name.cmp(&b.name)));
+        Ok(items)
+    }
+
+    /// 读取一个条目的 .trashinfo
+    pub fn read_info(&self, name: &str) -> Result<TrashItem> {
+        let info_path = self.info_path(name);
+        let content = fs::read_to_string(&info_path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => FileUtilsError::NotInTrash { name: name.to_string() },
+            _ => FileUtilsError::from_io(e, &info_path),
+        })?;
+        let missing = |key: &str| FileUtilsError::Corrupt {
+            path: info_path.clone(),
+            message: format!("缺少 {}", key),
+        };
+
+        let mut original_path = None;
+        let mut deleted_at = None;
+
+        for line in content.lines() {
+            if let Some(value) = line.strip_prefix("Path=") {
+                original_path = Some(decode_path(value));
+            } else if let Some(value) = line.strip_prefix("DeletionDate=") {
+                deleted_at = parse_deletion_date(value);
+            }
+        }
+
+        Ok(TrashItem {
+            name: name.to_string(),
+            original_path: original_path.ok_or_else(|| missing("Path"))?,
+            deleted_at: deleted_at.ok_or_else(|| missing("DeletionDate"))?,
+        })
+    }
+
+    /// 把条目恢复到原来的位置
+    pub fn restore(&self, name: &str) -> Result<PathBuf> {
+        let item = self.read_info(name)?;
+        self.restore_to(name, &item.original_path)?;
+        Ok(item.original_path)
+    }
+
+    /// 把条目移动到指定位置并删除它的 .trashinfo
+    pub fn restore_to(&self, name: &str, destination: &Path) -> Result<()> {
+        let trashed = self.files_dir().join(name);
+        if trashed.symlink_metadata().is_err() {
+            return Err(FileUtilsError::NotInTrash { name: name.to_string() });
+        }
+
+        // 不覆盖原位置上已有的文件
+        if destination.symlink_metadata().is_ok() {
+            return Err(FileUtilsError::AlreadyExists { path: destination.to_path_buf() });
+        }
+
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent).with_path(parent)?;
+        }
+
+        utils::move_path(&trashed, destination)?;
+        fs::remove_file(self.info_path(name)).ok();
+
+        Ok(())
+    }
+
+    /// 回收站中 files/ 下路径对应的条目名
+    pub fn name_of(&self, trashed: &Path) -> Option<String> {
+        if trashed.parent()? != self.files_dir() {
+            return None;
+        }
+        trashed.file_name().map(|n| n.to_string_lossy().to_string())
+    }
+
+    /// 永久删除条目；older_than 为 Some 时只删除早于该秒数的条目
+    pub fn empty(&self, older_than: Option<u64>) -> Result<Vec<TrashItem>> {
+        let now = utils::current_timestamp();
+        let mut removed = Vec::new();
+
+        for item in self.list()? {
+            if let Some(age) = older_than {
+                if now - item.deleted_at < age as i64 {
+                    continue;
+                }
+            }
+
+            self.remove(&item.name)?;
+            removed.push(item);
+        }
+
+        Ok(removed)
+    }
+
+    /// 永久删除单个条目
+    fn remove(&self, name: &str) -> Result<()> {
+        let trashed = self.files_dir().join(name);
+        match trashed.symlink_metadata() {
+            Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(&trashed).with_path(&trashed)?,
+            Ok(_) => fs::remove_file(&trashed).with_path(&trashed)?,
+            Err(_) => {},
+        }
+        fs::remove_file(self.info_path(name)).with_path(self.info_path(name))?;
+        Ok(())
+    }
+}
+
+/// DeletionDate 使用本地时间的 `YYYY-MM-DDThh:mm:ss` 格式
+fn format_deletion_date(timestamp: i64) -> String {
+    utils::strftime("%Y-%m-%dT%H:%M:%S", timestamp, utils::local_offset(timestamp))
+}
+
+fn parse_deletion_date(text: &str) -> Option<i64> {
+    let (date, time) = text.trim().split_once('T')?;
+
+    let mut date_parts = date.splitn(3, '-');
+    let year: i64 = date_parts.next()?.parse().ok()?;
+    let month: u32 = date_parts.next()?.parse().ok()?;
+    let day: u32 = date_parts.next()?.parse().ok()?;
+
+    let mut time_parts = time.splitn(3, ':');
+    let hour: i64 = time_parts.next()?.parse().ok()?;
+    let minute: i64 = time_parts.next()?.parse().ok()?;
+    let second: i64 = time_parts.next()?.get(..2)?.parse().ok()?;
+
+    if day == 0 || day > utils::days_in_month(year, month) {
+        return None;
+    }
+
+    let local = utils::days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second;
+    Some(local - utils::local_offset(local))
+}
+
+/// 按 URL 规则转义路径中的特殊字节
+fn encode_path(path: &Path) -> String {
+    let mut encoded = String::new();
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            },
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn decode_path(text: &str) -> PathBuf {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let high = (bytes[i + 1] as char).to_digit(16);
+            let low = (bytes[i + 2] as char).to_digit(16);
+            if let (Some(high), Some(low)) = (high, low) {
+                decoded.push((high * 16 + low) as u8);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    PathBuf::from(String::from_utf8_lossy(&decoded).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_put_and_restore() {
+        let dir = TempDir::new().unwrap();
+        let trash = Trash::new(dir.path().join("Trash"));
+        let file = dir.path().join("my file.txt");
+        fs::write(&file, "hello").unwrap();
+
+        let trashed = trash.put(&file).unwrap();
+        assert!(!file.exists());
+        assert!(trashed.exists());
+
+        let info = fs::read_to_string(trash.info_path("my file.txt")).unwrap();
+        assert!(info.starts_with("[Trash Info]\n"));
+        assert!(info.contains("my%20file.txt"));
+
+        let items = trash.list().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].original_path, file);
+
+        let restored = trash.restore("my file.txt").unwrap();
+        assert_eq!(restored, file);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "hello");
+        assert!(trash.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_name_collision() {
+        let dir = TempDir::new().unwrap();
+        let trash = Trash::new(dir.path().join("Trash"));
+        let file = dir.path().join("a.txt");
+
+        fs::write(&file, "1").unwrap();
+        let first = trash.put(&file).unwrap();
+        fs::write(&file, "2").unwrap();
+        let second = trash.put(&file).unwrap();
+
+        assert_ne!(first, second);
+        assert_eq!(trash.name_of(&second).as_deref(), Some("a.txt.2"));
+        assert_eq!(trash.list().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_empty_older_than() {
+        let dir = TempDir::new().unwrap();
+        let trash = Trash::new(dir.path().join("Trash"));
+        let file = dir.path().join("a.txt");
+        fs::write(&file, "1").unwrap();
+        trash.put(&file).unwrap();
+
+        // 手工写一个很早以前删除的条目
+        fs::write(trash.files_dir().join("old.txt"), "old").unwrap();
+        fs::write(
+            trash.info_path("old.txt"),
+            "[Trash Info]\nPath=/tmp/old.txt\nDeletionDate=2001-02-03T04:05:06\n",
+        ).unwrap();
+
+        let removed = trash.empty(Some(30 * 86_400)).unwrap();
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].name, "old.txt");
+        assert!(!trash.files_dir().join("old.txt").exists());
+        assert_eq!(trash.list().unwrap().len(), 1);
+
+        trash.empty(None).unwrap();
+        assert!(trash.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_deletion_date_round_trip() {
+        // DeletionDate 是本地时间
+        let local = 981_173_106;
+        assert_eq!(parse_deletion_date("2001-02-03T04:05:06"), Some(local - utils::local_offset(local)));
+        assert_eq!(parse_deletion_date("2001-02-30T04:05:06"), None);
+        assert_eq!(parse_deletion_date(&format_deletion_date(1_700_000_000)), Some(1_700_000_000));
+        assert_eq!(parse_deletion_date("garbage"), None);
+    }
+
+    #[test]
+    fn test_path_encoding() {
+        let path = Path::new("/home/user/报告 1.txt");
+        let encoded = encode_path(path);
+        assert!(!encoded.contains(' '));
+        assert_eq!(decode_path(&encoded), path);
+    }
+}
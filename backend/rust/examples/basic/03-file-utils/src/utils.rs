@@ -1,5 +1,10 @@
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use crate::error::{PathContext, Result};
+
 /// 格式化文件大小为人类可读的形式
 pub fn format_size(size: u64) -> String {
     const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
@@ -26,35 +31,65 @@ pub fn format_size(size: u64) -> String {
     }
 }
 
-/// 格式化系统时间为可读字符串
-pub fn format_time(time: SystemTime) -> String {
-    match time.duration_since(UNIX_EPOCH) {
-        Ok(duration) => {
-            let timestamp = duration.as_secs();
-            
-            // 简单的时间格式化
-            // 在实际应用中，建议使用 chrono 库进行更精确的时间处理
-            let datetime = chrono_format(timestamp);
-            datetime
-        },
-        Err(_) => "时间格式错误".to_string(),
+/// 时间显示风格，对应 `--time-style`
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum TimeStyle {
+    /// "3 分钟前"，超过一周显示本地日期
+    #[default]
+    Relative,
+    /// 本地时间 "2024-03-15 14:30"
+    Iso,
+    /// ISO-8601 完整格式 "2024-03-15T14:30:05+08:00"
+    FullIso,
+    /// 自定义格式，类似 strftime，如 "+%Y/%m/%d %H:%M"
+    Custom(String),
+}
+
+impl FromStr for TimeStyle {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "relative" => Ok(TimeStyle::Relative),
+            "iso" => Ok(TimeStyle::Iso),
+            "full-iso" => Ok(TimeStyle::FullIso),
+            other => match other.strip_prefix('+') {
+                Some(format) => Ok(TimeStyle::Custom(format.to_string())),
+                None => Err(format!("无效的时间风格: {} (可选: relative, iso, full-iso, +格式)", other)),
+            },
+        }
     }
 }
 
-/// 简单的时间格式化函数
-fn chrono_format(timestamp: u64) -> String {
-    // 这是一个简化的实现
-    // 实际应用中应该使用 chrono 库
+/// 按指定风格格式化系统时间（使用本地时区）
+pub fn format_time(time: SystemTime, style: &TimeStyle) -> String {
+    let timestamp = match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs() as i64),
+    };
     
-    const SECONDS_PER_MINUTE: u64 = 60;
-    const SECONDS_PER_HOUR: u64 = 3600;
-    const SECONDS_PER_DAY: u64 = 86400;
+    if *style == TimeStyle::Relative {
+        return format_relative(timestamp, current_timestamp());
+    }
     
-    // 获取当前时间戳
-    let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
+    format_timestamp(timestamp, local_offset(timestamp), style)
+}
+
+/// 按指定风格格式化时间戳，offset 为相对 UTC 的秒数
+pub fn format_timestamp(timestamp: i64, offset: i64, style: &TimeStyle) -> String {
+    match style {
+        TimeStyle::Relative => format_relative(timestamp, current_timestamp()),
+        TimeStyle::Iso => strftime("%Y-%m-%d %H:%M", timestamp, offset),
+        TimeStyle::FullIso => strftime("%Y-%m-%dT%H:%M:%S%:z", timestamp, offset),
+        TimeStyle::Custom(format) => strftime(format, timestamp, offset),
+    }
+}
+
+/// 相对时间，如 "3 分钟前"
+fn format_relative(timestamp: i64, now: i64) -> String {
+    const SECONDS_PER_MINUTE: i64 = 60;
+    const SECONDS_PER_HOUR: i64 = 3600;
+    const SECONDS_PER_DAY: i64 = 86400;
     
     if timestamp > now {
         return "未来时间".to_string();
@@ -63,54 +98,318 @@ fn chrono_format(timestamp: u64) -> String {
     let diff = now - timestamp;
     
     if diff < SECONDS_PER_MINUTE {
-        return format!("{} 秒前", diff);
+        format!("{} 秒前", diff)
     } else if diff < SECONDS_PER_HOUR {
-        return format!("{} 分钟前", diff / SECONDS_PER_MINUTE);
+        format!("{} 分钟前", diff / SECONDS_PER_MINUTE)
     } else if diff < SECONDS_PER_DAY {
-        return format!("{} 小时前", diff / SECONDS_PER_HOUR);
+        format!("{} 小时前", diff / SECONDS_PER_HOUR)
     } else if diff < SECONDS_PER_DAY * 7 {
-        return format!("{} 天前", diff / SECONDS_PER_DAY);
+        format!("{} 天前", diff / SECONDS_PER_DAY)
     } else {
-        // 对于更久的时间，显示简化的日期
-        return format_date_simple(timestamp);
+        // 对于更久的时间，显示本地日期
+        strftime("%Y-%m-%d", timestamp, local_offset(timestamp))
     }
 }
 
-/// 简化的日期格式化
-fn format_date_simple(timestamp: u64) -> String {
-    // Unix 时间戳转换的简化实现
-    // 1970年1月1日开始的秒数
-    
-    const SECONDS_PER_DAY: u64 = 86400;
-    const DAYS_PER_YEAR: u64 = 365;
-    const DAYS_PER_LEAP_YEAR: u64 = 366;
+/// 类似 strftime 的格式化
+///
+/// 支持 %Y %y %m %d %e %H %I %p %M %S %j %a %A %b %B %F %T %R %z %:z %s %n %t %%，
+/// 未知的转换说明原样输出。
+pub fn strftime(format: &str, timestamp: i64, offset: i64) -> String {
+    const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+    const WEEKDAYS_FULL: [&str; 7] = ["Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday"];
+    const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+    const MONTHS_FULL: [&str; 12] = [
+        "January", "February", "March", "April", "May", "June",
+        "July", "August", "September", "October", "November", "December",
+    ];
     
-    let days_since_epoch = timestamp / SECONDS_PER_DAY;
+    let local = timestamp + offset;
+    let days = local.div_euclid(86_400);
+    let seconds = local.rem_euclid(86_400);
+    let (year, month, day) = civil_from_days(days);
+    let (hour, minute, second) = (seconds / 3600, seconds % 3600 / 60, seconds % 60);
+    // 1970-01-01 是星期四
+    let weekday = (days + 4).rem_euclid(7) as usize;
+    let day_of_year = days - days_from_civil(year, 1, 1) + 1;
+    let hour12 = if hour % 12 == 0 { 12 } else { hour % 12 };
     
-    // 简化的年份计算
-    let mut year = 1970;
-    let mut remaining_days = days_since_epoch;
+    let mut result = String::new();
+    let mut chars = format.chars();
     
-    // 粗略估算年份
-    while remaining_days >= DAYS_PER_YEAR {
-        if is_leap_year(year) && remaining_days >= DAYS_PER_LEAP_YEAR {
-            remaining_days -= DAYS_PER_LEAP_YEAR;
-        } else {
-            remaining_days -= DAYS_PER_YEAR;
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            result.push(c);
+            continue;
+        }
+        
+        match chars.next() {
+            Some('Y') => result.push_str(&format!("{:04}", year)),
+            Some('y') => result.push_str(&format!("{:02}", year.rem_euclid(100))),
+            Some('m') => result.push_str(&format!("{:02}", month)),
+            Some('d') => result.push_str(&format!("{:02}", day)),
+            Some('e') => result.push_str(&format!("{:2}", day)),
+            Some('H') => result.push_str(&format!("{:02}", hour)),
+            Some('I') => result.push_str(&format!("{:02}", hour12)),
+            Some('p') => result.push_str(if hour < 12 { "AM" } else { "PM" }),
+            Some('M') => result.push_str(&format!("{:02}", minute)),
+            Some('S') => result.push_str(&format!("{:02}", second)),
+            Some('j') => result.push_str(&format!("{:03}", day_of_year)),
+            Some('a') => result.push_str(WEEKDAYS[weekday]),
+            Some('A') => result.push_str(WEEKDAYS_FULL[weekday]),
+            Some('b') => result.push_str(MONTHS[month as usize - 1]),
+            Some('B') => result.push_str(MONTHS_FULL[month as usize - 1]),
+            Some('F') => result.push_str(&format!("{:04}-{:02}-{:02}", year, month, day)),
+            Some('T') => result.push_str(&format!("{:02}:{:02}:{:02}", hour, minute, second)),
+            Some('R') => result.push_str(&format!("{:02}:{:02}", hour, minute)),
+            Some('z') => result.push_str(&format_offset(offset, false)),
+            Some(':') => {
+                let mut lookahead = chars.clone();
+                if lookahead.next() == Some('z') {
+                    chars = lookahead;
+                    result.push_str(&format_offset(offset, true));
+                } else {
+                    result.push_str("%:");
+                }
+            },
+            Some('s') => result.push_str(&timestamp.to_string()),
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('%') => result.push('%'),
+            Some(other) => {
+                result.push('%');
+                result.push(other);
+            },
+            None => result.push('%'),
         }
-        year += 1;
     }
     
-    // 简化的月日计算
-    let month = (remaining_days / 30) + 1; // 粗略估算
-    let day = (remaining_days % 30) + 1;
+    result
+}
+
+/// 时区偏移，如 "+0800" 或 "+08:00"
+fn format_offset(offset: i64, with_colon: bool) -> String {
+    let sign = if offset < 0 { '-' } else { '+' };
+    let minutes = offset.abs() / 60;
+    if with_colon {
+        format!("{}{:02}:{:02}", sign, minutes / 60, minutes % 60)
+    } else {
+        format!("{}{:02}{:02}", sign, minutes / 60, minutes % 60)
+    }
+}
+
+/// 指定时刻本地时区相对 UTC 的偏移秒数（考虑夏令时）
+#[cfg(unix)]
+pub fn local_offset(timestamp: i64) -> i64 {
+    let time = timestamp as libc::time_t;
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
     
-    format!("{:04}-{:02}-{:02}", year, month.min(12), day.min(31))
+    // SAFETY: localtime_r 只写入我们提供的 tm 结构
+    let result = unsafe { libc::localtime_r(&time, &mut tm) };
+    if result.is_null() {
+        0
+    } else {
+        tm.tm_gmtoff as i64
+    }
+}
+
+/// 非 Unix 平台按 UTC 处理
+#[cfg(not(unix))]
+pub fn local_offset(_timestamp: i64) -> i64 {
+    0
+}
+
+/// 当前 Unix 时间戳
+pub fn current_timestamp() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
 }
 
 /// 检查是否为闰年
 fn is_leap_year(year: u64) -> bool {
-    (year % 4 == 0 && year % 100 != 0) || (year % 400 == 0)
+    (year.is_multiple_of(4) && !year.is_multiple_of(100)) || year.is_multiple_of(400)
+}
+
+/// 某年某月的天数
+pub fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap_year(year.rem_euclid(400) as u64) => 29,
+        2 => 28,
+        _ => 0,
+    }
+}
+
+/// 把自 1970-01-01 起的天数转换为公历日期 (年, 月, 日)
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    // 以 0000-03-01 为纪元按 400 年周期计算，闰日落在每年的最后
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    
+    (year, month, day)
+}
+
+/// 把公历日期转换为自 1970-01-01 起的天数
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = month as i64;
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * mp + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// 解析时长，如 "30d"、"12h"、"2w"、"45m"、"10s"
+pub fn parse_duration(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let split = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: u64 = number.parse().ok()?;
+    
+    let multiplier = match unit {
+        "s" | "" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        "w" => 7 * 86_400,
+        _ => return None,
+    };
+    
+    number.checked_mul(multiplier)
+}
+
+/// 解析时间点，支持 "@1700000000"、"2024-03-15"、"2024-03-15 14:30"、
+/// "2024-03-15T14:30:05"，可带 "Z" 或 "+08:00" 后缀，否则按本地时区
+pub fn parse_time(text: &str) -> Option<i64> {
+    let text = text.trim();
+    if let Some(seconds) = text.strip_prefix('@') {
+        return seconds.parse().ok();
+    }
+    
+    let date = text.get(..10)?;
+    let rest = &text[10..];
+    let mut parts = date.splitn(3, '-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: u32 = parts.next()?.parse().ok()?;
+    let day: u32 = parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+    
+    // 拆出时区后缀
+    let (clock, offset) = if let Some(clock) = rest.strip_suffix('Z') {
+        (clock, Some(0))
+    } else if let Some(split) = rest.rfind(['+', '-']) {
+        let (clock, zone) = rest.split_at(split);
+        let digits: String = zone[1..].chars().filter(|c| *c != ':').collect();
+        if digits.len() != 4 || !digits.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let minutes = digits[..2].parse::<i64>().ok()? * 60 + digits[2..].parse::<i64>().ok()?;
+        (clock, Some(if zone.starts_with('-') { -minutes * 60 } else { minutes * 60 }))
+    } else {
+        (rest, None)
+    };
+    
+    let seconds_of_day = match clock {
+        "" => 0,
+        _ => {
+            let clock = clock.strip_prefix([' ', 'T'])?;
+            let fields: Vec<i64> = clock.split(':').map(|f| f.parse().ok()).collect::<Option<_>>()?;
+            match fields[..] {
+                [h, m] if h < 24 && m < 60 => h * 3600 + m * 60,
+                [h, m, s] if h < 24 && m < 60 && s < 60 => h * 3600 + m * 60 + s,
+                _ => return None,
+            }
+        },
+    };
+    
+    let naive = days_from_civil(year, month, day) * 86_400 + seconds_of_day;
+    Some(match offset {
+        Some(offset) => naive - offset,
+        // 先按当前偏移估算，再用估算时刻的偏移修正，处理夏令时切换
+        None => naive - local_offset(naive - local_offset(naive)),
+    })
+}
+
+/// 移动文件或目录，跨文件系统时退化为复制后删除
+pub fn move_path(source: &Path, destination: &Path) -> Result<()> {
+    if fs::rename(source, destination).is_ok() {
+        return Ok(());
+    }
+    
+    copy_recursive(source, destination)?;
+    
+    if source.is_dir() {
+        fs::remove_dir_all(source).with_path(source)?;
+    } else {
+        fs::remove_file(source).with_path(source)?;
+    }
+    
+    Ok(())
+}
+
+fn copy_recursive(source: &Path, destination: &Path) -> Result<()> {
+    if source.is_dir() {
+        fs::create_dir_all(destination).with_path(destination)?;
+        for entry in fs::read_dir(source).with_path(source)? {
+            let entry = entry.with_path(source)?;
+            copy_recursive(&entry.path(), &destination.join(entry.file_name()))?;
+        }
+    } else {
+        fs::copy(source, destination).with_path(source)?;
+    }
+    
+    Ok(())
+}
+
+/// 把权限位转换为 ls 风格的字符串，如 "drwxr-xr-x"
+pub fn permission_string(mode: u32) -> String {
+    let file_type = match mode & 0o170000 {
+        0o040000 => 'd',
+        0o120000 => 'l',
+        0o020000 => 'c',
+        0o060000 => 'b',
+        0o010000 => 'p',
+        0o140000 => 's',
+        _ => '-',
+    };
+    
+    let mut result = String::with_capacity(10);
+    result.push(file_type);
+    
+    // (读, 写, 执行, 特殊位, 特殊位字符)
+    let triples = [
+        (0o400, 0o200, 0o100, 0o4000, 's'),
+        (0o040, 0o020, 0o010, 0o2000, 's'),
+        (0o004, 0o002, 0o001, 0o1000, 't'),
+    ];
+    
+    for (read, write, execute, special, special_char) in triples {
+        result.push(if mode & read != 0 { 'r' } else { '-' });
+        result.push(if mode & write != 0 { 'w' } else { '-' });
+        result.push(match (mode & execute != 0, mode & special != 0) {
+            (true, true) => special_char,
+            (false, true) => special_char.to_ascii_uppercase(),
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+    
+    result
 }
 
 /// 获取文件扩展名
@@ -120,13 +419,32 @@ pub fn get_file_extension(filename: &str) -> Option<&str> {
         .and_then(|ext| ext.to_str())
 }
 
-/// 验证路径是否安全（防止路径遍历攻击）
-pub fn is_safe_path(path: &str) -> bool {
-    // 基本的安全检查
-    !path.contains("..") && !path.starts_with('/')
+/// 验证相对路径是否安全（防止路径遍历攻击）
+///
+/// 按组件检查而不是按字符串：`a..b` 这样的文件名是合法的，
+/// 只有绝对路径或 ".." 回退到起点之外时才不安全。不解析符号链接，
+/// 需要考虑符号链接时使用 `Sandbox::check`。
+pub fn is_safe_path<P: AsRef<std::path::Path>>(path: P) -> bool {
+    use std::path::Component;
+    
+    let mut depth = 0usize;
+    for component in path.as_ref().components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir => return false,
+            Component::CurDir => {},
+            Component::ParentDir => match depth.checked_sub(1) {
+                Some(parent) => depth = parent,
+                None => return false,
+            },
+            Component::Normal(_) => depth += 1,
+        }
+    }
+    
+    true
 }
 
 /// 创建备份文件名
+#[allow(dead_code)]
 pub fn create_backup_name(original: &str) -> String {
     let path = std::path::Path::new(original);
     
@@ -167,13 +485,27 @@ mod tests {
         assert_eq!(get_file_extension(".hidden"), None);
     }
     
+    #[test]
+    fn test_permission_string() {
+        assert_eq!(permission_string(0o100644), "-rw-r--r--");
+        assert_eq!(permission_string(0o040755), "drwxr-xr-x");
+        assert_eq!(permission_string(0o120777), "lrwxrwxrwx");
+        assert_eq!(permission_string(0o104755), "-rwsr-xr-x");
+        assert_eq!(permission_string(0o041777), "drwxrwxrwt");
+        assert_eq!(permission_string(0o102644), "-rw-r-Sr--");
+    }
+    
     #[test]
     fn test_is_safe_path() {
         assert!(is_safe_path("file.txt"));
         assert!(is_safe_path("dir/file.txt"));
         assert!(!is_safe_path("../file.txt"));
         assert!(!is_safe_path("/etc/passwd"));
-        assert!(!is_safe_path("dir/../file.txt"));
+        assert!(!is_safe_path("dir/../../file.txt"));
+        assert!(is_safe_path("dir/../file.txt"));
+        assert!(is_safe_path("a..b"));
+        assert!(is_safe_path("dir/..hidden/file"));
+        assert!(is_safe_path(""));
     }
     
     #[test]
@@ -183,6 +515,96 @@ mod tests {
         assert_eq!(create_backup_name("test"), "test.backup");
     }
     
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(days_from_civil(2024, 12, 31), 20_088);
+        
+        for days in [-800_000, -1, 0, 59, 365, 10_957, 20_000, 2_932_896] {
+            let (y, m, d) = civil_from_days(days);
+            assert_eq!(days_from_civil(y, m, d), days);
+        }
+    }
+    
+    #[test]
+    fn test_strftime_known_timestamps() {
+        // 2024-03-15 14:30:05 UTC，星期五
+        let ts = 1_710_513_005;
+        assert_eq!(strftime("%Y-%m-%d %H:%M:%S", ts, 0), "2024-03-15 14:30:05");
+        assert_eq!(strftime("%F %T %z", ts, 8 * 3600), "2024-03-15 22:30:05 +0800");
+        assert_eq!(strftime("%a %A %b %B %j", ts, 0), "Fri Friday Mar March 075");
+        assert_eq!(strftime("%I:%M %p|%e|%y|%%|%q", ts, 0), "02:30 PM|15|24|%|%q");
+        // 跨日的负偏移
+        assert_eq!(strftime("%F %R %:z", ts, -(15 * 3600 + 30 * 60)), "2024-03-14 23:00 -15:30");
+        // 闰年最后一天与 epoch 之前
+        assert_eq!(strftime("%F %j", 1_735_603_200, 0), "2024-12-31 366");
+        assert_eq!(strftime("%F %T", -1, 0), "1969-12-31 23:59:59");
+        assert_eq!(strftime("%s", 0, 3600), "0");
+    }
+    
+    #[test]
+    fn test_format_timestamp_styles() {
+        let ts = 951_782_400; // 2000-02-29 00:00:00 UTC
+        assert_eq!(format_timestamp(ts, 0, &TimeStyle::Iso), "2000-02-29 00:00");
+        assert_eq!(format_timestamp(ts, 5 * 3600 + 1800, &TimeStyle::FullIso), "2000-02-29T05:30:00+05:30");
+        assert_eq!(format_timestamp(ts, 0, &TimeStyle::Custom("%d/%m/%Y".to_string())), "29/02/2000");
+    }
+    
+    #[test]
+    fn test_format_relative() {
+        let now = 1_700_000_000;
+        assert_eq!(format_relative(now - 30, now), "30 秒前");
+        assert_eq!(format_relative(now - 5 * 60, now), "5 分钟前");
+        assert_eq!(format_relative(now - 3 * 3600, now), "3 小时前");
+        assert_eq!(format_relative(now - 2 * 86_400, now), "2 天前");
+        assert_eq!(format_relative(now + 10, now), "未来时间");
+    }
+    
+    #[test]
+    fn test_time_style_from_str() {
+        assert_eq!("relative".parse::<TimeStyle>(), Ok(TimeStyle::Relative));
+        assert_eq!("iso".parse::<TimeStyle>(), Ok(TimeStyle::Iso));
+        assert_eq!("full-iso".parse::<TimeStyle>(), Ok(TimeStyle::FullIso));
+        assert_eq!("+%H".parse::<TimeStyle>(), Ok(TimeStyle::Custom("%H".to_string())));
+        assert!("long".parse::<TimeStyle>().is_err());
+    }
+    
+    #[test]
+    fn test_days_in_month() {
+        assert_eq!(days_in_month(2024, 2), 29);
+        assert_eq!(days_in_month(1900, 2), 28);
+        assert_eq!(days_in_month(2023, 4), 30);
+        assert_eq!(days_in_month(2023, 13), 0);
+    }
+    
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("30d"), Some(30 * 86_400));
+        assert_eq!(parse_duration("12h"), Some(12 * 3600));
+        assert_eq!(parse_duration("2w"), Some(14 * 86_400));
+        assert_eq!(parse_duration("90"), Some(90));
+        assert_eq!(parse_duration("d"), None);
+        assert_eq!(parse_duration("3y"), None);
+    }
+    
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(parse_time("@1700000000"), Some(1_700_000_000));
+        assert_eq!(parse_time("2024-03-15Z"), Some(1_710_460_800));
+        assert_eq!(parse_time("2024-03-15T14:30:05Z"), Some(1_710_513_005));
+        assert_eq!(parse_time("2024-03-15 22:30+08:00"), Some(1_710_513_000));
+        assert_eq!(parse_time("2024-03-15 06:30-0800"), Some(1_710_513_000));
+        
+        let local = parse_time("2024-03-15 14:30").unwrap();
+        assert_eq!(local + local_offset(local), 1_710_513_000);
+        
+        for invalid in ["", "2024-3-15", "2024-02-30", "2024-03-15 25:00", "2024-03-15 14", "2024-03-15+8", "@x"] {
+            assert_eq!(parse_time(invalid), None, "{:?}", invalid);
+        }
+    }
+    
     #[test]
     fn test_is_leap_year() {
         assert!(is_leap_year(2000));
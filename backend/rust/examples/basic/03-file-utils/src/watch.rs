@@ -0,0 +1,564 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+use crate::error::{FileUtilsError, Result};
+use crate::records::{serialize_time, FileKind};
+use crate::sync::Filter;
+
+/// 默认的防抖时长：最后一个事件之后这么久没有新事件才输出一批
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// watch 的选项，对应 `--include`、`--exclude`、`--debounce`、`--exec`
+#[derive(Debug, Clone)]
+pub struct WatchOptions {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub debounce: Duration,
+    /// 每批事件之后通过 `sh -c` 运行的命令
+    pub exec: Option<String>,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        WatchOptions {
+            include: Vec::new(),
+            exclude: Vec::new(),
+            debounce: DEFAULT_DEBOUNCE,
+            exec: None,
+        }
+    }
+}
+
+/// 变化的种类
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Created,
+    Modified,
+    Deleted,
+    Renamed,
+}
+
+impl ChangeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeKind::Created => "created",
+            ChangeKind::Modified => "modified",
+            ChangeKind::Deleted => "deleted",
+            ChangeKind::Renamed => "renamed",
+        }
+    }
+}
+
+/// 一个经过防抖合并的文件变化事件
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangeEvent {
+    pub event: ChangeKind,
+    #[serde(rename = "type")]
+    pub kind: FileKind,
+    /// 文件当前的字节数，目录和已删除的文件为 0
+    pub size: u64,
+    #[serde(serialize_with = "serialize_time")]
+    pub time: Option<i64>,
+    pub path: PathBuf,
+    /// 重命名前的路径
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<PathBuf>,
+}
+
+/// 内核报告的原始事件，路径相对于监控的根目录
+#[derive(Debug, Clone, PartialEq)]
+struct RawEvent {
+    kind: RawKind,
+    path: PathBuf,
+    is_dir: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RawKind {
+    Created,
+    Modified,
+    Deleted,
+    /// 带 cookie 的移出/移入，成对出现时是一次重命名
+    MovedFrom(u32),
+    MovedTo(u32),
+}
+
+/// 合并后的变化，还没有补充大小等信息
+#[derive(Debug, Clone, PartialEq)]
+struct Change {
+    kind: ChangeKind,
+    path: PathBuf,
+    from: Option<PathBuf>,
+    is_dir: bool,
+}
+
+impl Change {
+    fn new(kind: ChangeKind, path: PathBuf, is_dir: bool) -> Self {
+        Change { kind, path, from: None, is_dir }
+    }
+}
+
+/// 把一批原始事件合并为每个路径一条变化
+///
+/// 新建后修改仍是新建，新建后删除相互抵消，删除后重建算作修改；
+/// 成对的移出/移入合并为重命名，落单的分别算作删除和新建（移出或移入了监控范围）。
+fn coalesce(raw: Vec<RawEvent>) -> Vec<Change> {
+    let mut changes: Vec<Change> = Vec::new();
+    let mut moved_from: HashMap<u32, RawEvent> = HashMap::new();
+
+    for event in raw {
+        match event.kind {
+            RawKind::Created => apply_created(&mut changes, event.path, event.is_dir),
+            RawKind::Modified => {
+                if !changes.iter().any(|c| c.path == event.path) {
+                    changes.push(Change::new(ChangeKind::Modified, event.path, event.is_dir));
+                }
+            },
+            RawKind::Deleted => apply_deleted(&mut changes, event.path, event.is_dir),
+            RawKind::MovedFrom(cookie) => {
+                moved_from.insert(cookie, event);
+            },
+            RawKind::MovedTo(cookie) => match moved_from.remove(&cookie) {
+                Some(from) => apply_renamed(&mut changes, from.path, event.path, event.is_dir),
+                None => apply_created(&mut changes, event.path, event.is_dir),
+            },
+        }
+    }
+
+    let mut left: Vec<RawEvent> = moved_from.into_values().collect();
+    left.sort_by(|a, b| a.path.cmp(&b.path));
+    for event in left {
+        apply_deleted(&mut changes, event.path, event.is_dir);
+    }
+
+    changes
+}
+
+fn apply_created(changes: &mut Vec<Change>, path: PathBuf, is_dir: bool) {
+    match changes.iter_mut().find(|c| c.path == path) {
+        Some(change) if change.kind == ChangeKind::Deleted => change.kind = ChangeKind::Modified,
+        Some(_) => {},
+        None => changes.push(Change::new(ChangeKind::Created, path, is_dir)),
+    }
+}
+
+fn apply_deleted(changes: &mut Vec<Change>, path: PathBuf, is_dir: bool) {
+    match changes.iter().position(|c| c.path == path) {
+        Some(i) if changes[i].kind == ChangeKind::Created => {
+            changes.remove(i);
+        },
+        Some(i) => {
+            // 重命名后又删除，等于删除了原来的文件
+            let change = &mut changes[i];
+            if let Some(from) = change.from.take() {
+                change.path = from;
+            }
+            change.kind = ChangeKind::Deleted;
+        },
+        None => changes.push(Change::new(ChangeKind::Deleted, path, is_dir)),
+    }
+}
+
+fn apply_renamed(changes: &mut Vec<Change>, from: PathBuf, to: PathBuf, is_dir: bool) {
+    match changes.iter().position(|c| c.path == from) {
+        // 刚新建的文件被改名，只报告最终的名字
+        Some(i) if changes[i].kind == ChangeKind::Created => {
+            changes.remove(i);
+            apply_created(changes, to, is_dir);
+        },
+        _ => changes.push(Change { kind: ChangeKind::Renamed, path: to, from: Some(from), is_dir }),
+    }
+}
+
+/// 变化是否通过 include/exclude 过滤；include 只作用于文件
+fn accepted(filter: &Filter, change: &Change) -> bool {
+    let passes = |path: &Path| !filter.excluded(path) && (change.is_dir || filter.included(path));
+    passes(&change.path) || change.from.as_deref().is_some_and(passes)
+}
+
+/// 补充文件类型、大小和时间，路径转换为以根目录开头
+fn to_event(root: &Path, change: Change, time: i64) -> ChangeEvent {
+    let path = root.join(&change.path);
+    let metadata = match change.kind {
+        ChangeKind::Deleted => None,
+        _ => fs::symlink_metadata(&path).ok(),
+    };
+
+    let kind = match &metadata {
+        Some(metadata) => FileKind::from_metadata(metadata),
+        None if change.is_dir => FileKind::Dir,
+        None => FileKind::File,
+    };
+
+    ChangeEvent {
+        event: change.kind,
+        kind,
+        size: metadata.filter(|m| m.is_file()).map(|m| m.len()).unwrap_or(0),
+        time: Some(time),
+        path,
+        from: change.from.map(|from| root.join(from)),
+    }
+}
+
+/// 通过 `sh -c` 运行 --exec 命令，变化的路径每行一个放在环境变量 FILE_UTILS_CHANGED 中
+///
+/// 命令失败只打印警告，不会结束监控。
+pub fn run_command(command: &str, events: &[ChangeEvent]) -> Result<()> {
+    let changed: Vec<String> = events.iter().map(|e| e.path.display().to_string()).collect();
+
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("FILE_UTILS_CHANGED", changed.join("\n"))
+        .status()
+        .map_err(|e| FileUtilsError::from_io(e, "sh"))?;
+
+    if !status.success() {
+        eprintln!("⚠️  命令执行失败 ({}): {}", status, command);
+    }
+
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+pub use linux::Watcher;
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use inotify::{EventMask, Inotify, WatchDescriptor, WatchMask};
+    use std::collections::HashMap;
+    use std::fs;
+    use std::io;
+    use std::os::unix::io::AsRawFd;
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    use super::{accepted, coalesce, to_event, ChangeEvent, RawEvent, RawKind};
+    use crate::error::{PathContext, Result};
+    use crate::sync::Filter;
+    use crate::utils;
+
+    /// 基于 inotify 的递归目录监控：每个子目录一个 watch，新建的子目录会自动加入
+    pub struct Watcher {
+        inotify: Inotify,
+        root: PathBuf,
+        /// watch 对应的目录，相对于根目录
+        dirs: HashMap<WatchDescriptor, PathBuf>,
+        filter: Filter,
+        debounce: Duration,
+        buffer: Vec<u8>,
+    }
+
+    impl Watcher {
+        pub fn new(root: &Path, filter: Filter, debounce: Duration) -> Result<Self> {
+            let mut watcher = Watcher {
+                inotify: Inotify::init().with_path(root)?,
+                root: root.to_path_buf(),
+                dirs: HashMap::new(),
+                filter,
+                debounce,
+                buffer: vec![0; 64 * 1024],
+            };
+
+            watcher.add_tree(Path::new(""), &mut Vec::new())?;
+            Ok(watcher)
+        }
+
+        /// 等待下一批事件；timeout 为 None 时一直等待，超时返回空列表
+        ///
+        /// 收到第一个事件后继续读取，直到 debounce 时长内没有新事件，再把这批事件合并输出。
+        pub fn next_batch(&mut self, timeout: Option<Duration>) -> Result<Vec<ChangeEvent>> {
+            if !self.wait(timeout)? {
+                return Ok(Vec::new());
+            }
+
+            let mut raw = Vec::new();
+            self.read_raw(&mut raw)?;
+            while self.wait(Some(self.debounce))? {
+                self.read_raw(&mut raw)?;
+            }
+
+            let time = utils::current_timestamp();
+            Ok(coalesce(raw)
+                .into_iter()
+                .filter(|change| accepted(&self.filter, change))
+                .map(|change| to_event(&self.root, change, time))
+                .collect())
+        }
+
+        /// 为目录及其子目录添加 watch；目录中已有的条目作为新建事件放入 discovered，
+        /// 这样 `mkdir -p a/b && touch a/b/c` 这种在 watch 生效前完成的操作也能被报告
+        fn add_tree(&mut self, relative: &Path, discovered: &mut Vec<RawEvent>) -> Result<()> {
+            let dir = self.root.join(relative);
+            let mask = WatchMask::CREATE
+                | WatchMask::MODIFY
+                | WatchMask::DELETE
+                | WatchMask::MOVED_FROM
+                | WatchMask::MOVED_TO
+                | WatchMask::ONLYDIR
+                | WatchMask::DONT_FOLLOW;
+
+            let wd = match self.inotify.watches().add(&dir, mask) {
+                Ok(wd) => wd,
+                // 刚创建就被删除的目录
+                Err(e) if e.kind() == io::ErrorKind::NotFound && !relative.as_os_str().is_empty() => return Ok(()),
+                Err(e) => return Err(e).with_path(&dir),
+            };
+            self.dirs.insert(wd, relative.to_path_buf());
+
+            let entries = match fs::read_dir(&dir) {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+                Err(e) => return Err(e).with_path(&dir),
+            };
+
+            for entry in entries.flatten() {
+                let child = relative.join(entry.file_name());
+                let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+                discovered.push(RawEvent { kind: RawKind::Created, path: child.clone(), is_dir });
+
+                if is_dir && !self.filter.excluded(&child) {
+                    self.add_tree(&child, discovered)?;
+                }
+            }
+
+            Ok(())
+        }
+
+        /// 用 poll 等待 inotify 可读，返回是否有事件
+        fn wait(&self, timeout: Option<Duration>) -> Result<bool> {
+            let mut pollfd = libc::pollfd {
+                fd: self.inotify.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            let timeout = timeout.map(|t| t.as_millis().min(i32::MAX as u128) as i32).unwrap_or(-1);
+
+            // SAFETY: pollfd 在调用期间有效，数量为 1
+            let result = unsafe { libc::poll(&mut pollfd, 1, timeout) };
+            match result {
+                -1 => {
+                    let error = io::Error::last_os_error();
+                    if error.kind() == io::ErrorKind::Interrupted {
+                        Ok(false)
+                    } else {
+                        Err(error).with_path(&self.root)
+                    }
+                },
+                0 => Ok(false),
+                _ => Ok(true),
+            }
+        }
+
+        /// 读出当前所有可读的事件，并为新出现的子目录添加 watch
+        fn read_raw(&mut self, raw: &mut Vec<RawEvent>) -> Result<()> {
+            let start = raw.len();
+            let mut removed = Vec::new();
+
+            loop {
+                let events = match self.inotify.read_events(&mut self.buffer) {
+                    Ok(events) => events,
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                    Err(e) => return Err(e).with_path(&self.root),
+                };
+
+                for event in events {
+                    if event.mask.contains(EventMask::Q_OVERFLOW) {
+                        eprintln!("⚠️  事件过多，部分变化可能没有报告");
+                        continue;
+                    }
+                    if event.mask.contains(EventMask::IGNORED) {
+                        removed.push(event.wd);
+                        continue;
+                    }
+
+                    let (Some(dir), Some(name)) = (self.dirs.get(&event.wd), event.name) else {
+                        continue;
+                    };
+                    let kind = if event.mask.contains(EventMask::CREATE) {
+                        RawKind::Created
+                    } else if event.mask.contains(EventMask::MODIFY) {
+                        RawKind::Modified
+                    } else if event.mask.contains(EventMask::DELETE) {
+                        RawKind::Deleted
+                    } else if event.mask.contains(EventMask::MOVED_FROM) {
+                        RawKind::MovedFrom(event.cookie)
+                    } else if event.mask.contains(EventMask::MOVED_TO) {
+                        RawKind::MovedTo(event.cookie)
+                    } else {
+                        continue;
+                    };
+
+                    raw.push(RawEvent {
+                        kind,
+                        path: dir.join(name),
+                        is_dir: event.mask.contains(EventMask::ISDIR),
+                    });
+                }
+            }
+
+            for wd in removed {
+                self.dirs.remove(&wd);
+            }
+
+            self.track_dirs(raw, start)
+        }
+
+        /// 根据新读到的目录事件维护 watch：新建或移入的目录加入监控，改名的目录更新路径
+        fn track_dirs(&mut self, raw: &mut Vec<RawEvent>, start: usize) -> Result<()> {
+            let mut moved_from = HashMap::new();
+            let mut discovered = Vec::new();
+
+            for event in raw[start..].iter().filter(|e| e.is_dir) {
+                match event.kind {
+                    RawKind::MovedFrom(cookie) => {
+                        moved_from.insert(cookie, event.path.clone());
+                    },
+                    RawKind::MovedTo(cookie) if moved_from.contains_key(&cookie) => {
+                        let from = moved_from.remove(&cookie).unwrap_or_default();
+                        for dir in self.dirs.values_mut() {
+                            if let Ok(rest) = dir.strip_prefix(&from) {
+                                *dir = event.path.join(rest);
+                            }
+                        }
+                    },
+                    RawKind::Created | RawKind::MovedTo(_) if !self.filter.excluded(&event.path) => {
+                        let path = event.path.clone();
+                        self.add_tree(&path, &mut discovered)?;
+                    },
+                    _ => {},
+                }
+            }
+
+            // 移出监控范围的目录不再监控
+            for from in moved_from.into_values() {
+                let stale: Vec<WatchDescriptor> = self
+                    .dirs
+                    .iter()
+                    .filter(|(_, dir)| dir.starts_with(&from))
+                    .map(|(wd, _)| wd.clone())
+                    .collect();
+                for wd in stale {
+                    self.dirs.remove(&wd);
+                    let _ = self.inotify.watches().remove(wd);
+                }
+            }
+
+            raw.extend(discovered);
+            Ok(())
+        }
+    }
+}
+
+/// 其他平台暂不支持
+#[cfg(not(target_os = "linux"))]
+pub struct Watcher;
+
+#[cfg(not(target_os = "linux"))]
+impl Watcher {
+    pub fn new(_root: &Path, _filter: Filter, _debounce: Duration) -> Result<Self> {
+        Err(FileUtilsError::InvalidInput("watch 目前只支持 Linux (inotify)".to_string()))
+    }
+
+    pub fn next_batch(&mut self, _timeout: Option<Duration>) -> Result<Vec<ChangeEvent>> {
+        Ok(Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw(kind: RawKind, path: &str) -> RawEvent {
+        RawEvent { kind, path: PathBuf::from(path), is_dir: false }
+    }
+
+    fn summary(changes: &[Change]) -> Vec<(ChangeKind, &str)> {
+        changes.iter().map(|c| (c.kind, c.path.to_str().unwrap())).collect()
+    }
+
+    #[test]
+    fn test_coalesce() {
+        let changes = coalesce(vec![
+            raw(RawKind::Created, "new.txt"),
+            raw(RawKind::Modified, "new.txt"),
+            raw(RawKind::Created, "tmp.swp"),
+            raw(RawKind::Deleted, "tmp.swp"),
+            raw(RawKind::Deleted, "saved.txt"),
+            raw(RawKind::Created, "saved.txt"),
+            raw(RawKind::MovedFrom(7), "a.txt"),
+            raw(RawKind::MovedTo(7), "b.txt"),
+            raw(RawKind::MovedFrom(8), "gone.txt"),
+            raw(RawKind::MovedTo(9), "arrived.txt"),
+        ]);
+
+        assert_eq!(
+            summary(&changes),
+            [
+                (ChangeKind::Created, "new.txt"),
+                (ChangeKind::Modified, "saved.txt"),
+                (ChangeKind::Renamed, "b.txt"),
+                (ChangeKind::Created, "arrived.txt"),
+                (ChangeKind::Deleted, "gone.txt"),
+            ]
+        );
+        assert_eq!(changes[2].from.as_deref(), Some(Path::new("a.txt")));
+
+        // 重命名后删除等于删除原文件，新建后改名只报告新名字
+        let changes = coalesce(vec![
+            raw(RawKind::MovedFrom(1), "a.txt"),
+            raw(RawKind::MovedTo(1), "b.txt"),
+            raw(RawKind::Deleted, "b.txt"),
+            raw(RawKind::Created, "c.txt"),
+            raw(RawKind::MovedFrom(2), "c.txt"),
+            raw(RawKind::MovedTo(2), "d.txt"),
+        ]);
+        assert_eq!(summary(&changes), [(ChangeKind::Deleted, "a.txt"), (ChangeKind::Created, "d.txt")]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_watcher_reports_changes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        let filter = Filter::new(&[], &["*.tmp".to_string()]).unwrap();
+        let mut watcher = Watcher::new(root, filter, Duration::from_millis(50)).unwrap();
+        let timeout = Some(Duration::from_secs(5));
+
+        let batch = |watcher: &mut Watcher| -> Vec<(ChangeKind, PathBuf)> {
+            let mut events: Vec<_> = watcher
+                .next_batch(timeout)
+                .unwrap()
+                .into_iter()
+                .map(|e| (e.event, e.path.strip_prefix(root).unwrap().to_path_buf()))
+                .collect();
+            events.sort_by(|a, b| a.1.cmp(&b.1));
+            events
+        };
+
+        fs::write(root.join("a.txt"), "hello").unwrap();
+        assert_eq!(batch(&mut watcher), [(ChangeKind::Created, PathBuf::from("a.txt"))]);
+
+        fs::rename(root.join("a.txt"), root.join("b.txt")).unwrap();
+        assert_eq!(batch(&mut watcher), [(ChangeKind::Renamed, PathBuf::from("b.txt"))]);
+
+        // 新建的子目录自动加入监控，其中的文件也会被报告
+        fs::create_dir_all(root.join("sub/deep")).unwrap();
+        fs::write(root.join("sub/deep/c.txt"), "c").unwrap();
+        let events = batch(&mut watcher);
+        assert!(events.contains(&(ChangeKind::Created, PathBuf::from("sub/deep/c.txt"))));
+
+        fs::write(root.join("sub/deep/c.txt"), "changed").unwrap();
+        assert_eq!(batch(&mut watcher), [(ChangeKind::Modified, PathBuf::from("sub/deep/c.txt"))]);
+
+        // 被排除的文件不产生事件
+        fs::write(root.join("x.tmp"), "x").unwrap();
+        fs::remove_file(root.join("b.txt")).unwrap();
+        assert_eq!(batch(&mut watcher), [(ChangeKind::Deleted, PathBuf::from("b.txt"))]);
+    }
+}
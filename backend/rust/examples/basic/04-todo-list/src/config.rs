@@ -0,0 +1,118 @@
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::sqlite::SqliteStorage;
+use crate::storage::{JsonStorage, Storage};
+
+/// 配置文件，放在运行目录下
+pub const CONFIG_FILE: &str = "todo-config.json";
+
+/// 覆盖配置文件中存储方式的环境变量
+const STORAGE_ENV: &str = "TODO_STORAGE";
+
+/// 任务数据的存储方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// 整个保存在一个 JSON 文件中
+    #[default]
+    Json,
+    /// 嵌入式 SQLite 数据库文件
+    Sqlite,
+}
+
+impl Backend {
+    pub fn default_path(&self) -> &'static str {
+        match self {
+            Backend::Json => "todos.json",
+            Backend::Sqlite => "todos.db",
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Backend::Json => write!(f, "json"),
+            Backend::Sqlite => write!(f, "sqlite"),
+        }
+    }
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.trim().to_lowercase().as_str() {
+            "json" => Ok(Backend::Json),
+            "sqlite" | "sqlite3" | "db" => Ok(Backend::Sqlite),
+            _ => Err(format!("无效的存储方式: {} (可选: json, sqlite)", text)),
+        }
+    }
+}
+
+/// 程序配置，如 `{"storage": "sqlite", "path": "todos.db"}`，每一项都可以省略
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    pub storage: Backend,
+    /// 数据文件的路径，省略时使用存储方式的默认路径
+    #[serde(default)]
+    pub path: Option<String>,
+}
+
+impl Config {
+    /// 读取配置文件，没有配置文件时使用默认配置；环境变量 TODO_STORAGE 可以临时切换存储方式
+    pub fn load() -> Result<Self, Box<dyn Error>> {
+        let file = JsonStorage::new(CONFIG_FILE);
+        let mut config: Config = if file.exists() { file.load()? } else { Config::default() };
+
+        if let Ok(backend) = env::var(STORAGE_ENV) {
+            let backend: Backend = backend.parse()?;
+            // 配置文件中的路径属于原来的存储方式
+            if backend != config.storage {
+                config = Config { storage: backend, path: None };
+            }
+        }
+        Ok(config)
+    }
+
+    /// 配置的存储方式使用的路径
+    pub fn path(&self, backend: Backend) -> String {
+        match &self.path {
+            Some(path) if backend == self.storage => path.clone(),
+            _ => backend.default_path().to_string(),
+        }
+    }
+
+    /// 打开指定方式的存储，路径见 `path`
+    pub fn open(&self, backend: Backend) -> Result<Box<dyn Storage>, Box<dyn Error>> {
+        let path = self.path(backend);
+        Ok(match backend {
+            Backend::Json => Box::new(JsonStorage::new(&path)),
+            Backend::Sqlite => Box::new(SqliteStorage::open(&path)?),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backend_and_paths() {
+        assert_eq!("SQLite".parse::<Backend>(), Ok(Backend::Sqlite));
+        assert!("mysql".parse::<Backend>().unwrap_err().contains("可选"));
+
+        let config: Config = serde_json::from_str("{}").unwrap();
+        assert_eq!(config, Config::default());
+        assert_eq!(config.path(Backend::Json), "todos.json");
+
+        let config: Config = serde_json::from_str(r#"{"storage": "sqlite", "path": "data/my.db"}"#).unwrap();
+        assert_eq!(config.path(Backend::Sqlite), "data/my.db");
+        assert_eq!(config.path(Backend::Json), "todos.json");
+    }
+}
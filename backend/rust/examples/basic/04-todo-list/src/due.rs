@@ -0,0 +1,204 @@
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Weekday};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// 任务的截止时间
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Due {
+    /// unix 时间戳（秒）；只给出日期时为当天的最后一秒
+    pub at: i64,
+    /// 只指定了日期，没有具体时间
+    pub all_day: bool,
+}
+
+impl Due {
+    /// 按本地时间解析截止时间，相对时间以 now 为基准
+    ///
+    /// 支持 `today`/`tomorrow`（今天/明天/后天）、星期几（`fri`、`周五`）、
+    /// `+3d`/`+2w`/`+4h`/`+30m`、`2026-11-01`、`14:00`，日期后面可以再跟 `HH:MM`
+    pub fn parse(text: &str, now: DateTime<Local>) -> Result<Due, String> {
+        let text = text.trim().to_lowercase();
+        let invalid = || format!("无法识别的时间: {} (示例: tomorrow, +3d, 2026-11-01 14:00)", text);
+
+        // +4h、+30m 直接在当前时刻上加，不再接受后面的时间
+        if let Some(offset) = text.strip_prefix('+') {
+            if offset.ends_with(['h', 'm']) {
+                let seconds = parse_duration(offset)?;
+                return Ok(Due { at: now.timestamp() + seconds, all_day: false });
+            }
+        }
+
+        if let Ok(datetime) = NaiveDateTime::parse_from_str(&text, "%Y-%m-%dt%H:%M") {
+            return from_local(datetime, false);
+        }
+
+        let (day, time) = match text.rsplit_once(char::is_whitespace) {
+            Some((day, time)) => (day.trim(), Some(parse_time(time).ok_or_else(invalid)?)),
+            None => match parse_time(&text) {
+                Some(time) => ("today", Some(time)),
+                None => (text.as_str(), None),
+            },
+        };
+
+        let today = now.date_naive();
+        let date = match day {
+            "today" | "今天" => today,
+            "tomorrow" | "明天" => today + Duration::days(1),
+            "后天" => today + Duration::days(2),
+            _ => {
+                if let Some(offset) = day.strip_prefix('+').filter(|offset| offset.ends_with(['d', 'w'])) {
+                    today + Duration::seconds(parse_duration(offset)?)
+                } else if let Some(weekday) = parse_weekday(day) {
+                    // 总是下一个该星期几，今天是周五时 fri 表示下周五
+                    let ahead = (weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()) % 7;
+                    today + Duration::days(if ahead == 0 { 7 } else { ahead as i64 })
+                } else {
+                    NaiveDate::parse_from_str(day, "%Y-%m-%d").map_err(|_| invalid())?
+                }
+            },
+        };
+
+        Due::on(date, time)
+    }
+
+    /// 本地日期上的截止时间，没有 time 时为全天
+    pub fn on(date: NaiveDate, time: Option<NaiveTime>) -> Result<Due, String> {
+        match time {
+            Some(time) => from_local(date.and_time(time), false),
+            None => from_local(date.and_hms_opt(23, 59, 59).expect("有效的时间"), true),
+        }
+    }
+
+    /// 截止时间已过
+    pub fn is_past(&self, now: i64) -> bool {
+        self.at < now
+    }
+
+    /// 截止时间所在的本地日期
+    pub fn date(&self) -> NaiveDate {
+        local(self.at).date_naive()
+    }
+
+    /// 截止的本地时刻，全天的截止时间为 None
+    pub fn time(&self) -> Option<NaiveTime> {
+        (!self.all_day).then(|| local(self.at).time())
+    }
+}
+
+impl FromStr for Due {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Due::parse(text, Local::now())
+    }
+}
+
+impl fmt::Display for Due {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let format = if self.all_day { "%Y-%m-%d" } else { "%Y-%m-%d %H:%M" };
+        write!(f, "{}", local(self.at).format(format))
+    }
+}
+
+/// 解析时长，如 `30m`、`4h`、`3d`、`2w`，返回秒数
+pub fn parse_duration(text: &str) -> Result<i64, String> {
+    let text = text.trim();
+    let invalid = || format!("无效的时长: {} (示例: 30m, 4h, 3d, 2w)", text);
+
+    let unit = text.chars().last().ok_or_else(invalid)?;
+    let count: i64 = text[..text.len() - unit.len_utf8()].parse().map_err(|_| invalid())?;
+    let seconds = match unit {
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        'w' => 7 * 24 * 60 * 60,
+        _ => return Err(invalid()),
+    };
+
+    count.checked_mul(seconds).filter(|s| *s >= 0).ok_or_else(invalid)
+}
+
+/// 当前时间的 unix 时间戳
+pub fn now() -> i64 {
+    Local::now().timestamp()
+}
+
+fn local(timestamp: i64) -> DateTime<Local> {
+    Local.timestamp_opt(timestamp, 0).single().unwrap_or_else(Local::now)
+}
+
+fn from_local(datetime: NaiveDateTime, all_day: bool) -> Result<Due, String> {
+    // 夏令时切换时本地时间可能重复或不存在，重复时取较早的一个
+    let at = Local
+        .from_local_datetime(&datetime)
+        .earliest()
+        .ok_or_else(|| format!("本地时间不存在: {}", datetime))?;
+    Ok(Due { at: at.timestamp(), all_day })
+}
+
+fn parse_time(text: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(text, "%H:%M").ok()
+}
+
+/// 解析星期几：`mon`、`monday`、`周一`、`星期一`
+pub fn parse_weekday(text: &str) -> Option<Weekday> {
+    let weekday = match text {
+        "周一" | "星期一" => Weekday::Mon,
+        "周二" | "星期二" => Weekday::Tue,
+        "周三" | "星期三" => Weekday::Wed,
+        "周四" | "星期四" => Weekday::Thu,
+        "周五" | "星期五" => Weekday::Fri,
+        "周六" | "星期六" => Weekday::Sat,
+        "周日" | "星期日" | "周天" => Weekday::Sun,
+        _ => return text.parse().ok(),
+    };
+    Some(weekday)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(y: i32, mo: u32, d: u32, h: u32, mi: u32, s: u32) -> i64 {
+        Local.with_ymd_and_hms(y, mo, d, h, mi, s).unwrap().timestamp()
+    }
+
+    #[test]
+    fn test_parse_due() {
+        // 2026-10-16 是星期五
+        let now = Local.with_ymd_and_hms(2026, 10, 16, 10, 30, 0).unwrap();
+        let parse = |text: &str| Due::parse(text, now).unwrap();
+
+        assert_eq!(parse("today"), Due { at: at(2026, 10, 16, 23, 59, 59), all_day: true });
+        assert_eq!(parse("Tomorrow"), Due { at: at(2026, 10, 17, 23, 59, 59), all_day: true });
+        assert_eq!(parse("后天 09:00"), Due { at: at(2026, 10, 18, 9, 0, 0), all_day: false });
+        assert_eq!(parse("+3d").at, at(2026, 10, 19, 23, 59, 59));
+        assert_eq!(parse("+1w 8:15").at, at(2026, 10, 23, 8, 15, 0));
+        assert_eq!(parse("+4h"), Due { at: now.timestamp() + 4 * 3600, all_day: false });
+        assert_eq!(parse("mon").date(), NaiveDate::from_ymd_opt(2026, 10, 19).unwrap());
+        assert_eq!(parse("friday").date(), NaiveDate::from_ymd_opt(2026, 10, 23).unwrap());
+        assert_eq!(parse("周日").date(), NaiveDate::from_ymd_opt(2026, 10, 18).unwrap());
+        assert_eq!(parse("2026-11-01 14:00"), Due { at: at(2026, 11, 1, 14, 0, 0), all_day: false });
+        assert_eq!(parse("2026-11-01T14:00").at, at(2026, 11, 1, 14, 0, 0));
+        assert_eq!(parse("18:00").at, at(2026, 10, 16, 18, 0, 0));
+
+        assert_eq!(parse("2026-11-01").to_string(), "2026-11-01");
+        assert_eq!(parse("2026-11-01 14:00").to_string(), "2026-11-01 14:00");
+
+        for text in ["", "someday", "2026-13-01", "tomorrow 25:00", "+3x", "+-1d", "+2h 14:00"] {
+            assert!(Due::parse(text, now).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("30m"), Ok(1800));
+        assert_eq!(parse_duration("2h"), Ok(7200));
+        assert_eq!(parse_duration("1d"), Ok(86400));
+        assert_eq!(parse_duration("2w"), Ok(14 * 86400));
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("1y").is_err());
+        assert!(parse_duration("-1d").is_err());
+    }
+}
@@ -0,0 +1,202 @@
+use chrono::{Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+use crate::due;
+use crate::todo::Todo;
+
+/// 最多保留的操作数，更早的操作无法再撤销
+const HISTORY_LIMIT: usize = 100;
+
+/// 一次操作中某个任务的前后状态，None 表示任务不存在
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Change {
+    pub id: usize,
+    pub before: Option<Todo>,
+    pub after: Option<Todo>,
+}
+
+impl Change {
+    /// (应用前应有的状态, 应用后的状态)
+    fn sides(&self, undo: bool) -> (Option<&Todo>, Option<&Todo>) {
+        if undo {
+            (self.after.as_ref(), self.before.as_ref())
+        } else {
+            (self.before.as_ref(), self.after.as_ref())
+        }
+    }
+}
+
+/// 一次可撤销的操作：撤销时把涉及的任务恢复成 before，重做时再换成 after
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Operation {
+    pub label: String,
+    /// unix 时间戳（秒）
+    pub time: i64,
+    pub changes: Vec<Change>,
+    /// 操作前后的下一个任务 ID
+    pub next_id: (usize, usize),
+}
+
+impl Operation {
+    /// 比较操作前后的任务，没有任何变化时返回 None
+    pub fn diff(
+        label: String,
+        before: &HashMap<usize, Todo>,
+        after: &HashMap<usize, Todo>,
+        next_id: (usize, usize),
+    ) -> Option<Operation> {
+        let ids: BTreeSet<usize> = before.keys().chain(after.keys()).copied().collect();
+        let changes: Vec<Change> = ids
+            .into_iter()
+            .filter(|id| before.get(id) != after.get(id))
+            .map(|id| Change { id, before: before.get(&id).cloned(), after: after.get(&id).cloned() })
+            .collect();
+
+        if changes.is_empty() && next_id.0 == next_id.1 {
+            return None;
+        }
+        Some(Operation { label, time: due::now(), changes, next_id })
+    }
+
+    /// 把任务从一侧的状态换到另一侧，undo 为 true 时从 after 换回 before
+    ///
+    /// 任务的当前状态与记录不一致（比如数据文件被手动改过）时不做任何修改并返回错误
+    pub fn apply(&self, todos: &mut HashMap<usize, Todo>, next_id: &mut usize, undo: bool) -> Result<(), String> {
+        for change in &self.changes {
+            if todos.get(&change.id) != change.sides(undo).0 {
+                return Err(format!("任务 #{} 已经在记录之外被修改过，无法{}「{}」", change.id, if undo { "撤销" } else { "重做" }, self.label));
+            }
+        }
+
+        for change in &self.changes {
+            match change.sides(undo).1 {
+                Some(todo) => todos.insert(change.id, todo.clone()),
+                None => todos.remove(&change.id),
+            };
+        }
+        *next_id = if undo { self.next_id.0 } else { self.next_id.1 };
+        Ok(())
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let time = Local.timestamp_opt(self.time, 0).single().map(|time| time.format("%Y-%m-%d %H:%M").to_string());
+        write!(f, "{}  {}", time.unwrap_or_default(), self.label)?;
+        if self.changes.len() > 1 {
+            write!(f, " ({} 个任务)", self.changes.len())?;
+        }
+        Ok(())
+    }
+}
+
+/// 操作记录，与任务数据一起保存
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct History {
+    /// 可以撤销的操作，最新的在最后
+    done: Vec<Operation>,
+    /// 撤销后可以重做的操作，最近撤销的在最后
+    undone: Vec<Operation>,
+}
+
+impl History {
+    /// 记录新的操作，之前撤销的操作不能再重做
+    pub fn push(&mut self, operation: Operation) {
+        self.done.push(operation);
+        self.undone.clear();
+        if self.done.len() > HISTORY_LIMIT {
+            self.done.drain(..self.done.len() - HISTORY_LIMIT);
+        }
+    }
+
+    /// 取出最近的操作用于撤销，成功后用 `undone` 放回
+    pub fn pop_done(&mut self) -> Option<Operation> {
+        self.done.pop()
+    }
+
+    pub fn pop_undone(&mut self) -> Option<Operation> {
+        self.undone.pop()
+    }
+
+    pub fn undone(&mut self, operation: Operation) {
+        self.undone.push(operation);
+    }
+
+    /// 重做后放回，不清空其他可以重做的操作
+    pub fn redone(&mut self, operation: Operation) {
+        self.done.push(operation);
+    }
+
+    /// 可以撤销的操作，最新的在前
+    pub fn done(&self) -> impl Iterator<Item = &Operation> {
+        self.done.iter().rev()
+    }
+
+    /// 可以重做的操作，下一个要重做的在前
+    pub fn redoable(&self) -> impl Iterator<Item = &Operation> {
+        self.undone.iter().rev()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::todo::Priority;
+
+    fn todos(items: &[(usize, &str)]) -> HashMap<usize, Todo> {
+        items.iter().map(|(id, text)| (*id, Todo::new(*id, text.to_string(), Priority::Medium))).collect()
+    }
+
+    #[test]
+    fn test_diff_and_apply() {
+        let before = todos(&[(1, "a"), (2, "b")]);
+        let mut after = before.clone();
+        after.get_mut(&1).unwrap().complete();
+        after.remove(&2);
+        after.extend(todos(&[(3, "c")]));
+
+        assert_eq!(Operation::diff("x".to_string(), &before, &before, (3, 3)), None);
+        let operation = Operation::diff("x".to_string(), &before, &after, (3, 4)).unwrap();
+        assert_eq!(operation.changes.iter().map(|change| change.id).collect::<Vec<_>>(), [1, 2, 3]);
+        assert_eq!(operation.changes[1].after, None);
+
+        let mut current = after.clone();
+        let mut next_id = 4;
+        operation.apply(&mut current, &mut next_id, true).unwrap();
+        assert_eq!((&current, next_id), (&before, 3));
+        assert!(operation.apply(&mut current, &mut next_id, true).is_err());
+        operation.apply(&mut current, &mut next_id, false).unwrap();
+        assert_eq!((&current, next_id), (&after, 4));
+
+        // 记录之外的修改会让撤销失败，且不改动任何任务
+        current.get_mut(&3).unwrap().edit_description("changed".to_string());
+        let snapshot = current.clone();
+        assert!(operation.apply(&mut current, &mut next_id, true).unwrap_err().contains("#3"));
+        assert_eq!(current, snapshot);
+    }
+
+    #[test]
+    fn test_history_limit_and_redo() {
+        let empty = HashMap::new();
+        let operation = |n: usize| Operation::diff(n.to_string(), &empty, &empty, (n, n + 1)).unwrap();
+        let mut history = History::default();
+
+        for n in 0..HISTORY_LIMIT + 5 {
+            history.push(operation(n));
+        }
+        assert_eq!(history.done().count(), HISTORY_LIMIT);
+        assert_eq!(history.done().last().unwrap().label, "5");
+
+        let last = history.pop_done().unwrap();
+        history.undone(last);
+        assert_eq!(history.redoable().count(), 1);
+        history.push(operation(999));
+        assert_eq!(history.redoable().count(), 0);
+
+        let json = serde_json::to_string(&history).unwrap();
+        let loaded: History = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.done().next(), history.done().next());
+    }
+}
@@ -1,16 +1,59 @@
 mod todo;
 mod storage;
+mod due;
+mod recurrence;
+mod tags;
+mod query;
+mod history;
+mod migrations;
+mod sqlite;
+mod config;
 
+use std::collections::BTreeSet;
 use std::env;
 use std::io;
-use todo::{Todo, TodoManager, Priority};
+use std::process;
+use config::{Backend, Config, CONFIG_FILE};
+use due::Due;
+use migrations::{NewerVersion, UpgradeFailed};
+use recurrence::Recurrence;
+use query::{Query, Selector};
+use todo::{format_ids, DueFilter, SortBy, TodoManager, Priority};
 
 fn main() {
     let args: Vec<String> = env::args().collect();
-    let mut manager = TodoManager::new();
+    let config = Config::load().unwrap_or_else(|e| {
+        eprintln!("❌ 无法读取配置文件 {}: {}", CONFIG_FILE, e);
+        process::exit(1);
+    });
+    
+    // 迁移直接读写两种存储，不加载任务
+    if args.get(1).map(String::as_str) == Some("migrate") {
+        if let Err(e) = run_migrate(&config, &args[2..]) {
+            eprintln!("❌ {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+    
+    let storage = config.open(config.storage).unwrap_or_else(|e| {
+        eprintln!("❌ 无法打开存储 {}: {}", config.path(config.storage), e);
+        process::exit(1);
+    });
+    let mut manager = TodoManager::with_storage(storage);
     
     // 尝试从文件加载数据
     if let Err(e) = manager.load_from_file() {
+        // 更新版本或无法升级的数据文件不能当作空列表处理，否则保存时会被覆盖
+        if e.is::<NewerVersion>() {
+            eprintln!("❌ {}", e);
+            process::exit(1);
+        }
+        if e.is::<UpgradeFailed>() {
+            eprintln!("❌ {}", e);
+            eprintln!("数据文件没有修改，升级前的内容已备份为 .backup.<时间戳> 文件，修复后重新运行");
+            process::exit(1);
+        }
         eprintln!("警告: 无法加载待办事项文件: {}", e);
         eprintln!("将创建新的待办事项列表");
     }
@@ -25,41 +68,136 @@ fn main() {
 fn run_command_mode(manager: &mut TodoManager, args: &[String]) {
     match args[1].as_str() {
         "add" => {
-            if args.len() < 3 {
-                eprintln!("用法: {} add <任务描述> [优先级]", args[0]);
+            let mut words = args[2..].to_vec();
+            let due = match take_option(&mut words, "--due").and_then(|text| text.map(|text| text.parse::<Due>()).transpose()) {
+                Ok(due) => due,
+                Err(e) => {
+                    eprintln!("❌ {}", e);
+                    return;
+                }
+            };
+            let recurrence = match take_option(&mut words, "--repeat").and_then(|text| text.map(|text| text.parse::<Recurrence>()).transpose()) {
+                Ok(recurrence) => recurrence,
+                Err(e) => {
+                    eprintln!("❌ {}", e);
+                    return;
+                }
+            };
+            let parent = match take_option(&mut words, "--parent").map(|id| id.map(|id| id.parse::<usize>())) {
+                Ok(None) => None,
+                Ok(Some(Ok(parent))) if manager.get_todo(parent).is_some() => Some(parent),
+                Ok(Some(_)) => {
+                    eprintln!("❌ 找不到指定的父任务");
+                    return;
+                },
+                Err(e) => {
+                    eprintln!("❌ {}", e);
+                    return;
+                }
+            };
+            
+            if words.is_empty() {
+                eprintln!("用法: {} add <任务描述> [优先级] [--due <截止时间>] [--repeat <规则>] [--parent <父任务ID>]", args[0]);
                 eprintln!("优先级: low, medium, high, urgent (默认: medium)");
                 return;
             }
             
-            let description = args[2..].join(" ");
-            let priority = if args.len() > 3 {
-                match args.last().unwrap().as_str() {
-                    "low" | "l" => Priority::Low,
-                    "medium" | "m" => Priority::Medium,
-                    "high" | "h" => Priority::High,
-                    "urgent" | "u" => Priority::Urgent,
-                    _ => Priority::Medium,
-                }
-            } else {
-                Priority::Medium
+            // 最后一个词是优先级时不计入描述
+            let priority = match words.last().and_then(|word| parse_priority(word)) {
+                Some(priority) if words.len() > 1 => {
+                    words.pop();
+                    priority
+                },
+                _ => Priority::Medium,
             };
             
-            manager.add_todo(description, priority);
+            let description = words.join(" ");
+            manager.record(format!("添加 {}", description), |manager| {
+                let id = manager.add_todo(description, priority);
+                if due.is_some() {
+                    manager.set_due(id, due);
+                }
+                if recurrence.is_some() {
+                    manager.set_recurrence(id, recurrence);
+                }
+                if parent.is_some() {
+                    // 新任务没有子任务，不会形成循环
+                    let _ = manager.set_parent(id, parent);
+                }
+            });
             println!("✅ 任务添加成功！");
         },
         "list" | "ls" => {
-            manager.list_todos();
+            let mut options = args[2..].to_vec();
+            let sort = match take_option(&mut options, "--sort").as_ref().map(|key| key.as_deref()) {
+                Ok(None | Some("priority")) => SortBy::Priority,
+                Ok(Some("due")) => SortBy::Due,
+                Ok(Some(key)) => {
+                    eprintln!("❌ 无效的排序方式: {} (可选: priority, due)", key);
+                    return;
+                },
+                Err(e) => {
+                    eprintln!("❌ {}", e);
+                    return;
+                }
+            };
+            
+            match parse_query(&options.join(" ")) {
+                Ok(query) => manager.list_todos(&query, sort),
+                Err(e) => {
+                    eprintln!("❌ {}", e);
+                    return;
+                }
+            }
         },
-        "complete" | "done" => {
-            if args.len() < 3 {
-                eprintln!("用法: {} complete <任务ID>", args[0]);
+        "complete" | "done" | "reopen" | "remove" | "rm" | "priority" | "edit" => {
+            let mut words = args[2..].to_vec();
+            let assume_yes = take_flag(&mut words, &["--yes", "-y"]);
+            let action = match parse_bulk_action(args[1].as_str(), &mut words) {
+                Ok(action) => action,
+                Err(None) => {
+                    eprintln!("用法: {} {}", args[0], bulk_usage(args[1].as_str()));
+                    eprintln!("选择: 任务ID、ID 列表和范围 (1,3,5-9) 或查询 (如 status:pending +work)");
+                    return;
+                },
+                Err(Some(e)) => {
+                    eprintln!("❌ {}", e);
+                    return;
+                }
+            };
+            
+            if let Err(e) = run_bulk(manager, &words.join(" "), action, assume_yes) {
+                eprintln!("❌ {}", e);
+                return;
+            }
+        },
+        "due" => {
+            if args.len() < 4 {
+                eprintln!("用法: {} due <任务ID> <截止时间|none>", args[0]);
+                eprintln!("截止时间: today, tomorrow, fri, +3d, +4h, 2026-11-01, 2026-11-01 14:00");
                 return;
             }
             
             match args[2].parse::<usize>() {
                 Ok(id) => {
-                    if manager.complete_todo(id) {
-                        println!("✅ 任务已完成！");
+                    let text = args[3..].join(" ");
+                    let due = if text == "none" {
+                        None
+                    } else {
+                        match text.parse::<Due>() {
+                            Ok(due) => Some(due),
+                            Err(e) => {
+                                eprintln!("❌ {}", e);
+                                return;
+                            }
+                        }
+                    };
+                    
+                    if manager.set_due(id, due) {
+                        match due {
+                            Some(due) => println!("✅ 截止时间已设为 {}", due),
+                            None => println!("✅ 截止时间已清除"),
+                        }
                     } else {
                         eprintln!("❌ 找不到指定的任务ID");
                     }
@@ -67,16 +205,32 @@ fn run_command_mode(manager: &mut TodoManager, args: &[String]) {
                 Err(_) => eprintln!("❌ 任务ID必须是数字"),
             }
         },
-        "remove" | "rm" => {
-            if args.len() < 3 {
-                eprintln!("用法: {} remove <任务ID>", args[0]);
+        "repeat" => {
+            if args.len() < 4 {
+                eprintln!("用法: {} repeat <任务ID> <规则|none>", args[0]);
+                eprintln!("规则: daily, weekly[:mon,fri], monthly:<日>, every:<天数>d");
                 return;
             }
             
             match args[2].parse::<usize>() {
                 Ok(id) => {
-                    if manager.remove_todo(id) {
-                        println!("✅ 任务已删除！");
+                    let recurrence = if args[3] == "none" {
+                        None
+                    } else {
+                        match args[3..].join("").parse::<Recurrence>() {
+                            Ok(recurrence) => Some(recurrence),
+                            Err(e) => {
+                                eprintln!("❌ {}", e);
+                                return;
+                            }
+                        }
+                    };
+                    
+                    if manager.set_recurrence(id, recurrence.clone()) {
+                        match recurrence {
+                            Some(recurrence) => println!("✅ 已设为{}重复", recurrence),
+                            None => println!("✅ 已取消重复"),
+                        }
                     } else {
                         eprintln!("❌ 找不到指定的任务ID");
                     }
@@ -84,17 +238,31 @@ fn run_command_mode(manager: &mut TodoManager, args: &[String]) {
                 Err(_) => eprintln!("❌ 任务ID必须是数字"),
             }
         },
-        "edit" => {
+        "tag" | "untag" => {
             if args.len() < 4 {
-                eprintln!("用法: {} edit <任务ID> <新描述>", args[0]);
+                eprintln!("用法: {} {} <任务ID> <标签...>", args[0], args[1]);
+                eprintln!("标签: +项目 或 @场景，如 +work @home");
                 return;
             }
             
+            let tags = match parse_tags(&args[3..]) {
+                Ok(tags) => tags,
+                Err(e) => {
+                    eprintln!("❌ {}", e);
+                    return;
+                }
+            };
+            
             match args[2].parse::<usize>() {
                 Ok(id) => {
-                    let new_description = args[3..].join(" ");
-                    if manager.edit_todo(id, new_description) {
-                        println!("✅ 任务已更新！");
+                    let found = if args[1] == "tag" {
+                        manager.add_tags(id, &tags)
+                    } else {
+                        manager.remove_tags(id, &tags)
+                    };
+                    
+                    if found {
+                        println!("✅ 标签已更新！");
                     } else {
                         eprintln!("❌ 找不到指定的任务ID");
                     }
@@ -102,76 +270,219 @@ fn run_command_mode(manager: &mut TodoManager, args: &[String]) {
                 Err(_) => eprintln!("❌ 任务ID必须是数字"),
             }
         },
-        "priority" => {
+        "parent" => {
             if args.len() < 4 {
-                eprintln!("用法: {} priority <任务ID> <优先级>", args[0]);
-                eprintln!("优先级: low, medium, high, urgent");
+                eprintln!("用法: {} parent <任务ID> <父任务ID|none>", args[0]);
                 return;
             }
             
+            let parent = match args[3].as_str() {
+                "none" => None,
+                parent => match parent.parse::<usize>() {
+                    Ok(parent) => Some(parent),
+                    Err(_) => {
+                        eprintln!("❌ 任务ID必须是数字");
+                        return;
+                    }
+                },
+            };
+            
             match args[2].parse::<usize>() {
-                Ok(id) => {
-                    let priority = match args[3].as_str() {
-                        "low" | "l" => Priority::Low,
-                        "medium" | "m" => Priority::Medium,
-                        "high" | "h" => Priority::High,
-                        "urgent" | "u" => Priority::Urgent,
-                        _ => {
-                            eprintln!("❌ 无效的优先级");
+                Ok(id) => match manager.set_parent(id, parent) {
+                    Ok(()) => println!("✅ 父任务已更新！"),
+                    Err(e) => eprintln!("❌ {}", e),
+                },
+                Err(_) => eprintln!("❌ 任务ID必须是数字"),
+            }
+        },
+        "block" | "unblock" => {
+            if args.len() < 4 {
+                eprintln!("用法: {} {} <任务ID> <依赖的任务ID...>", args[0], args[1]);
+                return;
+            }
+            
+            let ids: Result<Vec<usize>, _> = args[2..].iter().map(|id| id.parse::<usize>()).collect();
+            let Ok(ids) = ids else {
+                eprintln!("❌ 任务ID必须是数字");
+                return;
+            };
+            
+            let id = ids[0];
+            for &blocker in &ids[1..] {
+                if args[1] == "block" {
+                    match manager.add_dependency(id, blocker) {
+                        Ok(()) => println!("✅ #{} 将等待 #{} 完成", id, blocker),
+                        Err(e) => eprintln!("❌ {}", e),
+                    }
+                } else if manager.remove_dependency(id, blocker) {
+                    println!("✅ 已移除 #{} 对 #{} 的依赖", id, blocker);
+                } else {
+                    eprintln!("❌ #{} 没有依赖 #{}", id, blocker);
+                }
+            }
+        },
+        "tree" => {
+            let root = match args.get(2).map(|id| id.parse::<usize>()) {
+                None => None,
+                Some(Ok(id)) => Some(id),
+                Some(Err(_)) => {
+                    eprintln!("❌ 任务ID必须是数字");
+                    return;
+                }
+            };
+            
+            if let Err(e) = manager.print_tree(root) {
+                eprintln!("❌ {}", e);
+                return;
+            }
+        },
+        "tags" => {
+            match args.get(2).map(String::as_str) {
+                None => {
+                    let counts = manager.tag_counts();
+                    if counts.is_empty() {
+                        println!("🏷️  还没有标签");
+                    }
+                    for (tag, (total, completed)) in counts {
+                        println!("  {}: {} 个任务 ({} 个已完成)", tag, total, completed);
+                    }
+                },
+                Some(action @ ("rename" | "merge")) => {
+                    if args.len() < 5 || (action == "rename" && args.len() != 5) {
+                        eprintln!("用法: {} tags rename <旧标签> <新标签>", args[0]);
+                        eprintln!("      {} tags merge <标签...> <目标标签>", args[0]);
+                        return;
+                    }
+                    
+                    let (target, sources) = match parse_tags(&args[3..]).map(|mut tags| (tags.pop(), tags)) {
+                        Ok((Some(target), sources)) => (target, sources),
+                        Ok((None, _)) => return,
+                        Err(e) => {
+                            eprintln!("❌ {}", e);
                             return;
                         }
                     };
                     
-                    if manager.set_priority(id, priority) {
-                        println!("✅ 优先级已更新！");
-                    } else {
-                        eprintln!("❌ 找不到指定的任务ID");
-                    }
+                    let changed = manager.merge_tags(&sources, &target);
+                    println!("✅ 已将 {} 改为 {}，涉及 {} 个任务", sources.join("、"), target, changed);
                 },
-                Err(_) => eprintln!("❌ 任务ID必须是数字"),
+                Some(action) => {
+                    eprintln!("❌ 未知的标签操作: {} (可选: rename, merge)", action);
+                    return;
+                }
             }
         },
         "search" => {
             if args.len() < 3 {
-                eprintln!("用法: {} search <关键词>", args[0]);
+                eprintln!("用法: {} search <查询>", args[0]);
                 return;
             }
             
-            let keyword = args[2..].join(" ");
-            manager.search_todos(&keyword);
+            let text = args[2..].join(" ");
+            match parse_query(&text) {
+                Ok(query) => manager.search_todos(&query, &text),
+                Err(e) => {
+                    eprintln!("❌ {}", e);
+                    return;
+                }
+            }
         },
         "filter" => {
             if args.len() < 3 {
-                eprintln!("用法: {} filter <类型>", args[0]);
-                eprintln!("类型: pending, completed, priority:<level>");
+                eprintln!("用法: {} filter <类型|查询>", args[0]);
+                eprintln!("类型: pending, completed, priority:<level>, overdue, today, upcoming[:<天数>]");
+                eprintln!("其余参数按查询语法处理，如: status:pending priority>=high +work");
                 return;
             }
             
             match args[2].as_str() {
                 "pending" => manager.filter_todos_by_status(false),
                 "completed" => manager.filter_todos_by_status(true),
-                priority_filter if priority_filter.starts_with("priority:") => {
-                    let priority_str = &priority_filter[9..];
-                    let priority = match priority_str {
-                        "low" => Priority::Low,
-                        "medium" => Priority::Medium,
-                        "high" => Priority::High,
-                        "urgent" => Priority::Urgent,
+                "overdue" => manager.filter_todos_by_due(DueFilter::Overdue),
+                "today" => manager.filter_todos_by_due(DueFilter::Today),
+                upcoming_filter if upcoming_filter == "upcoming" || upcoming_filter.starts_with("upcoming:") => {
+                    let days = match upcoming_filter.strip_prefix("upcoming:").map(|days| days.parse::<i64>()) {
+                        None => 7,
+                        Some(Ok(days)) if days > 0 => days,
                         _ => {
-                            eprintln!("❌ 无效的优先级");
+                            eprintln!("❌ 天数必须是正整数");
                             return;
                         }
                     };
-                    manager.filter_todos_by_priority(priority);
+                    manager.filter_todos_by_due(DueFilter::Upcoming(days));
+                },
+                priority_filter if args.len() == 3 && priority_filter.starts_with("priority:") => {
+                    match priority_filter[9..].parse::<Priority>() {
+                        Ok(priority) => manager.filter_todos_by_priority(priority),
+                        Err(e) => {
+                            eprintln!("❌ {}", e);
+                            return;
+                        }
+                    }
+                },
+                _ => match parse_query(&args[2..].join(" ")) {
+                    Ok(query) => manager.list_todos(&query, SortBy::Priority),
+                    Err(e) => {
+                        eprintln!("❌ {}", e);
+                        return;
+                    }
                 },
-                _ => {
-                    eprintln!("❌ 无效的过滤类型");
+            }
+        },
+        "stats" => {
+            match parse_query(&args[2..].join(" ")) {
+                Ok(query) => manager.show_statistics(&query),
+                Err(e) => {
+                    eprintln!("❌ {}", e);
                     return;
                 }
             }
         },
-        "stats" => {
-            manager.show_statistics();
+        "remind" => {
+            let mut options = args[2..].to_vec();
+            let check = options.iter().any(|option| option == "--check");
+            let within = match take_option(&mut options, "--within") {
+                Ok(within) => within.unwrap_or_else(|| "1d".to_string()),
+                Err(e) => {
+                    eprintln!("❌ {}", e);
+                    return;
+                }
+            };
+            let window = match due::parse_duration(&within) {
+                Ok(window) => window,
+                Err(e) => {
+                    eprintln!("❌ {}", e);
+                    return;
+                }
+            };
+            
+            // 没有到期的任务时不输出，方便放在 cron 里
+            let due_todos = manager.due_within(due::now(), window);
+            if check {
+                if !due_todos.is_empty() {
+                    process::exit(1);
+                }
+            } else if !due_todos.is_empty() {
+                println!("⏰ {} 个任务已逾期或将在 {} 内到期:", due_todos.len(), within);
+                for todo in due_todos {
+                    println!("{}", todo);
+                }
+            }
+        },
+        "undo" | "redo" => {
+            // 撤销多次时中途出错也要保存已经撤销的部分
+            if let Err(e) = run_undo(manager, args[1] == "redo", args.get(2).map(String::as_str)) {
+                eprintln!("❌ {}", e);
+            }
+        },
+        "log" => {
+            match args.get(2).map_or(Ok(10), |limit| limit.parse::<usize>()) {
+                Ok(limit) => manager.show_history(limit),
+                Err(_) => {
+                    eprintln!("❌ 条数必须是数字");
+                    return;
+                }
+            }
         },
         "clear" => {
             print!("确定要清空所有待办事项吗? (y/N): ");
@@ -242,35 +553,53 @@ fn run_interactive_mode(manager: &mut TodoManager) {
                         println!("✅ 任务添加成功！");
                     },
                     "list" | "ls" => {
-                        manager.list_todos();
-                    },
-                    "complete" | "done" => {
-                        if parts.len() < 2 {
-                            println!("用法: complete <任务ID>");
-                            continue;
+                        match parse_query(&parts[1..].join(" ")) {
+                            Ok(query) => manager.list_todos(&query, SortBy::Priority),
+                            Err(e) => println!("❌ {}", e),
                         }
-                        
-                        match parts[1].parse::<usize>() {
-                            Ok(id) => {
-                                if manager.complete_todo(id) {
-                                    println!("✅ 任务已完成！");
-                                } else {
-                                    println!("❌ 找不到指定的任务ID");
-                                }
+                    },
+                    "complete" | "done" | "reopen" | "remove" | "rm" | "priority" | "edit" => {
+                        let mut words: Vec<String> = parts[1..].iter().map(|word| word.to_string()).collect();
+                        let assume_yes = take_flag(&mut words, &["--yes", "-y"]);
+                        let action = match parse_bulk_action(parts[0], &mut words) {
+                            Ok(action) => action,
+                            Err(None) => {
+                                println!("用法: {}", bulk_usage(parts[0]));
+                                continue;
                             },
-                            Err(_) => println!("❌ 任务ID必须是数字"),
+                            Err(Some(e)) => {
+                                println!("❌ {}", e);
+                                continue;
+                            }
+                        };
+                        
+                        if let Err(e) = run_bulk(manager, &words.join(" "), action, assume_yes) {
+                            println!("❌ {}", e);
                         }
                     },
-                    "remove" | "rm" => {
-                        if parts.len() < 2 {
-                            println!("用法: remove <任务ID>");
+                    "due" => {
+                        if parts.len() < 3 {
+                            println!("用法: due <任务ID> <截止时间|none>");
                             continue;
                         }
                         
                         match parts[1].parse::<usize>() {
                             Ok(id) => {
-                                if manager.remove_todo(id) {
-                                    println!("✅ 任务已删除！");
+                                let text = parts[2..].join(" ");
+                                let due = if text == "none" {
+                                    None
+                                } else {
+                                    match text.parse::<Due>() {
+                                        Ok(due) => Some(due),
+                                        Err(e) => {
+                                            println!("❌ {}", e);
+                                            continue;
+                                        }
+                                    }
+                                };
+                                
+                                if manager.set_due(id, due) {
+                                    println!("✅ 截止时间已更新！");
                                 } else {
                                     println!("❌ 找不到指定的任务ID");
                                 }
@@ -278,17 +607,28 @@ fn run_interactive_mode(manager: &mut TodoManager) {
                             Err(_) => println!("❌ 任务ID必须是数字"),
                         }
                     },
-                    "edit" => {
+                    "repeat" => {
                         if parts.len() < 3 {
-                            println!("用法: edit <任务ID> <新描述>");
+                            println!("用法: repeat <任务ID> <规则|none>");
                             continue;
                         }
                         
                         match parts[1].parse::<usize>() {
                             Ok(id) => {
-                                let new_description = parts[2..].join(" ");
-                                if manager.edit_todo(id, new_description) {
-                                    println!("✅ 任务已更新！");
+                                let recurrence = if parts[2] == "none" {
+                                    None
+                                } else {
+                                    match parts[2..].join("").parse::<Recurrence>() {
+                                        Ok(recurrence) => Some(recurrence),
+                                        Err(e) => {
+                                            println!("❌ {}", e);
+                                            continue;
+                                        }
+                                    }
+                                };
+                                
+                                if manager.set_recurrence(id, recurrence) {
+                                    println!("✅ 重复规则已更新！");
                                 } else {
                                     println!("❌ 找不到指定的任务ID");
                                 }
@@ -296,28 +636,30 @@ fn run_interactive_mode(manager: &mut TodoManager) {
                             Err(_) => println!("❌ 任务ID必须是数字"),
                         }
                     },
-                    "priority" => {
+                    "tag" | "untag" => {
                         if parts.len() < 3 {
-                            println!("用法: priority <任务ID> <优先级>");
-                            println!("优先级: low, medium, high, urgent");
+                            println!("用法: {} <任务ID> <标签...>", parts[0]);
                             continue;
                         }
                         
+                        let tags = match parse_tags(&parts[2..]) {
+                            Ok(tags) => tags,
+                            Err(e) => {
+                                println!("❌ {}", e);
+                                continue;
+                            }
+                        };
+                        
                         match parts[1].parse::<usize>() {
                             Ok(id) => {
-                                let priority = match parts[2] {
-                                    "low" | "l" => Priority::Low,
-                                    "medium" | "m" => Priority::Medium,
-                                    "high" | "h" => Priority::High,
-                                    "urgent" | "u" => Priority::Urgent,
-                                    _ => {
-                                        println!("❌ 无效的优先级");
-                                        continue;
-                                    }
+                                let found = if parts[0] == "tag" {
+                                    manager.add_tags(id, &tags)
+                                } else {
+                                    manager.remove_tags(id, &tags)
                                 };
                                 
-                                if manager.set_priority(id, priority) {
-                                    println!("✅ 优先级已更新！");
+                                if found {
+                                    println!("✅ 标签已更新！");
                                 } else {
                                     println!("❌ 找不到指定的任务ID");
                                 }
@@ -327,15 +669,32 @@ fn run_interactive_mode(manager: &mut TodoManager) {
                     },
                     "search" => {
                         if parts.len() < 2 {
-                            println!("用法: search <关键词>");
+                            println!("用法: search <查询>");
                             continue;
                         }
                         
-                        let keyword = parts[1..].join(" ");
-                        manager.search_todos(&keyword);
+                        let text = parts[1..].join(" ");
+                        match parse_query(&text) {
+                            Ok(query) => manager.search_todos(&query, &text),
+                            Err(e) => println!("❌ {}", e),
+                        }
                     },
                     "stats" => {
-                        manager.show_statistics();
+                        match parse_query(&parts[1..].join(" ")) {
+                            Ok(query) => manager.show_statistics(&query),
+                            Err(e) => println!("❌ {}", e),
+                        }
+                    },
+                    "undo" | "redo" => {
+                        if let Err(e) = run_undo(manager, parts[0] == "redo", parts.get(1).copied()) {
+                            println!("❌ {}", e);
+                        }
+                    },
+                    "log" => {
+                        match parts.get(1).map_or(Ok(10), |limit| limit.parse::<usize>()) {
+                            Ok(limit) => manager.show_history(limit),
+                            Err(_) => println!("❌ 条数必须是数字"),
+                        }
                     },
                     "clear" => {
                         print!("确定要清空所有待办事项吗? (y/N): ");
@@ -372,31 +731,333 @@ fn run_interactive_mode(manager: &mut TodoManager) {
     }
 }
 
+/// 可以一次作用于多个任务的操作
+enum BulkAction {
+    Complete { force: bool },
+    Reopen,
+    Remove,
+    Priority(Priority),
+    Edit(String),
+    /// 把描述中的旧文本替换为新文本
+    Replace(String, String),
+}
+
+fn bulk_usage(command: &str) -> &'static str {
+    match command {
+        "complete" | "done" => "complete <选择> [--force] [--yes]",
+        "reopen" => "reopen <选择> [--yes]",
+        "remove" | "rm" => "remove <选择> [--yes]",
+        "priority" => "priority <选择> <优先级> [--yes]",
+        _ => "edit <任务ID> <新描述> 或 edit <选择> --replace <旧文本> <新文本> [--yes]",
+    }
+}
+
+/// 解析批量操作的参数，words 中只留下选择任务的部分；Err(None) 表示参数不全
+fn parse_bulk_action(command: &str, words: &mut Vec<String>) -> Result<BulkAction, Option<String>> {
+    let action = match command {
+        "complete" | "done" => BulkAction::Complete { force: take_flag(words, &["--force", "-f"]) },
+        "reopen" => BulkAction::Reopen,
+        "remove" | "rm" => BulkAction::Remove,
+        "priority" => {
+            if words.len() < 2 {
+                return Err(None);
+            }
+            let level = words.pop().unwrap_or_default();
+            BulkAction::Priority(level.parse().map_err(Some)?)
+        },
+        _ => match words.iter().position(|word| word == "--replace") {
+            Some(index) if index > 0 && index + 3 == words.len() => {
+                let new = words.pop().unwrap_or_default();
+                let old = words.pop().unwrap_or_default();
+                words.pop();
+                if old.is_empty() {
+                    return Err(Some("要替换的文本不能为空".to_string()));
+                }
+                BulkAction::Replace(old, new)
+            },
+            Some(_) => return Err(None),
+            None if words.len() >= 2 => BulkAction::Edit(words.split_off(1).join(" ")),
+            None => return Err(None),
+        },
+    };
+    
+    if words.is_empty() {
+        return Err(None);
+    }
+    Ok(action)
+}
+
+/// 对选中的任务执行操作；选中的不止一个 ID 时先列出任务并确认，assume_yes 跳过确认
+fn run_bulk(manager: &mut TodoManager, selection: &str, action: BulkAction, assume_yes: bool) -> Result<(), String> {
+    let selector: Selector = selection.parse()?;
+    let missing = selector.missing_ids(manager);
+    if selector.single_id().is_some() && !missing.is_empty() {
+        return Err("找不到指定的任务ID".to_string());
+    }
+    if !missing.is_empty() {
+        println!("⚠️  找不到任务: {}", format_ids(&missing));
+    }
+    
+    let ids = manager.select(&selector);
+    if ids.is_empty() {
+        return Err("没有符合条件的任务".to_string());
+    }
+    if matches!(action, BulkAction::Edit(_)) && ids.len() > 1 {
+        return Err(format!("选中了 {} 个任务，批量编辑请使用 --replace <旧文本> <新文本>", ids.len()));
+    }
+    
+    let operation = match &action {
+        BulkAction::Complete { force: true } => "强制完成".to_string(),
+        BulkAction::Complete { force: false } => "完成".to_string(),
+        BulkAction::Reopen => "重新打开".to_string(),
+        BulkAction::Remove => "删除".to_string(),
+        BulkAction::Priority(priority) => format!("设置优先级为 {}", priority),
+        BulkAction::Edit(_) => "修改描述".to_string(),
+        BulkAction::Replace(old, new) => format!("把描述中的 \"{}\" 替换为 \"{}\"", old, new),
+    };
+    
+    if selector.single_id().is_none() && !assume_yes {
+        println!("操作: {}", operation);
+        println!("涉及以下 {} 个任务:", ids.len());
+        for todo in ids.iter().filter_map(|id| manager.get_todo(*id)) {
+            println!("{}", todo);
+        }
+        
+        print!("确定要继续吗? (y/N): ");
+        io::Write::flush(&mut io::stdout()).unwrap();
+        let mut input = String::new();
+        io::stdin().read_line(&mut input).map_err(|e| e.to_string())?;
+        if !input.trim().to_lowercase().starts_with('y') {
+            println!("操作已取消");
+            return Ok(());
+        }
+    }
+    
+    let single = ids.len() == 1;
+    // 整个批量操作记录为一次，可以一次撤销
+    manager.record(format!("{}: {}", operation, selection), |manager| {
+        match action {
+            BulkAction::Complete { force } => {
+                let mut completed = 0;
+                for (id, result) in manager.complete_many(&ids, force) {
+                    match result {
+                        Ok(next) => {
+                            completed += 1;
+                            if let Some(todo) = next.and_then(|next| manager.get_todo(next)) {
+                                println!("🔁 下一次: {}", todo);
+                            }
+                        },
+                        Err(e) if single => return Err(e),
+                        Err(e) => println!("⚠️  #{} 未完成: {}", id, e),
+                    }
+                }
+                if single {
+                    println!("✅ 任务已完成！");
+                } else {
+                    println!("✅ 已完成 {} 个任务", completed);
+                }
+            },
+            BulkAction::Reopen => {
+                for id in &ids {
+                    manager.reopen_todo(*id);
+                }
+                if single {
+                    println!("✅ 任务已重新打开！");
+                } else {
+                    println!("✅ 已重新打开 {} 个任务", ids.len());
+                }
+            },
+            BulkAction::Remove => {
+                for id in &ids {
+                    manager.remove_todo(*id);
+                }
+                if single {
+                    println!("✅ 任务已删除！");
+                } else {
+                    println!("✅ 已删除 {} 个任务", ids.len());
+                }
+            },
+            BulkAction::Priority(priority) => {
+                for id in &ids {
+                    manager.set_priority(*id, priority.clone());
+                }
+                if single {
+                    println!("✅ 优先级已更新！");
+                } else {
+                    println!("✅ 已更新 {} 个任务的优先级", ids.len());
+                }
+            },
+            BulkAction::Edit(description) => {
+                manager.edit_todo(ids[0], description);
+                println!("✅ 任务已更新！");
+            },
+            BulkAction::Replace(old, new) => {
+                let mut changed = 0;
+                for id in &ids {
+                    let description = match manager.get_todo(*id) {
+                        Some(todo) if todo.description.contains(&old) => todo.description.replace(&old, &new),
+                        _ => continue,
+                    };
+                    manager.edit_todo(*id, description);
+                    changed += 1;
+                }
+                if changed == 0 {
+                    return Err(format!("选中的任务描述中都没有 \"{}\"", old));
+                }
+                println!("✅ 已更新 {} 个任务", changed);
+            },
+        }
+        
+        Ok(())
+    })
+}
+
+/// 把一种存储中的任务、下一个任务 ID 和操作记录复制到另一种存储
+///
+/// 目标中已经有任务时需要 --force，目标中原有的任务会被全部替换
+fn run_migrate(config: &Config, args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = args.to_vec();
+    let force = take_flag(&mut args, &["--force"]);
+    let usage = "用法: migrate --from <json|sqlite> --to <json|sqlite> [--force]";
+    let from: Backend = take_option(&mut args, "--from")?.ok_or(usage)?.parse()?;
+    let to: Backend = take_option(&mut args, "--to")?.ok_or(usage)?.parse()?;
+    if !args.is_empty() {
+        return Err(usage.into());
+    }
+    if from == to {
+        return Err("来源和目标是同一种存储".into());
+    }
+    
+    let data = config.open(from)?.load_todos()?;
+    let target = config.open(to)?;
+    // 目标还不存在时从空数据开始
+    let existing = target.load_todos().unwrap_or_default();
+    if !existing.todos.is_empty() && !force {
+        return Err(format!("{} 中已经有 {} 个任务，使用 --force 替换", config.path(to), existing.todos.len()).into());
+    }
+    
+    let changed: BTreeSet<usize> = existing.todos.iter().chain(&data.todos).map(|todo| todo.id).collect();
+    target.save_todos(&data, &changed)?;
+    
+    println!("✅ 已把 {} 个任务从 {} ({}) 迁移到 {} ({})", data.todos.len(), from, config.path(from), to, config.path(to));
+    if config.storage != to {
+        println!("💡 在 {} 中设置 {{\"storage\": \"{}\"}} 以使用新的存储", CONFIG_FILE, to);
+    }
+    Ok(())
+}
+
+/// 撤销或重做最近的 count 次操作（默认 1 次），遇到无法撤销的操作时停下
+fn run_undo(manager: &mut TodoManager, redo: bool, count: Option<&str>) -> Result<(), String> {
+    let count = match count.map(|count| count.parse::<usize>()) {
+        None => 1,
+        Some(Ok(count)) if count > 0 => count,
+        _ => return Err("次数必须是正整数".to_string()),
+    };
+    
+    for _ in 0..count {
+        if redo {
+            println!("↪️  已重做: {}", manager.redo()?);
+        } else {
+            println!("↩️  已撤销: {}", manager.undo()?);
+        }
+    }
+    Ok(())
+}
+
+/// 从参数中去掉开关选项，返回是否出现过
+fn take_flag(args: &mut Vec<String>, names: &[&str]) -> bool {
+    let before = args.len();
+    args.retain(|arg| !names.contains(&arg.as_str()));
+    args.len() != before
+}
+
+fn parse_priority(text: &str) -> Option<Priority> {
+    text.parse().ok()
+}
+
+/// 解析查询，出错时返回带位置标记的错误信息
+fn parse_query(text: &str) -> Result<Query, String> {
+    text.parse::<Query>().map_err(|e| e.render(text))
+}
+
+fn parse_tags<S: AsRef<str>>(words: &[S]) -> Result<Vec<String>, String> {
+    words.iter().map(|word| tags::parse_tag(word.as_ref())).collect()
+}
+
+/// 从参数中取出 `<名称> <值>` 形式的选项
+fn take_option(args: &mut Vec<String>, name: &str) -> Result<Option<String>, String> {
+    match args.iter().position(|arg| arg == name) {
+        None => Ok(None),
+        Some(index) if index + 1 < args.len() => {
+            let value = args.remove(index + 1);
+            args.remove(index);
+            Ok(Some(value))
+        },
+        Some(_) => Err(format!("{} 需要一个值", name)),
+    }
+}
+
 fn show_help(program: &str) {
     println!("📝 待办事项管理器 v0.1.0");
     println!();
     println!("用法: {} [命令] [参数...]", program);
     println!();
     println!("命令:");
-    println!("  add <任务> [优先级]         添加新任务");
-    println!("  list                        列出所有任务");
-    println!("  complete <ID>               标记任务为已完成");
-    println!("  remove <ID>                 删除任务");
-    println!("  edit <ID> <新描述>          编辑任务描述");
-    println!("  priority <ID> <级别>        设置任务优先级");
-    println!("  search <关键词>             搜索任务");
-    println!("  filter <类型>               过滤任务");
-    println!("  stats                       显示统计信息");
+    println!("  add <任务> [优先级]         添加新任务，--due <时间> 截止时间，--repeat <规则> 重复");
+    println!("  list [查询] [--sort due]    列出任务，可按查询过滤、按截止时间排序");
+    println!("  complete <选择> [--force]   标记任务为已完成，--force 忽略依赖并完成子任务");
+    println!("  reopen <选择>               重新打开已完成的任务");
+    println!("  remove <选择>               删除任务");
+    println!("  edit <ID> <新描述>          编辑任务描述，--replace <旧> <新> 批量替换");
+    println!("  priority <选择> <级别>      设置任务优先级");
+    println!("  due <ID> <时间|none>        设置或清除截止时间");
+    println!("  repeat <ID> <规则|none>     设置或取消重复，完成时生成下一次");
+    println!("  parent <ID> <父ID|none>     设置父任务，add 时也可以用 --parent <父ID>");
+    println!("  block <ID> <依赖ID...>      任务要等依赖完成后才能完成，unblock 移除");
+    println!("  tree [ID]                   树状显示任务和子任务的层级与进度");
+    println!("  tag <ID> <标签...>          添加标签 (+项目、@场景)，untag 移除");
+    println!("  tags                        列出所有标签及任务数");
+    println!("  tags rename <旧> <新>       重命名标签");
+    println!("  tags merge <标签...> <目标> 把多个标签合并为一个");
+    println!("  search <查询>               搜索任务");
+    println!("  filter <类型|查询>          过滤任务");
+    println!("  stats [查询]                显示统计信息，可只统计符合查询的任务");
+    println!("  remind [--within <时长>]    列出已逾期或即将到期的任务 (默认 1d)");
+    println!("                              --check 不输出，有到期任务时退出码为 1");
+    println!("  undo [次数]                 撤销最近的操作，redo [次数] 重做");
+    println!("  log [条数]                  查看操作记录 (默认 10 条)");
+    println!("  migrate --from <存储> --to <存储> [--force]");
+    println!("                              在 json 和 sqlite 存储之间迁移全部数据");
     println!("  clear                       清空所有任务");
     println!("  help                        显示此帮助");
     println!();
     println!("优先级: low, medium, high, urgent");
-    println!("过滤类型: pending, completed, priority:<level>");
+    println!("过滤类型: pending, completed, priority:<level>, overdue, today, upcoming[:<天数>]");
+    println!("查询: 空格分隔的条件都要满足，OR 或 | 满足其一，-/NOT 取反，( ) 分组");
+    println!("  词 \"短语\" +项目 @场景 status:<pending/completed/blocked/overdue> priority>=high");
+    println!("  created>7d completed<today due<=fri id:10-20 parent:<ID|none> has:<due/repeat/tags/children>");
+    println!("截止时间: today, tomorrow, fri, +3d, +4h, 2026-11-01, 2026-11-01 14:00");
+    println!("时长: 30m, 4h, 1d, 2w");
+    println!("重复规则: daily, weekly[:mon,fri], monthly:<日>, every:<天数>d (完成后若干天)");
+    println!("存储: 由 {} 中的 storage (json/sqlite) 和 path 选择，环境变量 TODO_STORAGE 可临时切换", CONFIG_FILE);
+    println!("选择: 任务ID、ID 列表和范围 (1,3,5-9) 或查询；选中多个任务时会先确认，--yes 跳过");
     println!();
     println!("示例:");
     println!("  {} add \"学习Rust\" high", program);
     println!("  {} complete 1", program);
     println!("  {} filter priority:urgent", program);
+    println!("  {} add \"交报告\" urgent --due \"fri 17:00\"", program);
+    println!("  {} remind --within 2h --check", program);
+    println!("  {} add \"周报\" --due fri --repeat weekly:fri", program);
+    println!("  {} add \"给妈妈打电话 +家庭 @phone\"", program);
+    println!("  {} filter +work|+home -@phone", program);
+    println!("  {} list status:pending priority>=high created<7d", program);
+    println!("  {} search '\"exact phrase\" -draft id:10-20'", program);
+    println!("  {} add \"买机票\" --parent 3", program);
+    println!("  {} block 5 4", program);
+    println!("  {} complete 1,3,5-9", program);
+    println!("  {} priority \"+work status:pending\" high --yes", program);
+    println!("  {} migrate --from json --to sqlite", program);
     println!();
     println!("不带参数运行进入交互模式");
 }
@@ -407,12 +1068,18 @@ fn show_interactive_help() {
     println!("{}", "=".repeat(40));
     println!("  add <任务> [优先级]     添加新任务");
     println!("  list, ls               列出所有任务");
-    println!("  complete <ID>, done    标记任务完成");
-    println!("  remove <ID>, rm        删除任务");
+    println!("  complete <选择>, done  标记任务完成 (--force 强制)");
+    println!("  reopen <选择>          重新打开任务");
+    println!("  remove <选择>, rm      删除任务");
     println!("  edit <ID> <新描述>     编辑任务");
-    println!("  priority <ID> <级别>   设置优先级");
-    println!("  search <关键词>        搜索任务");
+    println!("  priority <选择> <级别> 设置优先级");
+    println!("  due <ID> <时间|none>   设置截止时间");
+    println!("  repeat <ID> <规则>     设置重复规则");
+    println!("  tag/untag <ID> <标签>  添加或移除标签");
+    println!("  search <查询>          搜索任务 (list/stats 也可带查询)");
     println!("  stats                  显示统计");
+    println!("  undo / redo [次数]     撤销或重做操作");
+    println!("  log [条数]             查看操作记录");
     println!("  clear                  清空所有任务");
     println!("  help, h                显示此帮助");
     println!("  quit, exit, q          退出程序");
@@ -0,0 +1,128 @@
+use serde_json::{json, Map, Value};
+use std::error::Error;
+use std::fmt;
+
+use crate::tags;
+
+/// 当前的数据文件版本，保存在文件的 `version` 字段中
+pub const CURRENT_VERSION: u64 = 1;
+
+/// 把文档从某个版本升级到下一个版本
+type Migration = fn(&mut Map<String, Value>) -> Result<(), String>;
+
+/// 数据文件的升级步骤，第 n 个把版本 n 的文档升级到 n + 1
+///
+/// 已经发布的步骤不能再修改，`Todo` 的格式变化时在末尾追加新的步骤并增加 CURRENT_VERSION
+const MIGRATIONS: [Migration; CURRENT_VERSION as usize] = [upgrade_unversioned];
+
+/// 数据文件来自更新版本的程序，既不能读取，也不能当作空列表覆盖
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewerVersion(pub u64);
+
+impl fmt::Display for NewerVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "数据文件版本 {} 比程序支持的版本 {} 新，请升级程序", self.0, CURRENT_VERSION)
+    }
+}
+
+impl Error for NewerVersion {}
+
+/// 数据文件无法升级到当前版本；原文件没有被修改，程序不能把它当作空列表继续，否则保存时会覆盖它
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpgradeFailed(pub String);
+
+impl fmt::Display for UpgradeFailed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for UpgradeFailed {}
+
+/// 文档的版本，没有 `version` 字段的是加入版本号之前的文件，记为 0
+pub fn version(document: &Value) -> Result<u64, String> {
+    let object = document.as_object().ok_or("数据文件格式无效: 不是 JSON 对象")?;
+    match object.get("version") {
+        None => Ok(0),
+        Some(version) => version.as_u64().ok_or_else(|| format!("数据文件版本无效: {}", version)),
+    }
+}
+
+/// 依次执行升级步骤，把文档升级到当前版本
+pub fn upgrade(document: &mut Value) -> Result<(), Box<dyn Error>> {
+    let version = version(document).map_err(UpgradeFailed)?;
+    if version > CURRENT_VERSION {
+        return Err(NewerVersion(version).into());
+    }
+
+    let object = document.as_object_mut().ok_or_else(|| UpgradeFailed("数据文件格式无效: 不是 JSON 对象".to_string()))?;
+    for (index, step) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        step(object).map_err(|e| UpgradeFailed(format!("数据文件从版本 {} 升级失败: {}", index, e)))?;
+        object.insert("version".to_string(), json!(index as u64 + 1));
+    }
+    Ok(())
+}
+
+/// 0 → 1：加入版本号之前的文件
+///
+/// 标签功能之前的任务没有 `tags` 字段，描述中的 `+项目`/`@场景` 还是普通文字，这里把它们取出为标签，
+/// 与新添加的任务一致；之后陆续加入的截止时间、重复规则、父任务和依赖字段补上空值
+fn upgrade_unversioned(document: &mut Map<String, Value>) -> Result<(), String> {
+    let todos = document
+        .get_mut("todos")
+        .and_then(Value::as_array_mut)
+        .ok_or("缺少 todos 列表")?;
+
+    for todo in todos {
+        let todo = todo.as_object_mut().ok_or("任务不是 JSON 对象")?;
+        if !todo.contains_key("tags") {
+            let description = todo.get("description").and_then(Value::as_str).ok_or("任务缺少描述")?;
+            let (description, tags) = tags::extract_tags(description);
+            todo.insert("description".to_string(), json!(description));
+            todo.insert("tags".to_string(), json!(tags));
+        }
+        for (key, empty) in [("due", json!(null)), ("recurrence", json!(null)), ("parent", json!(null)), ("blocked_by", json!([]))] {
+            todo.entry(key).or_insert(empty);
+        }
+    }
+
+    if !document.contains_key("next_id") {
+        return Err("缺少 next_id".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_and_upgrade() {
+        assert_eq!(version(&json!({"todos": [], "next_id": 1})), Ok(0));
+        assert_eq!(version(&json!({"version": 1})), Ok(1));
+        assert!(version(&json!({"version": "1"})).is_err());
+        assert!(version(&json!([])).is_err());
+
+        let mut document = json!({
+            "todos": [{"id": 1, "description": "买牛奶 +家庭 @超市", "completed": false, "priority": "Low",
+                       "created_at": "1640000000", "completed_at": null}],
+            "next_id": 2
+        });
+        upgrade(&mut document).unwrap();
+        assert_eq!(document["version"], json!(CURRENT_VERSION));
+        assert_eq!(document["todos"][0]["description"], json!("买牛奶"));
+        assert_eq!(document["todos"][0]["tags"], json!(["+家庭", "@超市"]));
+        assert_eq!(document["todos"][0]["blocked_by"], json!([]));
+
+        // 已经是当前版本时不做修改
+        let upgraded = document.clone();
+        upgrade(&mut document).unwrap();
+        assert_eq!(document, upgraded);
+
+        let error = upgrade(&mut json!({"version": CURRENT_VERSION + 1})).unwrap_err();
+        assert!(error.is::<NewerVersion>());
+        let error = upgrade(&mut json!({"next_id": 1})).unwrap_err();
+        assert!(error.is::<UpgradeFailed>());
+        assert!(error.to_string().contains("todos"));
+    }
+}
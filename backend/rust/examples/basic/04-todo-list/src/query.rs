@@ -0,0 +1,710 @@
+use chrono::{DateTime, Duration, Local, NaiveDate, TimeZone};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::due::{self, Due};
+use crate::tags;
+use crate::todo::{Priority, Todo, TodoManager};
+
+const FIELDS: &str = "status, priority, created, completed, due, id, parent, has";
+
+/// 解析后的查询条件
+///
+/// 空白分隔的条件同时满足，`OR` 或 `|` 满足其一（比空白结合得更紧），`-` 或 `NOT` 取反，括号分组。条件包括：
+/// 普通词（不区分大小写匹配描述）、`"短语"`（原样匹配）、`+项目`/`@场景` 标签，
+/// 以及 `字段:值`、`字段>=值` 形式的比较，如 `status:pending priority>=high created>7d id:10-20`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    expr: Expr,
+    /// 解析时的当前时间，相对时间和逾期都以它为准
+    now: i64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Expr {
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Not(Box<Expr>),
+    /// 小写后的词，不区分大小写
+    Text(String),
+    Phrase(String),
+    Tag(String),
+    Status(Status),
+    Priority(Cmp, Priority),
+    /// 时间字段与 [开始, 结束) 区间比较
+    Time(TimeField, Cmp, i64, i64),
+    /// 闭区间
+    Id(usize, usize),
+    Parent(Option<usize>),
+    Has(Attribute),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Cmp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Status {
+    Pending,
+    Completed,
+    Blocked,
+    Overdue,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum TimeField {
+    Created,
+    Completed,
+    Due,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Attribute {
+    Due,
+    Repeat,
+    Tags,
+    Parent,
+    Children,
+    Blockers,
+}
+
+/// 查询的语法错误，start..end 是出错位置（按字符计）
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl ParseError {
+    fn new(message: impl Into<String>, start: usize, end: usize) -> Self {
+        ParseError { message: message.into(), start, end: end.max(start + 1) }
+    }
+
+    /// 错误信息加上原查询和指向出错位置的标记，用于直接输出给用户
+    pub fn render(&self, input: &str) -> String {
+        let width = |text: &str| text.chars().map(|c| if c as u32 >= 0x2E80 { 2 } else { 1 }).sum::<usize>();
+        let prefix: String = input.chars().take(self.start).collect();
+        let marked: String = input.chars().skip(self.start).take(self.end - self.start).collect();
+        format!(
+            "{}\n  {}\n  {}{}",
+            self,
+            input,
+            " ".repeat(width(&prefix)),
+            "^".repeat(width(&marked).max(1))
+        )
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "查询语法错误: {}", self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl Query {
+    pub fn parse(text: &str, now: DateTime<Local>) -> Result<Query, ParseError> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser { tokens, position: 0, now, length: text.chars().count() };
+        let expr = parser.parse_and()?;
+
+        if let Some(token) = parser.tokens.get(parser.position) {
+            return Err(ParseError::new("多余的右括号", token.start, token.end));
+        }
+
+        Ok(Query { expr, now: now.timestamp() })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.expr == Expr::And(Vec::new())
+    }
+
+    /// 解析后的条件，供存储翻译成自己的查询
+    pub(crate) fn expr(&self) -> &Expr {
+        &self.expr
+    }
+
+    /// 解析时的当前时间（unix 时间戳）
+    pub(crate) fn now(&self) -> i64 {
+        self.now
+    }
+
+    pub fn matches(&self, todo: &Todo, manager: &TodoManager) -> bool {
+        self.eval(&self.expr, todo, manager)
+    }
+
+    fn eval(&self, expr: &Expr, todo: &Todo, manager: &TodoManager) -> bool {
+        match expr {
+            Expr::And(exprs) => exprs.iter().all(|expr| self.eval(expr, todo, manager)),
+            Expr::Or(exprs) => exprs.iter().any(|expr| self.eval(expr, todo, manager)),
+            Expr::Not(expr) => !self.eval(expr, todo, manager),
+            Expr::Text(word) => todo.description.to_lowercase().contains(word),
+            Expr::Phrase(phrase) => todo.description.contains(phrase),
+            Expr::Tag(tag) => todo.tags.contains(tag),
+            Expr::Status(Status::Pending) => !todo.completed,
+            Expr::Status(Status::Completed) => todo.completed,
+            Expr::Status(Status::Blocked) => !todo.completed && !manager.open_blockers(todo).is_empty(),
+            Expr::Status(Status::Overdue) => todo.is_overdue(self.now),
+            Expr::Priority(cmp, priority) => compare(*cmp, todo.priority.cmp(priority)),
+            Expr::Time(field, cmp, start, end) => {
+                let time = match field {
+                    TimeField::Created => todo.created_at.parse().ok(),
+                    TimeField::Completed => todo.completed_at.as_ref().and_then(|time| time.parse().ok()),
+                    TimeField::Due => todo.due.map(|due| due.at),
+                };
+                time.is_some_and(|time: i64| match cmp {
+                    Cmp::Eq => (*start..*end).contains(&time),
+                    Cmp::Ne => !(*start..*end).contains(&time),
+                    Cmp::Lt => time < *start,
+                    Cmp::Le => time < *end,
+                    Cmp::Gt => time >= *end,
+                    Cmp::Ge => time >= *start,
+                })
+            },
+            Expr::Id(low, high) => (*low..=*high).contains(&todo.id),
+            Expr::Parent(parent) => todo.parent == *parent,
+            Expr::Has(Attribute::Due) => todo.due.is_some(),
+            Expr::Has(Attribute::Repeat) => todo.recurrence.is_some(),
+            Expr::Has(Attribute::Tags) => !todo.tags.is_empty(),
+            Expr::Has(Attribute::Parent) => todo.parent.is_some(),
+            Expr::Has(Attribute::Children) => !manager.children(todo.id).is_empty(),
+            Expr::Has(Attribute::Blockers) => !todo.blocked_by.is_empty(),
+        }
+    }
+}
+
+impl FromStr for Query {
+    type Err = ParseError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Query::parse(text, Local::now())
+    }
+}
+
+/// 批量操作选择任务的方式：ID 列表和范围（`1,3,5-9`），或者一个查询
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selector {
+    /// 闭区间，单个 ID 的上下限相同
+    Ids(Vec<(usize, usize)>),
+    Query(Query),
+}
+
+impl Selector {
+    /// 只选中了一个 ID
+    pub fn single_id(&self) -> Option<usize> {
+        match self {
+            Selector::Ids(ranges) if ranges.len() == 1 && ranges[0].0 == ranges[0].1 => Some(ranges[0].0),
+            _ => None,
+        }
+    }
+
+    pub fn matches(&self, todo: &Todo, manager: &TodoManager) -> bool {
+        match self {
+            Selector::Ids(ranges) => ranges.iter().any(|(low, high)| (*low..=*high).contains(&todo.id)),
+            Selector::Query(query) => query.matches(todo, manager),
+        }
+    }
+
+    /// 单独列出、但不存在的 ID；范围中缺少的 ID 不算
+    pub fn missing_ids(&self, manager: &TodoManager) -> Vec<usize> {
+        match self {
+            Selector::Ids(ranges) => ranges
+                .iter()
+                .filter(|(low, high)| low == high && manager.get_todo(*low).is_none())
+                .map(|(id, _)| *id)
+                .collect(),
+            Selector::Query(_) => Vec::new(),
+        }
+    }
+}
+
+/// 只由数字、逗号和 `-` 组成的是 ID 列表，其余按查询解析，查询的语法错误带位置标记
+impl FromStr for Selector {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim();
+        if !text.starts_with(|c: char| c.is_ascii_digit()) || !text.chars().all(|c| c.is_ascii_digit() || c == ',' || c == '-') {
+            return text.parse::<Query>().map(Selector::Query).map_err(|e| e.render(text));
+        }
+
+        let invalid = |part: &str| format!("无效的任务ID: {} (示例: 3、1,3,5-9)", part);
+        let mut ranges = Vec::new();
+        for part in text.split(',') {
+            let parse = |id: &str| id.parse::<usize>().map_err(|_| invalid(part));
+            let range = match part.split_once('-') {
+                Some((low, high)) => (parse(low)?, parse(high)?),
+                None => (parse(part)?, parse(part)?),
+            };
+            if range.0 > range.1 {
+                return Err(invalid(part));
+            }
+            ranges.push(range);
+        }
+        Ok(Selector::Ids(ranges))
+    }
+}
+
+fn compare(cmp: Cmp, ordering: std::cmp::Ordering) -> bool {
+    match cmp {
+        Cmp::Eq => ordering.is_eq(),
+        Cmp::Ne => ordering.is_ne(),
+        Cmp::Lt => ordering.is_lt(),
+        Cmp::Le => ordering.is_le(),
+        Cmp::Gt => ordering.is_gt(),
+        Cmp::Ge => ordering.is_ge(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    Or,
+    Not,
+    Word(String),
+    Phrase(String),
+}
+
+#[derive(Debug)]
+struct Spanned {
+    token: Token,
+    start: usize,
+    end: usize,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Spanned>, ParseError> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    // 从 start 处的引号读到下一个引号，返回内容和引号之后的位置
+    let quoted = |start: usize| -> Result<(String, usize), ParseError> {
+        match chars[start + 1..].iter().position(|c| *c == '"') {
+            Some(length) => Ok((chars[start + 1..start + 1 + length].iter().collect(), start + length + 2)),
+            None => Err(ParseError::new("缺少配对的双引号", start, chars.len())),
+        }
+    };
+
+    while i < chars.len() {
+        let start = i;
+        let token = match chars[i] {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            },
+            '(' => {
+                i += 1;
+                Token::LParen
+            },
+            ')' => {
+                i += 1;
+                Token::RParen
+            },
+            '|' => {
+                i += 1;
+                Token::Or
+            },
+            '-' if chars.get(i + 1).is_some_and(|c| !c.is_whitespace()) => {
+                i += 1;
+                Token::Not
+            },
+            '"' => {
+                let (phrase, next) = quoted(i)?;
+                i = next;
+                Token::Phrase(phrase)
+            },
+            _ => {
+                // 词中可以用引号包含空白，如 due<"tomorrow 14:00"
+                let mut word = String::new();
+                while i < chars.len() && !chars[i].is_whitespace() && !matches!(chars[i], '(' | ')' | '|') {
+                    if chars[i] == '"' {
+                        let (part, next) = quoted(i)?;
+                        word.push_str(&part);
+                        i = next;
+                    } else {
+                        word.push(chars[i]);
+                        i += 1;
+                    }
+                }
+                match word.as_str() {
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    "AND" => continue,
+                    _ => Token::Word(word),
+                }
+            },
+        };
+        tokens.push(Spanned { token, start, end: i });
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Spanned>,
+    position: usize,
+    now: DateTime<Local>,
+    /// 查询的字符数，用于报告结尾处的错误
+    length: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|spanned| &spanned.token)
+    }
+
+    /// 空白分隔的条件都要满足
+    fn parse_and(&mut self) -> Result<Expr, ParseError> {
+        let mut exprs = Vec::new();
+
+        while !matches!(self.peek(), None | Some(Token::RParen)) {
+            if self.peek() == Some(&Token::Or) {
+                let or = &self.tokens[self.position];
+                return Err(ParseError::new("OR 前面缺少条件", or.start, or.end));
+            }
+            exprs.push(self.parse_or()?);
+        }
+
+        Ok(if exprs.len() == 1 { exprs.remove(0) } else { Expr::And(exprs) })
+    }
+
+    /// OR 比空白结合得更紧，`+work|+home -@office` 表示 (+work 或 +home) 且不是 @office
+    fn parse_or(&mut self) -> Result<Expr, ParseError> {
+        let mut exprs = vec![self.parse_unary()?];
+
+        while self.peek() == Some(&Token::Or) {
+            let or = &self.tokens[self.position];
+            let (start, end) = (or.start, or.end);
+            self.position += 1;
+
+            if matches!(self.peek(), None | Some(Token::Or | Token::RParen)) {
+                return Err(ParseError::new("OR 后面缺少条件", start, end));
+            }
+            exprs.push(self.parse_unary()?);
+        }
+
+        Ok(if exprs.len() == 1 { exprs.remove(0) } else { Expr::Or(exprs) })
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, ParseError> {
+        let Spanned { token, start, end } = &self.tokens[self.position];
+        let (start, end) = (*start, *end);
+        self.position += 1;
+
+        match token.clone() {
+            Token::Not => {
+                if matches!(self.peek(), None | Some(Token::Or | Token::RParen)) {
+                    return Err(ParseError::new("取反后面缺少条件", start, end));
+                }
+                Ok(Expr::Not(Box::new(self.parse_unary()?)))
+            },
+            Token::LParen => {
+                let expr = self.parse_and()?;
+                if self.peek() != Some(&Token::RParen) {
+                    return Err(ParseError::new("缺少右括号", start, self.length.max(end)));
+                }
+                self.position += 1;
+                if expr == Expr::And(Vec::new()) {
+                    return Err(ParseError::new("括号中没有条件", start, self.tokens[self.position - 1].end));
+                }
+                Ok(expr)
+            },
+            Token::Phrase(phrase) => Ok(Expr::Phrase(phrase)),
+            Token::Word(word) => self.parse_term(&word, start, end),
+            Token::Or | Token::RParen => unreachable!("调用前已经检查过 OR 和右括号"),
+        }
+    }
+
+    fn parse_term(&self, word: &str, start: usize, end: usize) -> Result<Expr, ParseError> {
+        if tags::is_tag(word) {
+            return Ok(Expr::Tag(word.to_string()));
+        }
+
+        // 以英文字母开头、后面紧跟比较符号的是字段条件，其余当作普通词
+        let name_length = word.chars().take_while(char::is_ascii_alphabetic).count();
+        let rest = &word[name_length..];
+        let operator = [">=", "<=", "!=", ":", "=", ">", "<"].into_iter().find(|op| rest.starts_with(op));
+        let Some(operator) = operator.filter(|_| name_length > 0) else {
+            return Ok(Expr::Text(word.to_lowercase()));
+        };
+
+        let name = word[..name_length].to_lowercase();
+        let value = &rest[operator.len()..];
+        let error = |message: String| ParseError::new(message, start, end);
+
+        let cmp = match operator {
+            ":" | "=" => Cmp::Eq,
+            "!=" => Cmp::Ne,
+            "<" => Cmp::Lt,
+            "<=" => Cmp::Le,
+            ">" => Cmp::Gt,
+            _ => Cmp::Ge,
+        };
+        let equality_only = || match cmp {
+            Cmp::Eq => Ok(false),
+            Cmp::Ne => Ok(true),
+            _ => Err(error(format!("字段 {} 不支持 {}，只能用 : 或 !=", name, operator))),
+        };
+
+        if value.is_empty() && FIELDS.split(", ").any(|field| field == name) {
+            return Err(error(format!("字段 {} 缺少值", name)));
+        }
+
+        let expr = match name.as_str() {
+            "status" | "is" => {
+                let negate = equality_only()?;
+                let status = match value.to_lowercase().as_str() {
+                    "pending" | "open" | "未完成" => Status::Pending,
+                    "completed" | "done" | "已完成" => Status::Completed,
+                    "blocked" => Status::Blocked,
+                    "overdue" => Status::Overdue,
+                    _ => return Err(error(format!("未知的状态: {} (可选: pending, completed, blocked, overdue)", value))),
+                };
+                negated(Expr::Status(status), negate)
+            },
+            "priority" | "p" => {
+                let priority = value
+                    .parse::<Priority>()
+                    .map_err(|_| error(format!("未知的优先级: {} (可选: low, medium, high, urgent)", value)))?;
+                Expr::Priority(cmp, priority)
+            },
+            "created" | "completed" | "due" => {
+                let field = match name.as_str() {
+                    "created" => TimeField::Created,
+                    "completed" => TimeField::Completed,
+                    _ => TimeField::Due,
+                };
+                let (from, to) = self.time_range(field, value).map_err(error)?;
+                Expr::Time(field, cmp, from, to)
+            },
+            "id" => {
+                let parse = |text: &str| text.parse::<usize>().map_err(|_| error(format!("无效的任务ID: {}", value)));
+                match (cmp, value.split_once('-')) {
+                    (Cmp::Eq | Cmp::Ne, Some((low, high))) => negated(Expr::Id(parse(low)?, parse(high)?), cmp == Cmp::Ne),
+                    (_, Some(_)) => return Err(error("ID 范围只能用 : 或 !=".to_string())),
+                    (_, None) => {
+                        let id = parse(value)?;
+                        match cmp {
+                            Cmp::Eq => Expr::Id(id, id),
+                            Cmp::Ne => negated(Expr::Id(id, id), true),
+                            Cmp::Lt => negated(Expr::Id(id, usize::MAX), true),
+                            Cmp::Le => Expr::Id(0, id),
+                            Cmp::Gt => negated(Expr::Id(0, id), true),
+                            Cmp::Ge => Expr::Id(id, usize::MAX),
+                        }
+                    },
+                }
+            },
+            "parent" => {
+                let negate = equality_only()?;
+                let parent = match value {
+                    "none" => None,
+                    id => Some(id.parse::<usize>().map_err(|_| error(format!("无效的任务ID: {}", id)))?),
+                };
+                negated(Expr::Parent(parent), negate)
+            },
+            "has" => {
+                let negate = equality_only()?;
+                let attribute = match value.to_lowercase().as_str() {
+                    "due" => Attribute::Due,
+                    "repeat" => Attribute::Repeat,
+                    "tags" | "tag" => Attribute::Tags,
+                    "parent" => Attribute::Parent,
+                    "children" | "subtasks" => Attribute::Children,
+                    "blockers" | "deps" => Attribute::Blockers,
+                    _ => return Err(error(format!("未知的属性: {} (可选: due, repeat, tags, parent, children, blockers)", value))),
+                };
+                negated(Expr::Has(attribute), negate)
+            },
+            _ => {
+                return Err(error(format!(
+                    "未知的字段: {} (可用: {})；要搜索包含 {} 的文本请加引号",
+                    name, FIELDS, operator
+                )))
+            },
+        };
+
+        Ok(expr)
+    }
+
+    /// 时间值对应的 [开始, 结束) 区间
+    ///
+    /// `7d`、`4h` 这样的时长对 created/completed 表示多久以前，对 due 表示多久以后；
+    /// 其余按截止时间的写法解析，只有日期时区间是一整天
+    fn time_range(&self, field: TimeField, value: &str) -> Result<(i64, i64), String> {
+        let now = self.now.timestamp();
+
+        if value.starts_with(|c: char| c.is_ascii_digit()) {
+            if let Ok(seconds) = due::parse_duration(value) {
+                let at = if field == TimeField::Due { now + seconds } else { now - seconds };
+                return Ok((at, at + 1));
+            }
+        }
+
+        let due = Due::parse(value, self.now)?;
+        if !due.all_day {
+            return Ok((due.at, due.at + 1));
+        }
+        Ok((start_of_day(due.date()), start_of_day(due.date() + Duration::days(1))))
+    }
+}
+
+fn negated(expr: Expr, negate: bool) -> Expr {
+    if negate {
+        Expr::Not(Box::new(expr))
+    } else {
+        expr
+    }
+}
+
+fn start_of_day(date: NaiveDate) -> i64 {
+    let midnight = date.and_hms_opt(0, 0, 0).expect("有效的时间");
+    // 夏令时从零点开始时这一天没有零点，退回到 UTC 的零点
+    Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .map_or_else(|| midnight.and_utc().timestamp(), |at| at.timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> DateTime<Local> {
+        Local.with_ymd_and_hms(2026, 10, 16, 10, 30, 0).unwrap()
+    }
+
+    fn parse(text: &str) -> Expr {
+        Query::parse(text, now()).unwrap().expr
+    }
+
+    fn error(text: &str) -> ParseError {
+        Query::parse(text, now()).unwrap_err()
+    }
+
+    #[test]
+    fn test_parse_structure() {
+        assert_eq!(parse(""), Expr::And(vec![]));
+        assert_eq!(
+            parse(r#"Report "Exact Phrase" -draft"#),
+            Expr::And(vec![
+                Expr::Text("report".to_string()),
+                Expr::Phrase("Exact Phrase".to_string()),
+                Expr::Not(Box::new(Expr::Text("draft".to_string()))),
+            ])
+        );
+
+        // NOT 优先于 OR，OR 优先于 AND
+        assert_eq!(
+            parse("+work|+home @phone OR NOT status:done"),
+            Expr::And(vec![
+                Expr::Or(vec![Expr::Tag("+work".to_string()), Expr::Tag("+home".to_string())]),
+                Expr::Or(vec![
+                    Expr::Tag("@phone".to_string()),
+                    Expr::Not(Box::new(Expr::Status(Status::Completed))),
+                ]),
+            ])
+        );
+        assert_eq!(
+            parse("(a OR b) AND c"),
+            Expr::And(vec![
+                Expr::Or(vec![Expr::Text("a".to_string()), Expr::Text("b".to_string())]),
+                Expr::Text("c".to_string()),
+            ])
+        );
+
+        assert_eq!(parse("priority>=high"), Expr::Priority(Cmp::Ge, Priority::High));
+        assert_eq!(parse("p:紧急"), Expr::Priority(Cmp::Eq, Priority::Urgent));
+        assert_eq!(parse("id:10-20"), Expr::Id(10, 20));
+        assert_eq!(parse("id>5"), Expr::Not(Box::new(Expr::Id(0, 5))));
+        assert_eq!(parse("parent:none"), Expr::Parent(None));
+        assert_eq!(parse("has!=due"), Expr::Not(Box::new(Expr::Has(Attribute::Due))));
+        assert_eq!(parse("会议:周一"), Expr::Text("会议:周一".to_string()));
+
+        let seven_days_ago = now().timestamp() - 7 * 86400;
+        assert_eq!(parse("created>7d"), Expr::Time(TimeField::Created, Cmp::Gt, seven_days_ago, seven_days_ago + 1));
+        let Expr::Time(TimeField::Due, Cmp::Lt, start, end) = parse(r#"due<"2026-10-20""#) else { panic!() };
+        assert_eq!(end - start, 86400);
+        assert_eq!(start, Local.with_ymd_and_hms(2026, 10, 20, 0, 0, 0).unwrap().timestamp());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(error("status:pending prioity>=high"), ParseError::new("未知的字段: prioity (可用: status, priority, created, completed, due, id, parent, has)；要搜索包含 >= 的文本请加引号", 15, 28));
+        assert_eq!(error("status:later").message, "未知的状态: later (可选: pending, completed, blocked, overdue)");
+        assert_eq!(error("priority>=top").start, 0);
+        assert_eq!(error("(a OR b").message, "缺少右括号");
+        assert_eq!(error("a OR b)").message, "多余的右括号");
+        assert_eq!(error("OR a").message, "OR 前面缺少条件");
+        assert_eq!(error("a |").message, "OR 后面缺少条件");
+        assert_eq!(error("(a -)").message, "取反后面缺少条件");
+        assert_eq!(error("NOT").message, "取反后面缺少条件");
+        assert_eq!(error("()").message, "括号中没有条件");
+        assert_eq!(error("due:").message, "字段 due 缺少值");
+        assert_eq!(error("has>due").message, "字段 has 不支持 >，只能用 : 或 !=");
+        assert_eq!(error("id:a-b").message, "无效的任务ID: a-b");
+        assert_eq!(error("created<someday").message, "无法识别的时间: someday (示例: tomorrow, +3d, 2026-11-01 14:00)");
+
+        let unterminated = error(r#"任务 "abc"#);
+        assert_eq!((unterminated.start, unterminated.end), (3, 7));
+        assert_eq!(unterminated.render(r#"任务 "abc"#), "查询语法错误: 缺少配对的双引号\n  任务 \"abc\n       ^^^^");
+    }
+
+    #[test]
+    fn test_parse_selector() {
+        assert_eq!("7".parse::<Selector>().unwrap().single_id(), Some(7));
+        assert_eq!("1,3,5-9".parse(), Ok(Selector::Ids(vec![(1, 1), (3, 3), (5, 9)])));
+        assert_eq!("1,3".parse::<Selector>().unwrap().single_id(), None);
+        assert!(matches!("status:done".parse(), Ok(Selector::Query(_))));
+        assert!(matches!("3d".parse(), Ok(Selector::Query(_))));
+        assert_eq!("5-".parse::<Selector>(), Err("无效的任务ID: 5- (示例: 3、1,3,5-9)".to_string()));
+        assert_eq!("9-5".parse::<Selector>(), Err("无效的任务ID: 9-5 (示例: 3、1,3,5-9)".to_string()));
+        assert!("is:later".parse::<Selector>().unwrap_err().ends_with("^^^^^^^^"));
+    }
+
+    #[test]
+    fn test_matches() {
+        let mut manager = TodoManager::new();
+        let report = manager.add_todo("Write Quarterly Report +work".to_string(), Priority::High);
+        let call = manager.add_todo("给妈妈打电话 @phone".to_string(), Priority::Low);
+        let review = manager.add_todo("Review report draft +work".to_string(), Priority::Urgent);
+        manager.set_parent(review, Some(report)).unwrap();
+        manager.add_dependency(call, review).unwrap();
+        manager.set_due(report, Some(Due::parse("2026-10-15", now()).unwrap()));
+        manager.complete_todo(review);
+
+        let ids = |text: &str| {
+            let query = Query::parse(text, now()).unwrap();
+            let mut ids: Vec<usize> = manager.get_all_todos().into_iter().filter(|todo| query.matches(todo, &manager)).map(|todo| todo.id).collect();
+            ids.sort();
+            ids
+        };
+
+        assert_eq!(ids(""), [report, call, review]);
+        assert_eq!(ids("report"), [report, review]);
+        assert_eq!(ids("report -draft"), [report]);
+        assert_eq!(ids(r#""Report""#), [report]);
+        assert_eq!(ids("status:pending priority>=high"), [report]);
+        assert_eq!(ids("priority<high OR status:done"), [call, review]);
+        assert_eq!(ids("+work|+家庭 -status:completed"), [report]);
+        assert_eq!(ids("is:overdue"), [report]);
+        assert_eq!(ids("due<=2026-10-15"), [report]);
+        assert_eq!(ids("due>2026-10-15"), Vec::<usize>::new());
+        assert_eq!(ids("has:children"), [report]);
+        assert_eq!(ids("has:blockers status:blocked"), Vec::<usize>::new());
+        assert_eq!(ids("parent:1"), [review]);
+        assert_eq!(ids("id:2-3"), [call, review]);
+        assert_eq!(ids("id!=2"), [report, review]);
+        assert_eq!(ids("created<7d"), Vec::<usize>::new());
+        assert_eq!(ids("completed>1d"), [review]);
+    }
+}
@@ -0,0 +1,208 @@
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::due::{self, Due};
+
+const WEEKDAY_NAMES: [&str; 7] = ["一", "二", "三", "四", "五", "六", "日"];
+
+/// 重复规则，保存和导出时使用 `daily`、`weekly:mon,fri`、`monthly:15`、`every:3d` 形式的文本
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Recurrence {
+    /// 每天
+    Daily,
+    /// 每周的这几天；为空时与上一次截止时间是同一天
+    Weekly(Vec<Weekday>),
+    /// 每月的第 N 天，当月没有这一天时取最后一天
+    Monthly(u32),
+    /// 完成后 N 天
+    AfterCompletion(u32),
+}
+
+impl Recurrence {
+    /// 规则的文本形式，可以被 `parse` 重新读回
+    pub fn rule(&self) -> String {
+        match self {
+            Recurrence::Daily => "daily".to_string(),
+            Recurrence::Weekly(days) if days.is_empty() => "weekly".to_string(),
+            Recurrence::Weekly(days) => {
+                let days: Vec<String> = days.iter().map(|day| day.to_string().to_lowercase()).collect();
+                format!("weekly:{}", days.join(","))
+            },
+            Recurrence::Monthly(day) => format!("monthly:{}", day),
+            Recurrence::AfterCompletion(days) => format!("every:{}d", days),
+        }
+    }
+
+    /// 在 completed 完成当前这次之后，下一次的截止时间
+    ///
+    /// 按日历重复的规则从上一次截止日期和完成日期中较晚的一个往后找，
+    /// 逾期很久才完成时不会生成一串已经逾期的任务；保留原来的截止时刻
+    pub fn next_due(&self, due: Option<Due>, completed: DateTime<Local>) -> Due {
+        let today = completed.date_naive();
+        let time = due.and_then(|due| due.time());
+
+        let date = match self {
+            Recurrence::AfterCompletion(days) => today + Duration::days(*days as i64),
+            _ => {
+                let anchor = due.map_or(today, |due| due.date());
+                let mut date = anchor.max(today);
+                loop {
+                    date = date.succ_opt().expect("日期超出范围");
+                    if self.matches(date, anchor) {
+                        break date;
+                    }
+                }
+            },
+        };
+
+        // 新的时刻恰好落在夏令时跳过的时间里时退回全天
+        Due::on(date, time).or_else(|_| Due::on(date, None)).expect("有效的截止时间")
+    }
+
+    fn matches(&self, date: NaiveDate, anchor: NaiveDate) -> bool {
+        match self {
+            Recurrence::Daily | Recurrence::AfterCompletion(_) => true,
+            Recurrence::Weekly(days) if days.is_empty() => date.weekday() == anchor.weekday(),
+            Recurrence::Weekly(days) => days.contains(&date.weekday()),
+            Recurrence::Monthly(day) => date.day() == (*day).min(days_in_month(date)),
+        }
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim().to_lowercase();
+        let invalid = || format!("无效的重复规则: {} (示例: daily, weekly:mon,fri, monthly:15, every:3d)", text);
+
+        let (kind, value) = match text.split_once(':') {
+            Some((kind, value)) => (kind, Some(value)),
+            None => (text.as_str(), None),
+        };
+
+        match (kind, value) {
+            ("daily" | "每天", None) => Ok(Recurrence::Daily),
+            ("weekly" | "每周", None) => Ok(Recurrence::Weekly(Vec::new())),
+            ("weekly" | "每周", Some(days)) => {
+                let mut weekdays = Vec::new();
+                for day in days.split(',') {
+                    let weekday = due::parse_weekday(day.trim()).ok_or_else(invalid)?;
+                    if !weekdays.contains(&weekday) {
+                        weekdays.push(weekday);
+                    }
+                }
+                weekdays.sort_by_key(|day| day.num_days_from_monday());
+                Ok(Recurrence::Weekly(weekdays))
+            },
+            ("monthly" | "每月", Some(day)) => match day.parse::<u32>() {
+                Ok(day @ 1..=31) => Ok(Recurrence::Monthly(day)),
+                _ => Err(invalid()),
+            },
+            ("every", Some(interval)) => {
+                let interval = if interval.ends_with(['d', 'w']) { interval.to_string() } else { format!("{}d", interval) };
+                let seconds = due::parse_duration(&interval).map_err(|_| invalid())?;
+                match seconds / (24 * 60 * 60) {
+                    days @ 1.. => u32::try_from(days).map(Recurrence::AfterCompletion).map_err(|_| invalid()),
+                    _ => Err(invalid()),
+                }
+            },
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl TryFrom<String> for Recurrence {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        text.parse()
+    }
+}
+
+impl From<Recurrence> for String {
+    fn from(recurrence: Recurrence) -> Self {
+        recurrence.rule()
+    }
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Recurrence::Daily => write!(f, "每天"),
+            Recurrence::Weekly(days) if days.is_empty() => write!(f, "每周"),
+            Recurrence::Weekly(days) => {
+                let days: Vec<&str> = days.iter().map(|day| WEEKDAY_NAMES[day.num_days_from_monday() as usize]).collect();
+                write!(f, "每周{}", days.join("、"))
+            },
+            Recurrence::Monthly(day) => write!(f, "每月{}日", day),
+            Recurrence::AfterCompletion(days) => write!(f, "完成后每{}天", days),
+        }
+    }
+}
+
+fn days_in_month(date: NaiveDate) -> u32 {
+    let (year, month) = if date.month() == 12 { (date.year() + 1, 1) } else { (date.year(), date.month() + 1) };
+    NaiveDate::from_ymd_opt(year, month, 1)
+        .and_then(|first| first.pred_opt())
+        .map_or(31, |last| last.day())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveTime, TimeZone};
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        let weekly: Recurrence = "weekly:fri, Mon,周一".parse().unwrap();
+        assert_eq!(weekly, Recurrence::Weekly(vec![Weekday::Mon, Weekday::Fri]));
+        assert_eq!(weekly.rule(), "weekly:mon,fri");
+        assert_eq!(weekly.to_string(), "每周一、五");
+
+        for rule in ["daily", "weekly", "monthly:31", "every:3d"] {
+            assert_eq!(rule.parse::<Recurrence>().unwrap().rule(), rule);
+        }
+        assert_eq!("every:2w".parse(), Ok(Recurrence::AfterCompletion(14)));
+        assert_eq!("every:5".parse(), Ok(Recurrence::AfterCompletion(5)));
+
+        for rule in ["", "hourly", "weekly:xyz", "monthly", "monthly:0", "monthly:32", "every:12h", "every:0d"] {
+            assert!(rule.parse::<Recurrence>().is_err(), "{}", rule);
+        }
+
+        // JSON 中保存为规则文本
+        assert_eq!(serde_json::to_string(&weekly).unwrap(), "\"weekly:mon,fri\"");
+        assert_eq!(serde_json::from_str::<Recurrence>("\"monthly:15\"").unwrap(), Recurrence::Monthly(15));
+    }
+
+    #[test]
+    fn test_next_due() {
+        // 2026-10-16 是星期五
+        let completed = Local.with_ymd_and_hms(2026, 10, 16, 10, 30, 0).unwrap();
+        let nine = NaiveTime::from_hms_opt(9, 0, 0);
+        let on = |d: NaiveDate, time| Some(Due::on(d, time).unwrap());
+        let next = |rule: &str, due| rule.parse::<Recurrence>().unwrap().next_due(due, completed);
+
+        // 提前完成时从截止日期往后，逾期完成时从今天往后
+        assert_eq!(next("daily", on(date(2026, 10, 18), nine)), on(date(2026, 10, 19), nine).unwrap());
+        assert_eq!(next("daily", on(date(2026, 10, 1), None)), on(date(2026, 10, 17), None).unwrap());
+        assert_eq!(next("daily", None), on(date(2026, 10, 17), None).unwrap());
+
+        assert_eq!(next("weekly:mon,wed", on(date(2026, 10, 16), nine)).date(), date(2026, 10, 19));
+        assert_eq!(next("weekly:mon,wed", on(date(2026, 10, 19), nine)).date(), date(2026, 10, 21));
+        assert_eq!(next("weekly", on(date(2026, 10, 14), None)).date(), date(2026, 10, 21));
+
+        assert_eq!(next("monthly:15", on(date(2026, 10, 15), None)).date(), date(2026, 11, 15));
+        assert_eq!(next("monthly:31", on(date(2026, 10, 31), None)).date(), date(2026, 11, 30));
+        assert_eq!(next("monthly:31", on(date(2026, 11, 30), None)).date(), date(2026, 12, 31));
+
+        assert_eq!(next("every:3d", on(date(2026, 10, 30), nine)), on(date(2026, 10, 19), nine).unwrap());
+    }
+}
@@ -0,0 +1,437 @@
+use rusqlite::functions::FunctionFlags;
+use rusqlite::types::Value;
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+
+use crate::due::Due;
+use crate::history::History;
+use crate::query::{Attribute, Cmp, Expr, Query, Status, TimeField};
+use crate::storage::{Storage, TodosData};
+use crate::todo::{Priority, Todo};
+
+/// 数据库结构的升级步骤，第 n 个把 `user_version` 从 n 升到 n + 1
+///
+/// 已经发布的步骤不能再修改，结构变化时在末尾追加新的步骤
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE todos (
+        id INTEGER PRIMARY KEY,
+        description TEXT NOT NULL,
+        completed INTEGER NOT NULL,
+        priority INTEGER NOT NULL,
+        created_at TEXT NOT NULL,
+        created_ts INTEGER,
+        completed_at TEXT,
+        completed_ts INTEGER,
+        due_at INTEGER,
+        due_all_day INTEGER,
+        recurrence TEXT,
+        parent INTEGER
+    );
+    CREATE TABLE todo_tags (
+        todo_id INTEGER NOT NULL,
+        tag TEXT NOT NULL,
+        PRIMARY KEY (todo_id, tag)
+    );
+    CREATE TABLE todo_dependencies (
+        todo_id INTEGER NOT NULL,
+        blocker_id INTEGER NOT NULL,
+        PRIMARY KEY (todo_id, blocker_id)
+    );
+    CREATE TABLE meta (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+    CREATE INDEX todo_tags_tag ON todo_tags (tag);
+    CREATE INDEX todo_dependencies_blocker ON todo_dependencies (blocker_id);
+    CREATE INDEX todos_completed ON todos (completed);
+    CREATE INDEX todos_priority ON todos (priority);
+    CREATE INDEX todos_created ON todos (created_ts);
+    CREATE INDEX todos_due ON todos (due_at);
+    CREATE INDEX todos_parent ON todos (parent);",
+];
+
+/// 嵌入式 SQLite 数据库存储，保存时只写入变化的任务，查询在数据库中用索引完成
+pub struct SqliteStorage {
+    conn: Connection,
+}
+
+impl SqliteStorage {
+    /// 打开数据库文件，不存在时创建，并升级到最新的结构
+    pub fn open(path: &str) -> Result<Self, Box<dyn Error>> {
+        let mut conn = Connection::open(path)?;
+        migrate(&mut conn)?;
+
+        // 与 Rust 的 to_lowercase 一致，非 ASCII 字符也能不区分大小写匹配
+        conn.create_scalar_function("todo_lower", 1, FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC, |ctx| {
+            Ok(ctx.get::<String>(0)?.to_lowercase())
+        })?;
+
+        Ok(SqliteStorage { conn })
+    }
+}
+
+/// 依次执行还没有执行过的升级步骤，每一步在单独的事务中完成
+fn migrate(conn: &mut Connection) -> Result<(), Box<dyn Error>> {
+    let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version > MIGRATIONS.len() {
+        return Err(format!("数据库结构版本 {} 比程序支持的版本 {} 新，请升级程序", version, MIGRATIONS.len()).into());
+    }
+
+    for (index, sql) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(sql)?;
+        tx.pragma_update(None, "user_version", index + 1)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+impl Storage for SqliteStorage {
+    fn load_todos(&self) -> Result<TodosData, Box<dyn Error>> {
+        let mut todos: HashMap<usize, Todo> = HashMap::new();
+
+        let mut statement = self.conn.prepare(
+            "SELECT id, description, completed, priority, created_at, completed_at, due_at, due_all_day, recurrence, parent FROM todos",
+        )?;
+        let mut rows = statement.query([])?;
+        while let Some(row) = rows.next()? {
+            let id: usize = row.get(0)?;
+            let due_at: Option<i64> = row.get(6)?;
+            let recurrence: Option<String> = row.get(8)?;
+            todos.insert(id, Todo {
+                id,
+                description: row.get(1)?,
+                completed: row.get(2)?,
+                priority: priority_from_rank(row.get(3)?),
+                created_at: row.get(4)?,
+                completed_at: row.get(5)?,
+                due: due_at.map(|at| Ok::<_, rusqlite::Error>(Due { at, all_day: row.get(7)? })).transpose()?,
+                recurrence: recurrence.map(|text| serde_json::from_str(&text)).transpose()?,
+                tags: BTreeSet::new(),
+                parent: row.get(9)?,
+                blocked_by: BTreeSet::new(),
+            });
+        }
+
+        let mut statement = self.conn.prepare("SELECT todo_id, tag FROM todo_tags")?;
+        let mut rows = statement.query([])?;
+        while let Some(row) = rows.next()? {
+            if let Some(todo) = todos.get_mut(&row.get(0)?) {
+                todo.tags.insert(row.get(1)?);
+            }
+        }
+
+        let mut statement = self.conn.prepare("SELECT todo_id, blocker_id FROM todo_dependencies")?;
+        let mut rows = statement.query([])?;
+        while let Some(row) = rows.next()? {
+            if let Some(todo) = todos.get_mut(&row.get(0)?) {
+                todo.blocked_by.insert(row.get(1)?);
+            }
+        }
+
+        let next_id = match get_meta(&self.conn, "next_id")? {
+            Some(next_id) => next_id.parse()?,
+            None => todos.keys().max().map_or(1, |id| id + 1),
+        };
+        let history = match get_meta(&self.conn, "history")? {
+            // 与 JSON 存储一致：记录损坏时给出警告，不影响任务数据
+            Some(history) => serde_json::from_str(&history).unwrap_or_else(|e| {
+                eprintln!("警告: 操作记录已损坏，撤销记录将从空开始: {}", e);
+                History::default()
+            }),
+            None => History::default(),
+        };
+
+        let mut todos: Vec<Todo> = todos.into_values().collect();
+        todos.sort_by_key(|todo| todo.id);
+        Ok(TodosData { todos, next_id, history })
+    }
+
+    fn save_todos(&self, data: &TodosData, changed: &BTreeSet<usize>) -> Result<(), Box<dyn Error>> {
+        let tx = self.conn.unchecked_transaction()?;
+
+        for id in changed {
+            delete_todo(&tx, *id)?;
+        }
+        for todo in data.todos.iter().filter(|todo| changed.contains(&todo.id)) {
+            insert_todo(&tx, todo)?;
+        }
+
+        tx.execute("INSERT OR REPLACE INTO meta (key, value) VALUES ('next_id', ?1)", [data.next_id.to_string()])?;
+        tx.execute("INSERT OR REPLACE INTO meta (key, value) VALUES ('history', ?1)", [serde_json::to_string(&data.history)?])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn find(&self, query: &Query) -> Option<Result<Vec<usize>, Box<dyn Error>>> {
+        let mut values = Vec::new();
+        let condition = to_sql(query.expr(), query.now(), &mut values);
+
+        let result = (|| {
+            let mut statement = self.conn.prepare(&format!("SELECT id FROM todos WHERE {}", condition))?;
+            let ids = statement
+                .query_map(rusqlite::params_from_iter(values), |row| row.get(0))?
+                .collect::<Result<Vec<usize>, _>>()?;
+            Ok(ids)
+        })();
+        Some(result)
+    }
+}
+
+fn get_meta(conn: &Connection, key: &str) -> rusqlite::Result<Option<String>> {
+    conn.query_row("SELECT value FROM meta WHERE key = ?1", [key], |row| row.get(0)).optional()
+}
+
+fn delete_todo(tx: &Transaction, id: usize) -> rusqlite::Result<()> {
+    tx.execute("DELETE FROM todos WHERE id = ?1", [id])?;
+    tx.execute("DELETE FROM todo_tags WHERE todo_id = ?1", [id])?;
+    tx.execute("DELETE FROM todo_dependencies WHERE todo_id = ?1", [id])?;
+    Ok(())
+}
+
+fn insert_todo(tx: &Transaction, todo: &Todo) -> Result<(), Box<dyn Error>> {
+    let recurrence = todo.recurrence.as_ref().map(serde_json::to_string).transpose()?;
+    tx.execute(
+        "INSERT INTO todos (id, description, completed, priority, created_at, created_ts, completed_at, completed_ts, due_at, due_all_day, recurrence, parent)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        params![
+            todo.id,
+            todo.description,
+            todo.completed,
+            priority_rank(&todo.priority),
+            todo.created_at,
+            todo.created_at.parse::<i64>().ok(),
+            todo.completed_at,
+            todo.completed_at.as_ref().and_then(|time| time.parse::<i64>().ok()),
+            todo.due.map(|due| due.at),
+            todo.due.map(|due| due.all_day),
+            recurrence,
+            todo.parent,
+        ],
+    )?;
+    for tag in &todo.tags {
+        tx.execute("INSERT INTO todo_tags (todo_id, tag) VALUES (?1, ?2)", params![todo.id, tag])?;
+    }
+    for blocker in &todo.blocked_by {
+        tx.execute("INSERT INTO todo_dependencies (todo_id, blocker_id) VALUES (?1, ?2)", params![todo.id, blocker])?;
+    }
+    Ok(())
+}
+
+/// 优先级保存为可以比较大小的数字
+fn priority_rank(priority: &Priority) -> i64 {
+    match priority {
+        Priority::Low => 0,
+        Priority::Medium => 1,
+        Priority::High => 2,
+        Priority::Urgent => 3,
+    }
+}
+
+fn priority_from_rank(rank: i64) -> Priority {
+    match rank {
+        0 => Priority::Low,
+        2 => Priority::High,
+        3 => Priority::Urgent,
+        _ => Priority::Medium,
+    }
+}
+
+/// 把查询条件翻译成 SQL 条件，参数依次追加到 values
+///
+/// 每个条件的结果都不会是 NULL，取反时与 `Query::matches` 保持一致
+fn to_sql(expr: &Expr, now: i64, values: &mut Vec<Value>) -> String {
+    let mut bind = |value: Value| {
+        values.push(value);
+        format!("?{}", values.len())
+    };
+
+    match expr {
+        Expr::And(exprs) if exprs.is_empty() => "1".to_string(),
+        Expr::And(exprs) => join(exprs, " AND ", now, values),
+        Expr::Or(exprs) => join(exprs, " OR ", now, values),
+        Expr::Not(expr) => format!("NOT ({})", to_sql(expr, now, values)),
+        Expr::Text(word) => format!("instr(todo_lower(description), {}) > 0", bind(Value::Text(word.clone()))),
+        Expr::Phrase(phrase) => format!("instr(description, {}) > 0", bind(Value::Text(phrase.clone()))),
+        Expr::Tag(tag) => format!("id IN (SELECT todo_id FROM todo_tags WHERE tag = {})", bind(Value::Text(tag.clone()))),
+        Expr::Status(Status::Pending) => "completed = 0".to_string(),
+        Expr::Status(Status::Completed) => "completed = 1".to_string(),
+        Expr::Status(Status::Blocked) => "(completed = 0 AND EXISTS (SELECT 1 FROM todo_dependencies d JOIN todos b ON b.id = d.blocker_id \
+                                          WHERE d.todo_id = todos.id AND b.completed = 0))".to_string(),
+        Expr::Status(Status::Overdue) => format!("(completed = 0 AND due_at IS NOT NULL AND due_at < {})", bind(Value::Integer(now))),
+        Expr::Priority(cmp, priority) => format!("priority {} {}", operator(*cmp), bind(Value::Integer(priority_rank(priority)))),
+        Expr::Time(field, cmp, start, end) => {
+            let column = match field {
+                TimeField::Created => "created_ts",
+                TimeField::Completed => "completed_ts",
+                TimeField::Due => "due_at",
+            };
+            let condition = match cmp {
+                Cmp::Eq => format!("{0} >= {1} AND {0} < {2}", column, bind(Value::Integer(*start)), bind(Value::Integer(*end))),
+                Cmp::Ne => format!("NOT ({0} >= {1} AND {0} < {2})", column, bind(Value::Integer(*start)), bind(Value::Integer(*end))),
+                Cmp::Lt => format!("{} < {}", column, bind(Value::Integer(*start))),
+                Cmp::Le => format!("{} < {}", column, bind(Value::Integer(*end))),
+                Cmp::Gt => format!("{} >= {}", column, bind(Value::Integer(*end))),
+                Cmp::Ge => format!("{} >= {}", column, bind(Value::Integer(*start))),
+            };
+            format!("({} IS NOT NULL AND {})", column, condition)
+        },
+        // `id>=N` 解析为 Id(N, usize::MAX)，转换时截到 i64::MAX，不能回绕成负数
+        Expr::Id(low, high) => {
+            let clamp = |id: usize| Value::Integer(i64::try_from(id).unwrap_or(i64::MAX));
+            format!("id BETWEEN {} AND {}", bind(clamp(*low)), bind(clamp(*high)))
+        },
+        Expr::Parent(parent) => format!("parent IS {}", bind(parent.map_or(Value::Null, |id| Value::Integer(id as i64)))),
+        Expr::Has(Attribute::Due) => "due_at IS NOT NULL".to_string(),
+        Expr::Has(Attribute::Repeat) => "recurrence IS NOT NULL".to_string(),
+        Expr::Has(Attribute::Tags) => "EXISTS (SELECT 1 FROM todo_tags t WHERE t.todo_id = todos.id)".to_string(),
+        Expr::Has(Attribute::Parent) => "parent IS NOT NULL".to_string(),
+        Expr::Has(Attribute::Children) => "EXISTS (SELECT 1 FROM todos c WHERE c.parent = todos.id)".to_string(),
+        Expr::Has(Attribute::Blockers) => "EXISTS (SELECT 1 FROM todo_dependencies d WHERE d.todo_id = todos.id)".to_string(),
+    }
+}
+
+fn join(exprs: &[Expr], separator: &str, now: i64, values: &mut Vec<Value>) -> String {
+    let parts: Vec<String> = exprs.iter().map(|expr| format!("({})", to_sql(expr, now, values))).collect();
+    parts.join(separator)
+}
+
+fn operator(cmp: Cmp) -> &'static str {
+    match cmp {
+        Cmp::Eq => "=",
+        Cmp::Ne => "!=",
+        Cmp::Lt => "<",
+        Cmp::Le => "<=",
+        Cmp::Gt => ">",
+        Cmp::Ge => ">=",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::todo::TodoManager;
+    use tempfile::TempDir;
+
+    fn open(dir: &TempDir) -> SqliteStorage {
+        SqliteStorage::open(&dir.path().join("todos.db").to_string_lossy()).unwrap()
+    }
+
+    fn sample_manager(dir: &TempDir) -> TodoManager {
+        let mut manager = TodoManager::with_storage(Box::new(open(dir)));
+        let report = manager.add_todo("写报告 +工作 @电脑".to_string(), Priority::High);
+        let data = manager.add_todo("整理 Straße 数据 +工作".to_string(), Priority::Medium);
+        let milk = manager.add_todo("买牛奶 @超市".to_string(), Priority::Low);
+        let old = manager.add_todo("旧任务".to_string(), Priority::Urgent);
+        manager.set_due(report, Some("2000-01-03".parse().unwrap()));
+        manager.set_due(milk, Some("2999-01-01 08:00".parse().unwrap()));
+        manager.set_recurrence(milk, Some("weekly:mon".parse().unwrap()));
+        manager.set_parent(data, Some(report)).unwrap();
+        manager.add_dependency(milk, data).unwrap();
+        manager.add_dependency(old, report).unwrap();
+        manager.complete_with_next(old, true).unwrap();
+        manager
+    }
+
+    #[test]
+    fn test_round_trip_and_incremental_save() {
+        let dir = TempDir::new().unwrap();
+        let mut manager = sample_manager(&dir);
+        manager.save_to_file().unwrap();
+
+        let mut data = open(&dir).load_todos().unwrap();
+        let mut expected: Vec<Todo> = manager.get_all_todos().into_iter().cloned().collect();
+        expected.sort_by_key(|todo| todo.id);
+        assert_eq!(data.todos, expected);
+        assert_eq!(data.next_id, 5);
+        assert_eq!(data.history.done().next().unwrap().label, "完成 #4");
+
+        // 只写入 changed 中的任务
+        data.todos[0].description = "没有标记为修改".to_string();
+        data.todos[1].description = "标记为修改".to_string();
+        data.todos.remove(2);
+        let changed: BTreeSet<usize> = [2, 3].into_iter().collect();
+        open(&dir).save_todos(&data, &changed).unwrap();
+
+        let loaded = open(&dir).load_todos().unwrap();
+        let descriptions: Vec<&str> = loaded.todos.iter().map(|todo| todo.description.as_str()).collect();
+        assert_eq!(descriptions, ["写报告", "标记为修改", "旧任务"]);
+
+        // 操作记录损坏时任务仍然可以读取
+        let conn = Connection::open(dir.path().join("todos.db")).unwrap();
+        conn.execute("UPDATE meta SET value = 'invalid json' WHERE key = 'history'", []).unwrap();
+        let loaded = open(&dir).load_todos().unwrap();
+        assert_eq!(loaded.todos.len(), 3);
+        assert!(loaded.history.done().next().is_none());
+    }
+
+    #[test]
+    fn test_schema_migrations() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("todos.db");
+        drop(open(&dir));
+
+        let conn = Connection::open(&path).unwrap();
+        let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+
+        // 再次打开时不会重复执行升级
+        drop(open(&dir));
+
+        conn.pragma_update(None, "user_version", MIGRATIONS.len() + 1).unwrap();
+        let error = SqliteStorage::open(&path.to_string_lossy()).err().unwrap();
+        assert!(error.to_string().contains("请升级程序"));
+    }
+
+    #[test]
+    fn test_find_matches_query() {
+        let dir = TempDir::new().unwrap();
+        let mut manager = sample_manager(&dir);
+        manager.save_to_file().unwrap();
+        let storage = open(&dir);
+
+        let queries = [
+            "",
+            "straße",
+            "STRASSE",
+            "\"写报告\"",
+            "+工作 -@电脑",
+            "status:pending",
+            "status:completed OR priority>=high",
+            "status:blocked",
+            "-status:blocked",
+            "status:overdue",
+            "priority<medium",
+            "due:2000-01-03",
+            "-due:2000-01-03",
+            "due>2000-01-03",
+            "created<1d",
+            "completed:today",
+            "-completed:today",
+            "id:2-3",
+            "id>=2",
+            "id<3",
+            "id>1",
+            "parent:1",
+            "parent:none",
+            "has:due has:repeat",
+            "has:tags",
+            "has:children",
+            "-has:blockers",
+        ];
+        for text in queries {
+            let query: Query = text.parse().unwrap();
+            let mut expected: Vec<usize> = manager
+                .get_all_todos()
+                .into_iter()
+                .filter(|todo| query.matches(todo, &manager))
+                .map(|todo| todo.id)
+                .collect();
+            expected.sort();
+            let mut found = storage.find(&query).unwrap().unwrap();
+            found.sort();
+            assert_eq!(found, expected, "查询: {}", text);
+        }
+    }
+}
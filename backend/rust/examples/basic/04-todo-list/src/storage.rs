@@ -1,15 +1,56 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::error::Error;
 use std::fs;
-use std::io::{self, Write};
+use std::io::Write;
 use std::path::Path;
+use std::time::SystemTime;
 
-pub struct Storage {
+use crate::history::History;
+use crate::migrations::{self, UpgradeFailed, CURRENT_VERSION};
+use crate::query::Query;
+use crate::todo::Todo;
+
+/// 保存和读取的全部数据
+#[derive(Debug, Clone, Default)]
+pub struct TodosData {
+    pub todos: Vec<Todo>,
+    pub next_id: usize,
+    pub history: History,
+}
+
+/// 任务数据的存储方式
+pub trait Storage {
+    /// 读取任务、下一个任务 ID 和操作记录
+    fn load_todos(&self) -> Result<TodosData, Box<dyn Error>>;
+
+    /// 保存数据；changed 是上次保存之后新增、修改或删除过的任务 ID，存储可以只写入这些任务
+    fn save_todos(&self, data: &TodosData, changed: &BTreeSet<usize>) -> Result<(), Box<dyn Error>>;
+
+    /// 在已保存的数据中查找可能符合查询的任务 ID，结果还会被逐个检查；
+    /// 不支持按查询查找的存储返回 None，由调用方遍历所有任务
+    fn find(&self, _query: &Query) -> Option<Result<Vec<usize>, Box<dyn Error>>> {
+        None
+    }
+}
+
+/// todos.json 中保存的内容，操作记录单独保存
+#[derive(Serialize, Deserialize)]
+struct JsonTodos {
+    /// 文件格式的版本，旧版本的文件读取时先升级
+    version: u64,
+    todos: Vec<Todo>,
+    next_id: usize,
+}
+
+/// JSON 文件存储，每次保存时重写整个文件
+pub struct JsonStorage {
     file_path: String,
 }
 
-impl Storage {
+impl JsonStorage {
     pub fn new(file_path: &str) -> Self {
-        Storage {
+        JsonStorage {
             file_path: file_path.to_string(),
         }
     }
@@ -63,6 +104,7 @@ impl Storage {
     }
     
     /// 删除文件
+    #[allow(dead_code)]
     pub fn delete(&self) -> Result<(), Box<dyn std::error::Error>> {
         if self.exists() {
             fs::remove_file(&self.file_path)?;
@@ -71,12 +113,14 @@ impl Storage {
     }
     
     /// 获取文件大小
+    #[allow(dead_code)]
     pub fn file_size(&self) -> Result<u64, Box<dyn std::error::Error>> {
         let metadata = fs::metadata(&self.file_path)?;
         Ok(metadata.len())
     }
     
     /// 获取文件修改时间
+    #[allow(dead_code)]
     pub fn last_modified(&self) -> Result<std::time::SystemTime, Box<dyn std::error::Error>> {
         let metadata = fs::metadata(&self.file_path)?;
         Ok(metadata.modified()?)
@@ -88,7 +132,7 @@ impl Storage {
             return Err("原文件不存在，无法创建备份".into());
         }
         
-        use std::time::{SystemTime, UNIX_EPOCH};
+        use std::time::UNIX_EPOCH;
         
         let timestamp = SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -102,6 +146,7 @@ impl Storage {
     }
     
     /// 从备份文件恢复
+    #[allow(dead_code)]
     pub fn restore_from_backup(&self, backup_path: &str) -> Result<(), Box<dyn std::error::Error>> {
         if !Path::new(backup_path).exists() {
             return Err(format!("备份文件不存在: {}", backup_path).into());
@@ -112,6 +157,7 @@ impl Storage {
     }
     
     /// 获取所有备份文件
+    #[allow(dead_code)]
     pub fn list_backups(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
         let file_name = Path::new(&self.file_path)
             .file_name()
@@ -152,6 +198,7 @@ impl Storage {
     }
     
     /// 清理旧的备份文件（保留最近的N个）
+    #[allow(dead_code)]
     pub fn cleanup_backups(&self, keep_count: usize) -> Result<usize, Box<dyn std::error::Error>> {
         let backups = self.list_backups()?;
         
@@ -173,6 +220,7 @@ impl Storage {
     }
     
     /// 验证文件完整性（简单的JSON格式检查）
+    #[allow(dead_code)]
     pub fn verify_integrity<T: for<'de> Deserialize<'de>>(&self) -> Result<bool, Box<dyn std::error::Error>> {
         match self.load::<T>() {
             Ok(_) => Ok(true),
@@ -200,9 +248,55 @@ impl Storage {
     }
 }
 
-impl Default for Storage {
+impl JsonStorage {
+    /// 操作记录的文件，与数据文件放在一起，如 todos.json 对应 todos.history.json
+    fn history_storage(&self) -> JsonStorage {
+        let path = Path::new(&self.file_path).with_extension("history.json");
+        JsonStorage::new(&path.to_string_lossy())
+    }
+}
+
+impl Storage for JsonStorage {
+    fn load_todos(&self) -> Result<TodosData, Box<dyn Error>> {
+        let mut document: serde_json::Value = self.load()?;
+        
+        // 旧版本的文件先备份原样内容再升级，升级成功后写回；升级失败时原文件不变，备份也已保留
+        let version = migrations::version(&document).map_err(UpgradeFailed)?;
+        if version < CURRENT_VERSION {
+            self.create_backup()?;
+        }
+        migrations::upgrade(&mut document)?;
+        if version < CURRENT_VERSION {
+            self.save(&document)?;
+        }
+        
+        let data: JsonTodos = serde_json::from_value(document)?;
+        
+        // 没有操作记录时从空记录开始；记录损坏时给出警告，不影响任务数据
+        let history_storage = self.history_storage();
+        let history = if history_storage.exists() {
+            history_storage.load().unwrap_or_else(|e| {
+                eprintln!("警告: 操作记录已损坏，撤销记录将从空开始: {}", e);
+                History::default()
+            })
+        } else {
+            History::default()
+        };
+        
+        Ok(TodosData { todos: data.todos, next_id: data.next_id, history })
+    }
+    
+    fn save_todos(&self, data: &TodosData, _changed: &BTreeSet<usize>) -> Result<(), Box<dyn Error>> {
+        self.save(&JsonTodos { version: CURRENT_VERSION, todos: data.todos.clone(), next_id: data.next_id })?;
+        
+        // 操作记录里已经有每个任务的历史状态，不再额外备份
+        self.history_storage().save_compressed(&data.history)
+    }
+}
+
+impl Default for JsonStorage {
     fn default() -> Self {
-        Storage::new("data.json")
+        JsonStorage::new("data.json")
     }
 }
 
@@ -222,7 +316,7 @@ mod tests {
     fn test_save_and_load() {
         let temp_file = NamedTempFile::new().unwrap();
         let file_path = temp_file.path().to_str().unwrap();
-        let storage = Storage::new(file_path);
+        let storage = JsonStorage::new(file_path);
         
         let test_data = TestData {
             name: "test".to_string(),
@@ -240,7 +334,7 @@ mod tests {
     
     #[test]
     fn test_file_not_exists() {
-        let storage = Storage::new("non_existent_file.json");
+        let storage = JsonStorage::new("non_existent_file.json");
         
         assert!(!storage.exists());
         
@@ -252,7 +346,7 @@ mod tests {
     fn test_backup_operations() {
         let temp_file = NamedTempFile::new().unwrap();
         let file_path = temp_file.path().to_str().unwrap();
-        let storage = Storage::new(file_path);
+        let storage = JsonStorage::new(file_path);
         
         let test_data = TestData {
             name: "test".to_string(),
@@ -287,7 +381,7 @@ mod tests {
     fn test_integrity_verification() {
         let temp_file = NamedTempFile::new().unwrap();
         let file_path = temp_file.path().to_str().unwrap();
-        let storage = Storage::new(file_path);
+        let storage = JsonStorage::new(file_path);
         
         let test_data = TestData {
             name: "test".to_string(),
@@ -307,7 +401,7 @@ mod tests {
     fn test_compressed_save() {
         let temp_file = NamedTempFile::new().unwrap();
         let file_path = temp_file.path().to_str().unwrap();
-        let storage = Storage::new(file_path);
+        let storage = JsonStorage::new(file_path);
         
         let test_data = TestData {
             name: "test".to_string(),
@@ -325,4 +419,129 @@ mod tests {
         let size = storage.file_size().unwrap();
         assert!(size > 0);
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_todos_round_trip() {
+        use crate::todo::{Priority, TodoManager};
+        
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todos.json");
+        let storage = JsonStorage::new(path.to_str().unwrap());
+        
+        let mut manager = TodoManager::new();
+        manager.add_todo("学习Rust +学习".to_string(), Priority::High);
+        manager.add_todo("写代码".to_string(), Priority::Low);
+        manager.remove_todo(2);
+        let data = TodosData {
+            todos: manager.get_all_todos().into_iter().cloned().collect(),
+            next_id: 3,
+            history: History::default(),
+        };
+        storage.save_todos(&data, &BTreeSet::new()).unwrap();
+        
+        // 操作记录保存在数据文件旁边，记录损坏时不影响读取任务
+        assert!(dir.path().join("todos.history.json").exists());
+        let loaded = storage.load_todos().unwrap();
+        assert_eq!((loaded.todos, loaded.next_id), (data.todos, 3));
+        
+        fs::write(dir.path().join("todos.history.json"), "invalid json").unwrap();
+        assert_eq!(storage.load_todos().unwrap().next_id, 3);
+    }
+    
+    /// 把 fixtures 中的数据文件复制到临时目录后读取，返回读取结果和目录中的备份文件
+    fn load_fixture(fixture: &str) -> (TodosData, Vec<String>, String) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todos.json");
+        fs::write(&path, fixture).unwrap();
+        
+        let data = JsonStorage::new(path.to_str().unwrap()).load_todos().unwrap();
+        let backups = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.to_string_lossy().contains(".backup."))
+            .map(|path| fs::read_to_string(path).unwrap())
+            .collect();
+        (data, backups, fs::read_to_string(&path).unwrap())
+    }
+    
+    #[test]
+    fn test_load_every_format_version() {
+        let fixtures = [
+            include_str!("../fixtures/todos-v0-baseline.json"),
+            include_str!("../fixtures/todos-v0-due.json"),
+            include_str!("../fixtures/todos-v0-recurrence.json"),
+            include_str!("../fixtures/todos-v0-tags.json"),
+            include_str!("../fixtures/todos-v0-subtasks.json"),
+        ];
+        for fixture in fixtures {
+            // 旧文件升级前备份原样内容，升级后写回当前版本
+            let (_, backups, upgraded) = load_fixture(fixture);
+            assert_eq!(backups, [fixture]);
+            let document: serde_json::Value = serde_json::from_str(&upgraded).unwrap();
+            assert_eq!(document["version"], CURRENT_VERSION);
+        }
+        
+        // 最早的格式：描述中的标签在升级时取出
+        let (data, _, _) = load_fixture(fixtures[0]);
+        assert_eq!(data.next_id, 3);
+        assert_eq!(data.todos[1].description, "给妈妈打电话");
+        assert_eq!(data.todos[1].tags.iter().collect::<Vec<_>>(), ["+家庭", "@phone"]);
+        assert_eq!(data.todos[1].completed_at.as_deref(), Some("1640003600"));
+        
+        let (data, _, _) = load_fixture(fixtures[1]);
+        assert!(data.todos[0].due.is_some_and(|due| due.all_day));
+        assert_eq!(data.todos[1].description, "买牛奶");
+        
+        let (data, _, _) = load_fixture(fixtures[2]);
+        assert_eq!(data.todos[1].recurrence, Some("weekly:fri".parse().unwrap()));
+        assert_eq!(data.todos[1].tags.len(), 1);
+        
+        // 已经有标签字段的任务不再从描述中取标签
+        let (data, _, _) = load_fixture(fixtures[3]);
+        assert_eq!(data.todos[1].description, "+阅读");
+        assert!(data.todos[1].tags.is_empty());
+        
+        let (old, _, _) = load_fixture(fixtures[4]);
+        assert_eq!(old.todos[2].parent, Some(1));
+        assert!(old.todos[2].blocked_by.contains(&2));
+        
+        // 当前版本直接读取，不备份也不改写
+        let current = include_str!("../fixtures/todos-v1.json");
+        let (data, backups, content) = load_fixture(current);
+        assert!(backups.is_empty());
+        assert_eq!(content, current);
+        assert_eq!(data.todos, old.todos);
+    }
+    
+    #[test]
+    fn test_load_failed_upgrade() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todos.json");
+        let fixture = include_str!("../fixtures/todos-v0-broken.json");
+        fs::write(&path, fixture).unwrap();
+        
+        // 升级失败时返回 UpgradeFailed，原文件不变，升级前已经备份
+        let error = JsonStorage::new(path.to_str().unwrap()).load_todos().unwrap_err();
+        assert!(error.is::<UpgradeFailed>());
+        assert!(error.to_string().contains("任务缺少描述"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), fixture);
+        let backups: Vec<String> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.to_string_lossy().contains(".backup."))
+            .map(|path| fs::read_to_string(path).unwrap())
+            .collect();
+        assert_eq!(backups, [fixture]);
+    }
+    
+    #[test]
+    fn test_load_newer_version() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let content = format!(r#"{{"version": {}, "todos": [], "next_id": 1}}"#, CURRENT_VERSION + 1);
+        fs::write(temp_file.path(), &content).unwrap();
+        
+        let error = JsonStorage::new(temp_file.path().to_str().unwrap()).load_todos().unwrap_err();
+        assert!(error.to_string().contains("请升级程序"));
+        assert_eq!(fs::read_to_string(temp_file.path()).unwrap(), content);
+    }
+}
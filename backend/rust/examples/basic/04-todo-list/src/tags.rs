@@ -0,0 +1,62 @@
+use std::collections::BTreeSet;
+
+/// 检查是否是标签：`+项目` 或 `@场景`，名称以字母（含中文）或下划线开头
+pub fn is_tag(word: &str) -> bool {
+    let mut chars = word.chars();
+    matches!(chars.next(), Some('+' | '@'))
+        && chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && !word.chars().any(char::is_whitespace)
+}
+
+/// 解析用户给出的标签，格式不对时返回错误
+pub fn parse_tag(word: &str) -> Result<String, String> {
+    if is_tag(word) {
+        Ok(word.to_string())
+    } else {
+        Err(format!("无效的标签: {} (标签以 + 表示项目、@ 表示场景，如 +work、@home)", word))
+    }
+}
+
+/// 从描述中取出标签，返回去掉标签后的描述
+pub fn extract_tags(description: &str) -> (String, BTreeSet<String>) {
+    let mut tags = BTreeSet::new();
+    let mut words = Vec::new();
+
+    for word in description.split_whitespace() {
+        if is_tag(word) {
+            tags.insert(word.to_string());
+        } else {
+            words.push(word);
+        }
+    }
+
+    // 描述全是标签时保留原文，避免得到空的任务
+    if words.is_empty() {
+        return (description.trim().to_string(), BTreeSet::new());
+    }
+
+    if tags.is_empty() {
+        (description.to_string(), tags)
+    } else {
+        (words.join(" "), tags)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(tags: &[&str]) -> BTreeSet<String> {
+        tags.iter().map(|tag| tag.to_string()).collect()
+    }
+
+    #[test]
+    fn test_extract_tags() {
+        assert_eq!(extract_tags("给妈妈 @phone 打电话 +家庭"), ("给妈妈 打电话".to_string(), set(&["+家庭", "@phone"])));
+        assert_eq!(extract_tags("涨价 +3 元 a@b.com"), ("涨价 +3 元 a@b.com".to_string(), set(&[])));
+        assert_eq!(extract_tags("+work @office"), ("+work @office".to_string(), set(&[])));
+        assert!(parse_tag("work").is_err());
+        assert!(parse_tag("@").is_err());
+        assert!(parse_tag("+_inbox").is_ok());
+    }
+}
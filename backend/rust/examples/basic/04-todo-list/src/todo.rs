@@ -1,10 +1,19 @@
+use chrono::{DateTime, Duration, Local};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fmt;
+use std::str::FromStr;
 
-use crate::storage::Storage;
+use crate::due::{self, Due};
+use crate::history::{History, Operation};
+use crate::query::{Query, Selector};
+use crate::recurrence::Recurrence;
+use crate::storage::{JsonStorage, Storage, TodosData};
+use crate::tags;
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// 按紧急程度从低到高排列，可以直接比较大小
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum Priority {
     Low,
     Medium,
@@ -24,7 +33,21 @@ impl fmt::Display for Priority {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl FromStr for Priority {
+    type Err = String;
+    
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.to_lowercase().as_str() {
+            "low" | "l" | "低" => Ok(Priority::Low),
+            "medium" | "m" | "中" => Ok(Priority::Medium),
+            "high" | "h" | "高" => Ok(Priority::High),
+            "urgent" | "u" | "紧急" => Ok(Priority::Urgent),
+            _ => Err(format!("无效的优先级: {} (可选: low, medium, high, urgent)", text)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Todo {
     pub id: usize,
     pub description: String,
@@ -32,10 +55,24 @@ pub struct Todo {
     pub priority: Priority,
     pub created_at: String,
     pub completed_at: Option<String>,
+    #[serde(default)]
+    pub due: Option<Due>,
+    #[serde(default)]
+    pub recurrence: Option<Recurrence>,
+    /// `+项目` 和 `@场景` 标签
+    #[serde(default)]
+    pub tags: BTreeSet<String>,
+    /// 父任务 ID
+    #[serde(default)]
+    pub parent: Option<usize>,
+    /// 必须先完成的任务 ID
+    #[serde(default)]
+    pub blocked_by: BTreeSet<usize>,
 }
 
 impl Todo {
     pub fn new(id: usize, description: String, priority: Priority) -> Self {
+        let (description, tags) = tags::extract_tags(&description);
         Todo {
             id,
             description,
@@ -43,6 +80,11 @@ impl Todo {
             priority,
             created_at: get_current_time(),
             completed_at: None,
+            due: None,
+            recurrence: None,
+            tags,
+            parent: None,
+            blocked_by: BTreeSet::new(),
         }
     }
     
@@ -60,8 +102,24 @@ impl Todo {
         self.priority = priority;
     }
     
+    /// 新描述中的标签会加到已有的标签上
     pub fn edit_description(&mut self, new_description: String) {
-        self.description = new_description;
+        let (description, tags) = tags::extract_tags(&new_description);
+        self.description = description;
+        self.tags.extend(tags);
+    }
+    
+    pub fn set_due(&mut self, due: Option<Due>) {
+        self.due = due;
+    }
+    
+    pub fn set_recurrence(&mut self, recurrence: Option<Recurrence>) {
+        self.recurrence = recurrence;
+    }
+    
+    /// 未完成且已过截止时间
+    pub fn is_overdue(&self, now: i64) -> bool {
+        !self.completed && self.due.is_some_and(|due| due.is_past(now))
     }
 }
 
@@ -79,6 +137,21 @@ impl fmt::Display for Todo {
             self.description
         )?;
         
+        for tag in &self.tags {
+            write!(f, " {}", tag)?;
+        }
+        
+        if let Some(due) = &self.due {
+            write!(f, " ⏰ {}", due)?;
+            if self.is_overdue(due::now()) {
+                write!(f, " (已逾期)")?;
+            }
+        }
+        
+        if let Some(recurrence) = &self.recurrence {
+            write!(f, " 🔁 {}", recurrence)?;
+        }
+        
         if self.completed {
             if let Some(completed_time) = &self.completed_at {
                 write!(f, " (完成于: {})", completed_time)?;
@@ -91,120 +164,597 @@ impl fmt::Display for Todo {
     }
 }
 
+/// 列表的排序方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortBy {
+    /// 按优先级（默认）
+    Priority,
+    /// 按截止时间，没有截止时间的排在最后
+    Due,
+}
+
+/// 按截止时间过滤
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DueFilter {
+    /// 已过截止时间
+    Overdue,
+    /// 今天到期
+    Today,
+    /// 今天之后若干天内到期
+    Upcoming(i64),
+}
+
+impl fmt::Display for DueFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DueFilter::Overdue => write!(f, "已逾期"),
+            DueFilter::Today => write!(f, "今天到期"),
+            DueFilter::Upcoming(days) => write!(f, "{} 天内到期", days),
+        }
+    }
+}
+
 pub struct TodoManager {
     todos: HashMap<usize, Todo>,
     next_id: usize,
-    storage: Storage,
+    storage: Box<dyn Storage>,
+    /// 操作记录，与任务一起保存
+    history: History,
+    /// 上次读取或保存之后变化过的任务 ID
+    dirty: BTreeSet<usize>,
+    /// 正在记录一次操作，其中调用的其他修改不再单独记录
+    recording: bool,
 }
 
 impl TodoManager {
+    /// 使用当前目录 todos.json 的管理器；命令行按配置选择存储，不再直接使用它
+    #[allow(dead_code)]
     pub fn new() -> Self {
+        Self::with_storage(Box::new(JsonStorage::new("todos.json")))
+    }
+    
+    pub fn with_storage(storage: Box<dyn Storage>) -> Self {
         TodoManager {
             todos: HashMap::new(),
             next_id: 1,
-            storage: Storage::new("todos.json"),
+            storage,
+            history: History::default(),
+            dirty: BTreeSet::new(),
+            recording: false,
+        }
+    }
+    
+    /// 把 f 中的所有修改记录为一次可以撤销的操作
+    ///
+    /// 嵌套调用时只有最外层会被记录，批量操作因此可以一次撤销
+    pub fn record<T>(&mut self, label: String, f: impl FnOnce(&mut Self) -> T) -> T {
+        if self.recording {
+            return f(self);
+        }
+        
+        let before = self.todos.clone();
+        let next_id = self.next_id;
+        self.recording = true;
+        let result = f(self);
+        self.recording = false;
+        
+        if let Some(operation) = Operation::diff(label, &before, &self.todos, (next_id, self.next_id)) {
+            self.dirty.extend(operation.changes.iter().map(|change| change.id));
+            self.history.push(operation);
+        }
+        result
+    }
+    
+    /// 撤销最近的一次操作，返回操作的说明
+    pub fn undo(&mut self) -> Result<String, String> {
+        let operation = self.history.pop_done().ok_or("没有可以撤销的操作")?;
+        if let Err(e) = operation.apply(&mut self.todos, &mut self.next_id, true) {
+            self.history.redone(operation);
+            return Err(e);
+        }
+        let label = operation.label.clone();
+        self.dirty.extend(operation.changes.iter().map(|change| change.id));
+        self.history.undone(operation);
+        Ok(label)
+    }
+    
+    /// 重做最近撤销的一次操作，返回操作的说明
+    pub fn redo(&mut self) -> Result<String, String> {
+        let operation = self.history.pop_undone().ok_or("没有可以重做的操作")?;
+        if let Err(e) = operation.apply(&mut self.todos, &mut self.next_id, false) {
+            self.history.undone(operation);
+            return Err(e);
         }
+        let label = operation.label.clone();
+        self.dirty.extend(operation.changes.iter().map(|change| change.id));
+        self.history.redone(operation);
+        Ok(label)
     }
     
     pub fn add_todo(&mut self, description: String, priority: Priority) -> usize {
-        let todo = Todo::new(self.next_id, description, priority);
-        let id = todo.id;
-        self.todos.insert(id, todo);
-        self.next_id += 1;
-        id
+        self.record(format!("添加 #{} {}", self.next_id, description), |manager| {
+            let todo = Todo::new(manager.next_id, description, priority);
+            let id = todo.id;
+            manager.todos.insert(id, todo);
+            manager.next_id += 1;
+            id
+        })
     }
     
+    /// 删除任务；它的子任务移到它的父任务下，其他任务对它的依赖一并去掉
     pub fn remove_todo(&mut self, id: usize) -> bool {
-        self.todos.remove(&id).is_some()
+        self.record(format!("删除 #{}", id), |manager| {
+            match manager.todos.remove(&id) {
+                Some(removed) => {
+                    manager.forget(&[removed]);
+                    true
+                },
+                None => false,
+            }
+        })
     }
     
+    #[allow(dead_code)]
     pub fn complete_todo(&mut self, id: usize) -> bool {
-        if let Some(todo) = self.todos.get_mut(&id) {
+        self.complete_with_next(id, false).is_ok()
+    }
+    
+    /// 完成任务，重复任务同时生成下一次，返回新任务的 ID
+    ///
+    /// 还有未完成的子任务或依赖时拒绝；force 时忽略依赖，并把子任务一起完成
+    pub fn complete_with_next(&mut self, id: usize, force: bool) -> Result<Option<usize>, String> {
+        self.record(format!("完成 #{}", id), |manager| {
+            let todo = manager.todos.get(&id).ok_or("找不到指定的任务ID")?;
+            let open_children: Vec<usize> = manager.descendants(id)
+                .into_iter()
+                .filter(|child| !child.completed)
+                .map(|child| child.id)
+                .collect();
+            
+            if !force && !todo.completed {
+                if !open_children.is_empty() {
+                    return Err(format!("还有 {} 个子任务未完成，使用 --force 一起完成", open_children.len()));
+                }
+                let blockers = manager.open_blockers(todo);
+                if !blockers.is_empty() {
+                    return Err(format!("需要先完成 {}，使用 --force 强制完成", format_ids(&blockers)));
+                }
+            }
+            
+            for child in open_children {
+                if let Some(child) = manager.todos.get_mut(&child) {
+                    child.complete();
+                }
+            }
+            
+            let todo = manager.todos.get_mut(&id).ok_or("找不到指定的任务ID")?;
+            let was_completed = todo.completed;
             todo.complete();
-            true
-        } else {
-            false
+            
+            // 再次完成已完成的任务不会重复生成
+            let next = match &todo.recurrence {
+                Some(recurrence) if !was_completed => {
+                    let mut next = Todo::new(manager.next_id, todo.description.clone(), todo.priority.clone());
+                    next.due = Some(recurrence.next_due(todo.due, Local::now()));
+                    next.recurrence = Some(recurrence.clone());
+                    next.tags = todo.tags.clone();
+                    next.parent = todo.parent;
+                    next
+                },
+                _ => return Ok(None),
+            };
+            
+            let next_id = next.id;
+            manager.todos.insert(next_id, next);
+            manager.next_id += 1;
+            Ok(Some(next_id))
+        })
+    }
+    
+    /// 批量完成任务，返回每个任务的结果
+    ///
+    /// 子任务和依赖也在其中时会先完成它们，顺序与 ids 无关
+    pub fn complete_many(&mut self, ids: &[usize], force: bool) -> Vec<(usize, Result<Option<usize>, String>)> {
+        self.record(format!("完成 {}", format_ids(ids)), |manager| {
+            let mut results: Vec<(usize, Result<Option<usize>, String>)> = ids.iter().map(|id| (*id, Err(String::new()))).collect();
+            let mut pending: Vec<usize> = (0..ids.len()).collect();
+            
+            // 每一轮都完成当前能完成的，直到没有进展
+            loop {
+                let before = pending.len();
+                pending.retain(|index| {
+                    let result = manager.complete_with_next(ids[*index], force);
+                    let done = result.is_ok();
+                    results[*index].1 = result;
+                    !done
+                });
+                if pending.is_empty() || pending.len() == before {
+                    break;
+                }
+            }
+            
+            results
+        })
+    }
+    
+    /// 设置父任务，None 表示移到顶层；不能把任务放到它自己或它的子任务下面
+    pub fn set_parent(&mut self, id: usize, parent: Option<usize>) -> Result<(), String> {
+        self.record(format!("设置 #{} 的父任务", id), |manager| {
+            if !manager.todos.contains_key(&id) {
+                return Err("找不到指定的任务ID".to_string());
+            }
+            
+            if let Some(parent) = parent {
+                if !manager.todos.contains_key(&parent) {
+                    return Err(format!("找不到父任务 #{}", parent));
+                }
+                
+                let mut ancestor = Some(parent);
+                while let Some(current) = ancestor {
+                    if current == id {
+                        return Err(format!("#{} 是 #{} 自身或它的子任务，不能作为父任务", parent, id));
+                    }
+                    ancestor = manager.todos.get(&current).and_then(|todo| todo.parent);
+                }
+            }
+            
+            if let Some(todo) = manager.todos.get_mut(&id) {
+                todo.parent = parent;
+            }
+            Ok(())
+        })
+    }
+    
+    /// 记录 id 要等 blocker 完成；会形成循环依赖时拒绝
+    pub fn add_dependency(&mut self, id: usize, blocker: usize) -> Result<(), String> {
+        self.record(format!("让 #{} 依赖 #{}", id, blocker), |manager| {
+            if !manager.todos.contains_key(&id) {
+                return Err("找不到指定的任务ID".to_string());
+            }
+            if !manager.todos.contains_key(&blocker) {
+                return Err(format!("找不到依赖的任务 #{}", blocker));
+            }
+            
+            // 从 blocker 沿依赖往前找，能走到 id 就说明会成环
+            let mut pending = vec![blocker];
+            let mut visited = HashSet::new();
+            while let Some(current) = pending.pop() {
+                if current == id {
+                    return Err(format!("#{} 已经直接或间接依赖 #{}，不能形成循环依赖", blocker, id));
+                }
+                if visited.insert(current) {
+                    if let Some(todo) = manager.todos.get(&current) {
+                        pending.extend(todo.blocked_by.iter().copied());
+                    }
+                }
+            }
+            
+            if let Some(todo) = manager.todos.get_mut(&id) {
+                todo.blocked_by.insert(blocker);
+            }
+            Ok(())
+        })
+    }
+    
+    pub fn remove_dependency(&mut self, id: usize, blocker: usize) -> bool {
+        self.record(format!("移除 #{} 对 #{} 的依赖", id, blocker), |manager| {
+            match manager.todos.get_mut(&id) {
+                Some(todo) => todo.blocked_by.remove(&blocker),
+                None => false,
+            }
+        })
+    }
+    
+    /// 直接子任务，按优先级排序
+    pub fn children(&self, id: usize) -> Vec<&Todo> {
+        let mut children: Vec<&Todo> = self.todos
+            .values()
+            .filter(|todo| todo.parent == Some(id))
+            .collect();
+        children.sort_by(|a, b| compare_by_priority(a, b));
+        children
+    }
+    
+    /// 所有层级的子任务
+    pub fn descendants(&self, id: usize) -> Vec<&Todo> {
+        let mut descendants = Vec::new();
+        let mut pending = vec![id];
+        let mut visited = HashSet::from([id]);
+        
+        while let Some(current) = pending.pop() {
+            for child in self.children(current) {
+                if visited.insert(child.id) {
+                    descendants.push(child);
+                    pending.push(child.id);
+                }
+            }
         }
+        
+        descendants
     }
     
-    pub fn reopen_todo(&mut self, id: usize) -> bool {
-        if let Some(todo) = self.todos.get_mut(&id) {
-            todo.reopen();
-            true
-        } else {
-            false
+    /// 子任务的完成进度 (已完成, 总数)，包括所有层级；没有子任务时为 None
+    pub fn progress(&self, id: usize) -> Option<(usize, usize)> {
+        let descendants = self.descendants(id);
+        if descendants.is_empty() {
+            return None;
         }
+        let completed = descendants.iter().filter(|todo| todo.completed).count();
+        Some((completed, descendants.len()))
     }
     
-    pub fn edit_todo(&mut self, id: usize, new_description: String) -> bool {
-        if let Some(todo) = self.todos.get_mut(&id) {
-            todo.edit_description(new_description);
-            true
-        } else {
-            false
+    /// 尚未完成的依赖，已删除的任务不算
+    pub fn open_blockers(&self, todo: &Todo) -> Vec<usize> {
+        todo.blocked_by
+            .iter()
+            .copied()
+            .filter(|id| self.todos.get(id).is_some_and(|blocker| !blocker.completed))
+            .collect()
+    }
+    
+    /// 任务后面附加的进度、阻塞和父任务信息
+    fn relations(&self, todo: &Todo) -> String {
+        let mut text = String::new();
+        
+        if let Some((completed, total)) = self.progress(todo.id) {
+            text.push_str(&format!(" 📂 {}/{}", completed, total));
+        }
+        
+        let blockers = self.open_blockers(todo);
+        if !todo.completed && !blockers.is_empty() {
+            text.push_str(&format!(" ⛔ 等待 {}", format_ids(&blockers)));
+        }
+        
+        text
+    }
+    
+    /// 树状显示任务层级；给出 root 时只显示这个任务及其子任务
+    pub fn print_tree(&self, root: Option<usize>) -> Result<(), String> {
+        let roots: Vec<&Todo> = match root {
+            Some(id) => vec![self.todos.get(&id).ok_or("找不到指定的任务ID")?],
+            None => {
+                // 父任务已经不存在的也当作顶层
+                let mut roots: Vec<&Todo> = self.todos
+                    .values()
+                    .filter(|todo| todo.parent.is_none_or(|parent| !self.todos.contains_key(&parent)))
+                    .collect();
+                roots.sort_by(|a, b| compare_by_priority(a, b));
+                roots
+            },
+        };
+        
+        if roots.is_empty() {
+            println!("📝 没有待办事项");
+            return Ok(());
+        }
+        
+        println!("🌳 任务层级:");
+        println!("{}", "=".repeat(80));
+        
+        for todo in roots {
+            println!("{}{}", todo.to_string().trim_start(), self.relations(todo));
+            self.print_children(todo.id, "");
         }
+        
+        Ok(())
+    }
+    
+    fn print_children(&self, id: usize, indent: &str) {
+        let children = self.children(id);
+        let count = children.len();
+        
+        for (index, child) in children.into_iter().enumerate() {
+            let last = index + 1 == count;
+            let branch = if last { "└── " } else { "├── " };
+            println!("{}{}{}{}", indent, branch, child.to_string().trim_start(), self.relations(child));
+            
+            let indent = format!("{}{}", indent, if last { "    " } else { "│   " });
+            self.print_children(child.id, &indent);
+        }
+    }
+    
+    /// 删除任务后整理剩下任务的父任务和依赖
+    fn forget(&mut self, removed: &[Todo]) {
+        let parents: HashMap<usize, Option<usize>> = removed.iter().map(|todo| (todo.id, todo.parent)).collect();
+        
+        for todo in self.todos.values_mut() {
+            // 父任务的父任务可能也一起被删除了，一直往上找到还在的
+            while let Some(grandparent) = todo.parent.and_then(|parent| parents.get(&parent)) {
+                todo.parent = *grandparent;
+            }
+            todo.blocked_by.retain(|id| !parents.contains_key(id));
+        }
+    }
+    
+    pub fn reopen_todo(&mut self, id: usize) -> bool {
+        self.record(format!("重新打开 #{}", id), |manager| {
+            if let Some(todo) = manager.todos.get_mut(&id) {
+                todo.reopen();
+                true
+            } else {
+                false
+            }
+        })
+    }
+    
+    pub fn edit_todo(&mut self, id: usize, new_description: String) -> bool {
+        self.record(format!("编辑 #{}", id), |manager| {
+            if let Some(todo) = manager.todos.get_mut(&id) {
+                todo.edit_description(new_description);
+                true
+            } else {
+                false
+            }
+        })
     }
     
     pub fn set_priority(&mut self, id: usize, priority: Priority) -> bool {
-        if let Some(todo) = self.todos.get_mut(&id) {
-            todo.set_priority(priority);
-            true
-        } else {
-            false
+        self.record(format!("设置 #{} 的优先级为 {}", id, priority), |manager| {
+            if let Some(todo) = manager.todos.get_mut(&id) {
+                todo.set_priority(priority);
+                true
+            } else {
+                false
+            }
+        })
+    }
+    
+    pub fn set_due(&mut self, id: usize, due: Option<Due>) -> bool {
+        self.record(format!("设置 #{} 的截止时间", id), |manager| {
+            if let Some(todo) = manager.todos.get_mut(&id) {
+                todo.set_due(due);
+                true
+            } else {
+                false
+            }
+        })
+    }
+    
+    pub fn set_recurrence(&mut self, id: usize, recurrence: Option<Recurrence>) -> bool {
+        self.record(format!("设置 #{} 的重复规则", id), |manager| {
+            if let Some(todo) = manager.todos.get_mut(&id) {
+                todo.set_recurrence(recurrence);
+                true
+            } else {
+                false
+            }
+        })
+    }
+    
+    pub fn add_tags(&mut self, id: usize, tags: &[String]) -> bool {
+        self.record(format!("给 #{} 添加标签 {}", id, tags.join(" ")), |manager| {
+            if let Some(todo) = manager.todos.get_mut(&id) {
+                todo.tags.extend(tags.iter().cloned());
+                true
+            } else {
+                false
+            }
+        })
+    }
+    
+    pub fn remove_tags(&mut self, id: usize, tags: &[String]) -> bool {
+        self.record(format!("移除 #{} 的标签 {}", id, tags.join(" ")), |manager| {
+            if let Some(todo) = manager.todos.get_mut(&id) {
+                todo.tags.retain(|tag| !tags.contains(tag));
+                true
+            } else {
+                false
+            }
+        })
+    }
+    
+    /// 把 sources 中的标签都换成 target，返回受影响的任务数；只给一个来源时就是重命名
+    pub fn merge_tags(&mut self, sources: &[String], target: &str) -> usize {
+        self.record(format!("把 {} 合并为 {}", sources.join("、"), target), |manager| {
+            let mut changed = 0;
+            
+            for todo in manager.todos.values_mut() {
+                let before = todo.tags.len();
+                todo.tags.retain(|tag| !sources.contains(tag));
+                if todo.tags.len() != before {
+                    todo.tags.insert(target.to_string());
+                    changed += 1;
+                }
+            }
+            
+            changed
+        })
+    }
+    
+    /// 每个标签的 (任务数, 已完成数)
+    pub fn tag_counts(&self) -> BTreeMap<&str, (usize, usize)> {
+        count_tags(self.todos.values())
+    }
+    
+    /// 选中的任务 ID，按 ID 排序
+    pub fn select(&self, selector: &Selector) -> Vec<usize> {
+        let mut ids: Vec<usize> = self.todos
+            .values()
+            .filter(|todo| selector.matches(todo, self))
+            .map(|todo| todo.id)
+            .collect();
+        ids.sort();
+        ids
+    }
+    
+    /// 符合查询的任务，未完成的在前并按优先级排序
+    pub fn query(&self, query: &Query) -> Vec<&Todo> {
+        // 内存中的任务与存储一致时先由存储按索引找出候选任务
+        let candidates: Vec<&Todo> = match self.dirty.is_empty().then(|| self.storage.find(query)).flatten() {
+            Some(Ok(ids)) => ids.iter().filter_map(|id| self.todos.get(id)).collect(),
+            _ => self.todos.values().collect(),
+        };
+        let mut todos: Vec<&Todo> = candidates
+            .into_iter()
+            .filter(|todo| query.matches(todo, self))
+            .collect();
+        todos.sort_by(|a, b| compare_by_priority(a, b));
+        todos
+    }
+    
+    /// 显示最近 limit 次操作和可以重做的操作
+    pub fn show_history(&self, limit: usize) {
+        if self.history.done().next().is_none() && self.history.redoable().next().is_none() {
+            println!("📜 还没有操作记录");
+            return;
+        }
+        
+        println!("📜 操作记录 (最新的在前):");
+        println!("{}", "=".repeat(60));
+        for (index, operation) in self.history.done().take(limit).enumerate() {
+            println!("{:>4}. {}", index + 1, operation);
+        }
+        
+        let redoable: Vec<_> = self.history.redoable().take(limit).collect();
+        if !redoable.is_empty() {
+            println!();
+            println!("可以重做 (redo):");
+            for operation in redoable {
+                println!("    - {}", operation);
+            }
         }
     }
     
-    pub fn list_todos(&self) {
+    pub fn list_todos(&self, query: &Query, sort: SortBy) {
         if self.todos.is_empty() {
             println!("📝 没有待办事项");
             return;
         }
         
-        println!("📋 所有待办事项:");
+        let mut todos = self.query(query);
+        if todos.is_empty() {
+            println!("📝 没有符合查询的任务");
+            return;
+        }
+        
+        if query.is_empty() {
+            println!("📋 所有待办事项:");
+        } else {
+            println!("📋 符合查询的待办事项:");
+        }
         println!("{}", "=".repeat(80));
         
-        // 按优先级排序
-        let mut todos: Vec<&Todo> = self.todos.values().collect();
-        todos.sort_by(|a, b| {
-            // 先按完成状态排序（未完成的在前）
-            match a.completed.cmp(&b.completed) {
-                std::cmp::Ordering::Equal => {
-                    // 再按优先级排序（高优先级在前）
-                    match (&b.priority, &a.priority) {
-                        (Priority::Urgent, _) if !matches!(a.priority, Priority::Urgent) => std::cmp::Ordering::Greater,
-                        (_, Priority::Urgent) if !matches!(b.priority, Priority::Urgent) => std::cmp::Ordering::Less,
-                        (Priority::High, _) if matches!(a.priority, Priority::Medium | Priority::Low) => std::cmp::Ordering::Greater,
-                        (_, Priority::High) if matches!(b.priority, Priority::Medium | Priority::Low) => std::cmp::Ordering::Less,
-                        (Priority::Medium, Priority::Low) => std::cmp::Ordering::Greater,
-                        (Priority::Low, Priority::Medium) => std::cmp::Ordering::Less,
-                        _ => a.id.cmp(&b.id), // 最后按ID排序
-                    }
-                }
-                other => other,
-            }
-        });
+        if sort == SortBy::Due {
+            todos.sort_by(|a, b| compare_by_due(a, b));
+        }
         
-        for todo in todos {
-            println!("{}", todo);
+        for todo in &todos {
+            println!("{}{}", todo, self.relations(todo));
         }
         
         println!("{}", "=".repeat(80));
-        println!("总计: {} 个任务", self.todos.len());
+        println!("总计: {} 个任务", todos.len());
     }
     
-    pub fn search_todos(&self, keyword: &str) {
-        let keyword_lower = keyword.to_lowercase();
-        let matching_todos: Vec<&Todo> = self.todos
-            .values()
-            .filter(|todo| todo.description.to_lowercase().contains(&keyword_lower))
-            .collect();
+    pub fn search_todos(&self, query: &Query, text: &str) {
+        let matching_todos = self.query(query);
         
         if matching_todos.is_empty() {
-            println!("🔍 没有找到包含 \"{}\" 的任务", keyword);
+            println!("🔍 没有找到符合 \"{}\" 的任务", text);
             return;
         }
         
-        println!("🔍 搜索结果 (关键词: \"{}\"):", keyword);
+        println!("🔍 搜索结果 (查询: \"{}\"):", text);
         println!("{}", "=".repeat(80));
         
         for todo in matching_todos {
@@ -252,9 +802,55 @@ impl TodoManager {
         }
     }
     
-    pub fn show_statistics(&self) {
-        let total = self.todos.len();
-        let completed = self.todos.values().filter(|todo| todo.completed).count();
+    /// 按截止时间过滤未完成的任务，结果按截止时间排序
+    pub fn todos_by_due(&self, filter: DueFilter, now: DateTime<Local>) -> Vec<&Todo> {
+        let today = now.date_naive();
+        let mut todos: Vec<&Todo> = self.todos
+            .values()
+            .filter(|todo| !todo.completed)
+            .filter(|todo| match (todo.due, filter) {
+                (None, _) => false,
+                (Some(due), DueFilter::Overdue) => due.is_past(now.timestamp()),
+                (Some(due), DueFilter::Today) => due.date() == today,
+                (Some(due), DueFilter::Upcoming(days)) => {
+                    due.date() > today && due.date() <= today + Duration::days(days)
+                }
+            })
+            .collect();
+        todos.sort_by(|a, b| compare_by_due(a, b));
+        todos
+    }
+    
+    pub fn filter_todos_by_due(&self, filter: DueFilter) {
+        let filtered_todos = self.todos_by_due(filter, Local::now());
+        
+        if filtered_todos.is_empty() {
+            println!("📋 没有{}的任务", filter);
+            return;
+        }
+        
+        println!("📋 {}的任务:", filter);
+        println!("{}", "=".repeat(80));
+        
+        for todo in filtered_todos {
+            println!("{}", todo);
+        }
+    }
+    
+    /// 在 now 之后 window 秒内到期或已经逾期的未完成任务，按截止时间排序
+    pub fn due_within(&self, now: i64, window: i64) -> Vec<&Todo> {
+        let mut todos: Vec<&Todo> = self.todos
+            .values()
+            .filter(|todo| !todo.completed && todo.due.is_some_and(|due| due.at <= now + window))
+            .collect();
+        todos.sort_by(|a, b| compare_by_due(a, b));
+        todos
+    }
+    
+    pub fn show_statistics(&self, query: &Query) {
+        let todos = self.query(query);
+        let total = todos.len();
+        let completed = todos.iter().filter(|todo| todo.completed).count();
         let pending = total - completed;
         
         let mut priority_stats = HashMap::new();
@@ -263,17 +859,27 @@ impl TodoManager {
         priority_stats.insert(Priority::Medium, 0);
         priority_stats.insert(Priority::Low, 0);
         
-        for todo in self.todos.values() {
+        for todo in &todos {
             if !todo.completed {
                 *priority_stats.get_mut(&todo.priority).unwrap() += 1;
             }
         }
         
-        println!("📊 待办事项统计:");
+        if query.is_empty() {
+            println!("📊 待办事项统计:");
+        } else {
+            println!("📊 待办事项统计 (符合查询的任务):");
+        }
         println!("{}", "=".repeat(40));
         println!("总任务数: {}", total);
         println!("已完成: {} ({:.1}%)", completed, if total > 0 { completed as f64 / total as f64 * 100.0 } else { 0.0 });
         println!("未完成: {} ({:.1}%)", pending, if total > 0 { pending as f64 / total as f64 * 100.0 } else { 0.0 });
+        
+        let now = due::now();
+        let overdue = todos.iter().filter(|todo| todo.is_overdue(now)).count();
+        if overdue > 0 {
+            println!("已逾期: {} 个", overdue);
+        }
         println!();
         println!("按优先级分布 (仅未完成):");
         println!("  🔴 紧急: {} 个", priority_stats[&Priority::Urgent]);
@@ -281,6 +887,15 @@ impl TodoManager {
         println!("  🟡 中: {} 个", priority_stats[&Priority::Medium]);
         println!("  🟢 低: {} 个", priority_stats[&Priority::Low]);
         
+        let tag_counts = count_tags(todos.iter().copied());
+        if !tag_counts.is_empty() {
+            println!();
+            println!("按标签分布 (已完成/总数):");
+            for (tag, (total, completed)) in tag_counts {
+                println!("  {}: {}/{}", tag, completed, total);
+            }
+        }
+        
         if total > 0 {
             let completion_rate = completed as f64 / total as f64 * 100.0;
             println!();
@@ -294,32 +909,44 @@ impl TodoManager {
     }
     
     pub fn clear_all(&mut self) {
-        self.todos.clear();
-        self.next_id = 1;
+        self.record("清空所有任务".to_string(), |manager| {
+            manager.todos.clear();
+            manager.next_id = 1;
+        })
     }
     
+    // 文本导入导出和清除已完成任务还没有对应的命令，保留给以后接入
+    #[allow(dead_code)]
     pub fn clear_completed(&mut self) -> usize {
-        let initial_count = self.todos.len();
-        self.todos.retain(|_, todo| !todo.completed);
-        initial_count - self.todos.len()
+        self.record("清除已完成的任务".to_string(), |manager| {
+            let completed: Vec<usize> = manager.todos.values().filter(|todo| todo.completed).map(|todo| todo.id).collect();
+            let removed: Vec<Todo> = completed.iter().filter_map(|id| manager.todos.remove(id)).collect();
+            manager.forget(&removed);
+            removed.len()
+        })
     }
     
-    pub fn save_to_file(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let todos_vec: Vec<&Todo> = self.todos.values().collect();
-        self.storage.save(&TodosData {
-            todos: todos_vec,
+    pub fn save_to_file(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let data = TodosData {
+            todos: self.todos.values().cloned().collect(),
             next_id: self.next_id,
-        })
+            history: self.history.clone(),
+        };
+        self.storage.save_todos(&data, &self.dirty)?;
+        self.dirty.clear();
+        Ok(())
     }
     
     pub fn load_from_file(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let data: TodosData = self.storage.load()?;
+        let data = self.storage.load_todos()?;
         
         self.todos.clear();
         for todo in data.todos {
             self.todos.insert(todo.id, todo);
         }
         self.next_id = data.next_id;
+        self.history = data.history;
+        self.dirty.clear();
         
         Ok(())
     }
@@ -328,48 +955,68 @@ impl TodoManager {
         self.todos.get(&id)
     }
     
+    #[allow(dead_code)]
     pub fn get_all_todos(&self) -> Vec<&Todo> {
         self.todos.values().collect()
     }
     
+    #[allow(dead_code)]
     pub fn import_from_text(&mut self, text: &str) -> usize {
-        let mut imported = 0;
-        
-        for line in text.lines() {
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
-            }
+        self.record("导入任务".to_string(), |manager| {
+            let mut imported = 0;
             
-            // 简单的文本导入格式：[优先级] 任务描述
-            let (priority, description) = if line.starts_with('[') {
-                if let Some(end_bracket) = line.find(']') {
-                    let priority_str = &line[1..end_bracket].to_lowercase();
-                    let priority = match priority_str {
-                        "urgent" | "u" | "紧急" => Priority::Urgent,
-                        "high" | "h" | "高" => Priority::High,
-                        "medium" | "m" | "中" => Priority::Medium,
-                        "low" | "l" | "低" => Priority::Low,
-                        _ => Priority::Medium,
-                    };
-                    let description = line[end_bracket + 1..].trim().to_string();
-                    (priority, description)
+            for line in text.lines() {
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+                
+                // 导出的已完成任务带有 [已完成] 前缀
+                let (completed, line) = match line.strip_prefix("[已完成]") {
+                    Some(rest) => (true, rest.trim_start()),
+                    None => (false, line),
+                };
+                
+                // 简单的文本导入格式：[优先级] 任务描述 [due:<时间>] [repeat:<规则>]
+                let (priority, description) = if line.starts_with('[') {
+                    if let Some(end_bracket) = line.find(']') {
+                        let priority_str = &line[1..end_bracket].to_lowercase();
+                        let priority = match priority_str.as_str() {
+                            "urgent" | "u" | "紧急" => Priority::Urgent,
+                            "high" | "h" | "高" => Priority::High,
+                            "medium" | "m" | "中" => Priority::Medium,
+                            "low" | "l" | "低" => Priority::Low,
+                            _ => Priority::Medium,
+                        };
+                        let description = line[end_bracket + 1..].trim().to_string();
+                        (priority, description)
+                    } else {
+                        (Priority::Medium, line.to_string())
+                    }
                 } else {
                     (Priority::Medium, line.to_string())
+                };
+                
+                let (description, due, recurrence) = split_attributes(&description);
+                
+                if !description.is_empty() {
+                    let id = manager.add_todo(description, priority);
+                    if let Some(todo) = manager.todos.get_mut(&id) {
+                        todo.set_due(due);
+                        todo.set_recurrence(recurrence);
+                        if completed {
+                            todo.complete();
+                        }
+                    }
+                    imported += 1;
                 }
-            } else {
-                (Priority::Medium, line.to_string())
-            };
-            
-            if !description.is_empty() {
-                self.add_todo(description, priority);
-                imported += 1;
             }
-        }
-        
-        imported
+            
+            imported
+        })
     }
     
+    #[allow(dead_code)]
     pub fn export_to_text(&self) -> String {
         let mut result = String::new();
         let mut todos: Vec<&Todo> = self.todos.values().collect();
@@ -385,23 +1032,95 @@ impl TodoManager {
             
             let status = if todo.completed { "[已完成] " } else { "" };
             
-            result.push_str(&format!("{}{} {}\n", status, priority_str, todo.description));
+            result.push_str(&format!("{}{} {}", status, priority_str, todo.description));
+            for tag in &todo.tags {
+                result.push_str(&format!(" {}", tag));
+            }
+            if let Some(due) = &todo.due {
+                result.push_str(&format!(" due:{}", due.to_string().replace(' ', "T")));
+            }
+            if let Some(recurrence) = &todo.recurrence {
+                result.push_str(&format!(" repeat:{}", recurrence.rule()));
+            }
+            result.push('\n');
         }
         
         result
     }
 }
 
-#[derive(Serialize, Deserialize)]
-struct TodosData {
-    todos: Vec<Todo>,
-    next_id: usize,
+pub fn format_ids(ids: &[usize]) -> String {
+    ids.iter().map(|id| format!("#{}", id)).collect::<Vec<_>>().join("、")
+}
+
+/// 拆出描述末尾的 `due:<时间>` 和 `repeat:<规则>`，无法解析的仍然作为描述的一部分
+fn split_attributes(description: &str) -> (String, Option<Due>, Option<Recurrence>) {
+    let mut description = description.trim_end();
+    let mut due = None;
+    let mut recurrence = None;
+    
+    loop {
+        let (rest, word) = description.rsplit_once(char::is_whitespace).unwrap_or(("", description));
+        if let (None, Some(Ok(parsed))) = (due, word.strip_prefix("due:").map(str::parse::<Due>)) {
+            due = Some(parsed);
+        } else if let (None, Some(Ok(parsed))) = (&recurrence, word.strip_prefix("repeat:").map(str::parse::<Recurrence>)) {
+            recurrence = Some(parsed);
+        } else {
+            break;
+        }
+        description = rest.trim_end();
+    }
+    
+    (description.to_string(), due, recurrence)
 }
 
-impl<'a> From<TodosData> for TodosData {
-    fn from(data: TodosData) -> Self {
-        data
+fn count_tags<'a>(todos: impl Iterator<Item = &'a Todo>) -> BTreeMap<&'a str, (usize, usize)> {
+    let mut counts: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+    
+    for todo in todos {
+        for tag in &todo.tags {
+            let (total, completed) = counts.entry(tag.as_str()).or_default();
+            *total += 1;
+            if todo.completed {
+                *completed += 1;
+            }
+        }
     }
+    
+    counts
+}
+
+/// 未完成的在前，再按优先级从高到低，最后按 ID
+fn compare_by_priority(a: &Todo, b: &Todo) -> Ordering {
+    // 先按完成状态排序（未完成的在前）
+    match a.completed.cmp(&b.completed) {
+        Ordering::Equal => {
+            // 再按优先级排序（高优先级在前）
+            match (&b.priority, &a.priority) {
+                (Priority::Urgent, _) if !matches!(a.priority, Priority::Urgent) => Ordering::Greater,
+                (_, Priority::Urgent) if !matches!(b.priority, Priority::Urgent) => Ordering::Less,
+                (Priority::High, _) if matches!(a.priority, Priority::Medium | Priority::Low) => Ordering::Greater,
+                (_, Priority::High) if matches!(b.priority, Priority::Medium | Priority::Low) => Ordering::Less,
+                (Priority::Medium, Priority::Low) => Ordering::Greater,
+                (Priority::Low, Priority::Medium) => Ordering::Less,
+                _ => a.id.cmp(&b.id), // 最后按ID排序
+            }
+        }
+        other => other,
+    }
+}
+
+/// 未完成的在前，再按截止时间从早到晚（没有截止时间的在最后），相同时按优先级
+fn compare_by_due(a: &Todo, b: &Todo) -> Ordering {
+    a.completed
+        .cmp(&b.completed)
+        .then_with(|| match (&a.due, &b.due) {
+            (Some(x), Some(y)) => x.at.cmp(&y.at),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        })
+        .then_with(|| compare_by_priority(a, b))
 }
 
 fn get_current_time() -> String {
@@ -496,6 +1215,46 @@ mod tests {
         assert_eq!(format!("{}", Priority::Urgent), "🔴 紧急");
     }
     
+    #[test]
+    fn test_due_filters_and_sorting() {
+        use chrono::TimeZone;
+        
+        let now = Local.with_ymd_and_hms(2026, 10, 16, 10, 30, 0).unwrap();
+        let due = |text: &str| Some(Due::parse(text, now).unwrap());
+        
+        let mut manager = TodoManager::new();
+        let yesterday = manager.add_todo("昨天".to_string(), Priority::Low);
+        let tonight = manager.add_todo("今晚".to_string(), Priority::Low);
+        let morning = manager.add_todo("今早".to_string(), Priority::High);
+        let friday = manager.add_todo("下周五".to_string(), Priority::Medium);
+        let later = manager.add_todo("下个月".to_string(), Priority::Urgent);
+        let done = manager.add_todo("已完成".to_string(), Priority::Low);
+        manager.add_todo("没有截止时间".to_string(), Priority::Urgent);
+        
+        manager.set_due(yesterday, due("2026-10-15"));
+        manager.set_due(tonight, due("today 20:00"));
+        manager.set_due(morning, due("09:00"));
+        manager.set_due(friday, due("fri"));
+        manager.set_due(later, due("2026-11-20"));
+        manager.set_due(done, due("2026-10-01"));
+        manager.complete_todo(done);
+        assert!(!manager.set_due(999, None));
+        
+        let ids = |todos: Vec<&Todo>| todos.iter().map(|todo| todo.id).collect::<Vec<_>>();
+        assert_eq!(ids(manager.todos_by_due(DueFilter::Overdue, now)), [yesterday, morning]);
+        assert_eq!(ids(manager.todos_by_due(DueFilter::Today, now)), [morning, tonight]);
+        assert_eq!(ids(manager.todos_by_due(DueFilter::Upcoming(7), now)), [friday]);
+        assert_eq!(ids(manager.todos_by_due(DueFilter::Upcoming(60), now)), [friday, later]);
+        
+        // 已逾期的也会提醒，窗口之外和已完成的不会
+        let window = due::parse_duration("1d").unwrap();
+        assert_eq!(ids(manager.due_within(now.timestamp(), window)), [yesterday, morning, tonight]);
+        
+        let mut todos = manager.get_all_todos();
+        todos.sort_by(|a, b| compare_by_due(a, b));
+        assert_eq!(ids(todos), [yesterday, morning, tonight, friday, later, 7, done]);
+    }
+    
     #[test]
     fn test_import_export() {
         let mut manager = TodoManager::new();
@@ -511,4 +1270,230 @@ mod tests {
         assert!(exported.contains("简单任务"));
         assert!(exported.contains("普通任务"));
     }
+    
+    #[test]
+    fn test_tags() {
+        let mut manager = TodoManager::new();
+        let call = manager.add_todo("给妈妈打电话 +家庭 @phone".to_string(), Priority::High);
+        let report = manager.add_todo("写周报 +work".to_string(), Priority::Medium);
+        let meeting = manager.add_todo("约会议室 +工作 @phone".to_string(), Priority::Low);
+        
+        assert_eq!(manager.get_todo(call).unwrap().description, "给妈妈打电话");
+        assert!(manager.get_todo(call).unwrap().to_string().contains("给妈妈打电话 +家庭 @phone"));
+        
+        assert!(manager.add_tags(report, &["@office".to_string()]));
+        assert!(manager.remove_tags(call, &["+家庭".to_string()]));
+        assert!(!manager.add_tags(999, &[]));
+        manager.edit_todo(call, "给爸妈打电话 +家庭".to_string());
+        assert_eq!(manager.get_todo(call).unwrap().tags, ["+家庭", "@phone"].map(String::from).into());
+        
+        // 合并 +工作 和 +work，已有目标标签的不会重复
+        manager.add_tags(report, &["+工作".to_string()]);
+        assert_eq!(manager.merge_tags(&["+工作".to_string(), "+work".to_string()], "+job"), 2);
+        assert_eq!(manager.get_todo(report).unwrap().tags, ["+job", "@office"].map(String::from).into());
+        
+        manager.complete_todo(meeting);
+        let counts = manager.tag_counts();
+        assert_eq!(counts["@phone"], (2, 1));
+        assert_eq!(counts["+job"], (2, 1));
+        assert!(!counts.contains_key("+work"));
+        
+        let query: Query = "+job|+家庭 -@office".parse().unwrap();
+        let ids: Vec<usize> = manager.query(&query).into_iter().map(|todo| todo.id).collect();
+        assert_eq!(ids, [call, meeting]);
+        
+        let mut imported = TodoManager::new();
+        imported.import_from_text(&manager.export_to_text());
+        assert_eq!(imported.get_todo(report).unwrap().tags, manager.get_todo(report).unwrap().tags);
+        assert_eq!(imported.get_todo(report).unwrap().description, "写周报");
+    }
+    
+    #[test]
+    fn test_subtasks_and_dependencies() {
+        let mut manager = TodoManager::new();
+        let trip = manager.add_todo("旅行".to_string(), Priority::High);
+        let tickets = manager.add_todo("订票".to_string(), Priority::Medium);
+        let hotel = manager.add_todo("订酒店".to_string(), Priority::Medium);
+        let pack = manager.add_todo("收拾行李".to_string(), Priority::Low);
+        let socks = manager.add_todo("买袜子".to_string(), Priority::Low);
+        
+        manager.set_parent(tickets, Some(trip)).unwrap();
+        manager.set_parent(hotel, Some(trip)).unwrap();
+        manager.set_parent(pack, Some(trip)).unwrap();
+        manager.set_parent(socks, Some(pack)).unwrap();
+        assert!(manager.set_parent(trip, Some(socks)).is_err());
+        assert!(manager.set_parent(trip, Some(trip)).is_err());
+        assert!(manager.set_parent(trip, Some(999)).is_err());
+        
+        assert_eq!(manager.children(trip).iter().map(|todo| todo.id).collect::<Vec<_>>(), [tickets, hotel, pack]);
+        assert_eq!(manager.progress(trip), Some((0, 4)));
+        assert_eq!(manager.progress(socks), None);
+        
+        // 订酒店要等订票，反过来依赖会成环
+        manager.add_dependency(hotel, tickets).unwrap();
+        manager.add_dependency(pack, hotel).unwrap();
+        assert!(manager.add_dependency(tickets, pack).is_err());
+        assert!(manager.add_dependency(tickets, tickets).is_err());
+        assert!(manager.complete_with_next(hotel, false).unwrap_err().contains("#2"));
+        assert!(manager.relations(manager.get_todo(hotel).unwrap()).contains("⛔ 等待 #2"));
+        
+        manager.complete_todo(tickets);
+        manager.complete_todo(hotel);
+        assert_eq!(manager.progress(trip), Some((2, 4)));
+        assert!(manager.complete_with_next(trip, false).unwrap_err().contains("2 个子任务"));
+        
+        manager.complete_with_next(trip, true).unwrap();
+        assert!(manager.get_all_todos().iter().all(|todo| todo.completed));
+        
+        // 删除父任务时子任务上移，依赖一并去掉
+        assert!(manager.remove_todo(pack));
+        assert_eq!(manager.get_todo(socks).unwrap().parent, Some(trip));
+        assert!(manager.remove_dependency(hotel, tickets));
+        assert!(!manager.remove_dependency(hotel, tickets));
+        manager.add_dependency(socks, hotel).unwrap();
+        manager.reopen_todo(socks);
+        assert_eq!(manager.clear_completed(), 3);
+        let socks = manager.get_todo(socks).unwrap();
+        assert_eq!(socks.parent, None);
+        assert!(socks.blocked_by.is_empty());
+    }
+    
+    #[test]
+    fn test_bulk_selection_and_completion() {
+        let mut manager = TodoManager::new();
+        let trip = manager.add_todo("旅行 +假期".to_string(), Priority::High);
+        let tickets = manager.add_todo("订票 +假期".to_string(), Priority::Medium);
+        let hotel = manager.add_todo("订酒店 +假期".to_string(), Priority::Medium);
+        let report = manager.add_todo("写周报".to_string(), Priority::Low);
+        manager.set_parent(tickets, Some(trip)).unwrap();
+        manager.add_dependency(tickets, hotel).unwrap();
+        
+        assert_eq!(manager.select(&"1,3-9".parse().unwrap()), [trip, hotel, report]);
+        assert_eq!("2,7".parse::<Selector>().unwrap().missing_ids(&manager), [7]);
+        assert_eq!(manager.select(&"+假期 -订票".parse().unwrap()), [trip, hotel]);
+        
+        // 父任务排在子任务前、子任务又依赖后面的任务，也能全部完成
+        let results = manager.complete_many(&[trip, tickets, hotel], false);
+        assert!(results.iter().all(|(_, result)| result.is_ok()));
+        assert_eq!(results.iter().map(|(id, _)| *id).collect::<Vec<_>>(), [trip, tickets, hotel]);
+        
+        manager.reopen_todo(trip);
+        manager.reopen_todo(tickets);
+        manager.add_dependency(tickets, report).unwrap();
+        let results = manager.complete_many(&[trip, tickets], false);
+        assert!(results.iter().all(|(_, result)| result.is_err()));
+        assert!(results[1].1.as_ref().unwrap_err().contains("#4"));
+    }
+    
+    #[test]
+    fn test_undo_redo() {
+        let mut manager = TodoManager::new();
+        let report = manager.add_todo("写周报".to_string(), Priority::High);
+        let weekly = manager.add_todo("周会".to_string(), Priority::Medium);
+        manager.set_recurrence(weekly, Some(Recurrence::Daily));
+        let child = manager.add_todo("准备材料".to_string(), Priority::Low);
+        manager.set_parent(child, Some(weekly)).unwrap();
+        
+        // 强制完成会同时完成子任务并生成下一次，一次撤销全部恢复
+        let next = manager.complete_with_next(weekly, true).unwrap().unwrap();
+        assert_eq!(manager.undo(), Ok("完成 #2".to_string()));
+        assert!(manager.get_todo(next).is_none());
+        assert!(!manager.get_todo(child).unwrap().completed);
+        assert_eq!(manager.add_todo("新任务".to_string(), Priority::Low), next);
+        assert!(manager.redo().is_err());
+        
+        manager.clear_all();
+        assert!(manager.get_all_todos().is_empty());
+        assert_eq!(manager.undo(), Ok("清空所有任务".to_string()));
+        assert_eq!(manager.get_all_todos().len(), 4);
+        assert_eq!(manager.get_todo(child).unwrap().parent, Some(weekly));
+        
+        // 删除会调整子任务，撤销后恢复原来的父任务；重做再删除一次
+        assert!(manager.remove_todo(weekly));
+        manager.undo().unwrap();
+        assert_eq!(manager.get_todo(child).unwrap().parent, Some(weekly));
+        assert_eq!(manager.redo(), Ok("删除 #2".to_string()));
+        assert!(manager.get_todo(weekly).is_none());
+        assert_eq!(manager.get_todo(child).unwrap().parent, None);
+        
+        // 嵌套的修改记录为一次，没有变化的操作不记录
+        manager.record("批量".to_string(), |manager| {
+            manager.set_priority(report, Priority::Low);
+            manager.edit_todo(report, "写月报".to_string());
+        });
+        assert!(!manager.set_priority(999, Priority::Low));
+        assert_eq!(manager.undo(), Ok("批量".to_string()));
+        assert_eq!(manager.get_todo(report).unwrap().description, "写周报");
+        assert_eq!(manager.get_todo(report).unwrap().priority, Priority::High);
+        
+        while manager.undo().is_ok() {}
+        assert!(manager.get_all_todos().is_empty());
+        assert_eq!(manager.add_todo("重新开始".to_string(), Priority::Low), 1);
+    }
+    
+    #[test]
+    fn test_load_old_data_file() {
+        // 没有截止时间、重复规则和标签字段的旧格式
+        let json = r#"{
+            "todos": [
+                {"id": 1, "description": "学习Rust", "completed": false, "priority": "High",
+                 "created_at": "1640000000", "completed_at": null}
+            ],
+            "next_id": 2
+        }"#;
+        
+        // 读取旧文件时会在旁边留下备份
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todos.json");
+        std::fs::write(&path, json).unwrap();
+        let data = JsonStorage::new(&path.to_string_lossy()).load_todos().unwrap();
+        assert_eq!(data.next_id, 2);
+        assert_eq!(data.todos[0].description, "学习Rust");
+        assert!(data.todos[0].due.is_none());
+        assert!(data.todos[0].recurrence.is_none());
+        assert!(data.todos[0].tags.is_empty());
+    }
+    
+    #[test]
+    fn test_recurring_completion_and_round_trip() {
+        let mut manager = TodoManager::new();
+        let id = manager.add_todo("倒垃圾".to_string(), Priority::Low);
+        let due: Due = "2000-01-03 08:00".parse().unwrap();
+        manager.set_due(id, Some(due));
+        manager.set_recurrence(id, Some("weekly:mon,thu".parse().unwrap()));
+        
+        let next = manager.complete_with_next(id, false).unwrap().unwrap();
+        assert!(manager.get_todo(id).unwrap().completed);
+        
+        // 逾期很久才完成，下一次落在今天之后，保留原来的时刻和规则
+        let next_todo = manager.get_todo(next).unwrap();
+        assert!(!next_todo.completed);
+        assert_eq!(next_todo.description, "倒垃圾");
+        assert_eq!(next_todo.recurrence, manager.get_todo(id).unwrap().recurrence);
+        let next_due = next_todo.due.unwrap();
+        assert!(next_due.date() > Local::now().date_naive());
+        assert_eq!(next_due.time(), due.time());
+        assert!(next_todo.to_string().contains("🔁 每周一、四"));
+        
+        assert_eq!(manager.complete_with_next(id, false), Ok(None));
+        assert!(manager.complete_with_next(999, false).is_err());
+        assert_eq!(manager.todos.len(), 2);
+        
+        let exported = manager.export_to_text();
+        assert!(exported.contains("[已完成] [低] 倒垃圾 due:2000-01-03T08:00 repeat:weekly:mon,thu\n"));
+        
+        let mut imported = TodoManager::new();
+        assert_eq!(imported.import_from_text(&exported), 2);
+        assert_eq!(imported.import_from_text("[高] 看 due:不是时间 的书 repeat:daily"), 1);
+        assert_eq!(imported.export_to_text().lines().count(), 3);
+        let copy = imported.get_todo(1).unwrap();
+        assert!(copy.completed);
+        assert_eq!(copy.due, Some(due));
+        assert_eq!(copy.recurrence, Some(Recurrence::Weekly(vec![chrono::Weekday::Mon, chrono::Weekday::Thu])));
+        assert_eq!(imported.get_todo(2).unwrap().due, manager.get_todo(next).unwrap().due);
+        
+        let book = imported.get_todo(3).unwrap();
+        assert_eq!(book.description, "看 due:不是时间 的书");
+        assert_eq!(book.recurrence, Some(Recurrence::Daily));
+    }
 }
\ No newline at end of file